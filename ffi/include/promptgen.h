@@ -0,0 +1,91 @@
+/*
+ * promptgen.h - the C API of promptgen-ffi, libpromptgen_ffi.so or .a
+ *
+ * Scripts and sessions are opaque handles, freed with their _free function.
+ * A script keeps its own copy of the text, freed along with the script and
+ * the last session started from it.
+ *
+ * Strings handed out belong to the session and stay valid until it's next
+ * answered or freed. Calls that fail return NULL or a PG_ERR_* status, and
+ * pg_last_error_code and pg_last_error_message tell why. Panics fail with
+ * PG_ERR_PANIC rather than unwinding into C. Handles aren't thread safe, use
+ * each from one thread at a time.
+ */
+
+#ifndef PROMPTGEN_H
+#define PROMPTGEN_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define PG_OK 0
+/* A NULL handle or string, or text that isn't UTF-8 */
+#define PG_ERR_INVALID_ARGUMENT 1
+/* The script doesn't parse, the message starts with line:column */
+#define PG_ERR_SYNTAX 2
+#define PG_ERR_NO_PROMPTS 3
+/* Not one of the responses on offer */
+#define PG_ERR_BAD_RESPONSE 4
+/* The response takes typed in text, see pg_session_answer_text */
+#define PG_ERR_INPUT_REQUIRED 5
+/* The typed in text was turned down, the message says what to retry with */
+#define PG_ERR_INVALID_INPUT 6
+#define PG_ERR_FINISHED 7
+#define PG_ERR_NOT_A_STATEMENT 8
+/* The question takes several answers, see pg_session_answer_many */
+#define PG_ERR_SELECTION_REQUIRED 9
+#define PG_ERR_SELECTION_COUNT 10
+#define PG_ERR_NO_DEFAULT 11
+/* The library panicked, a bug, the message says why */
+#define PG_ERR_PANIC 12
+
+typedef struct PgScript PgScript;
+typedef struct PgSession PgSession;
+
+/* Parses a NUL-terminated script, NULL when it doesn't parse. The text is
+ * copied, so source can be freed right after. */
+PgScript *pg_script_parse(const char *source);
+/* Frees the script, its text going once its sessions are freed too. */
+void pg_script_free(PgScript *script);
+
+/* Starts a conversation at the script's first prompt. */
+PgSession *pg_session_new(const PgScript *script);
+void pg_session_free(PgSession *session);
+
+/* The current prompt's label, NULL when it has none. */
+const char *pg_session_prompt_label(const PgSession *session);
+/* The current prompt's text, or the closing prompt's once finished. */
+const char *pg_session_prompt_text(const PgSession *session);
+/* How many responses are on offer, none once finished or on statements. */
+size_t pg_session_response_count(const PgSession *session);
+/* The response numbered index in the order offered, NULL past the last. */
+const char *pg_session_response_text(const PgSession *session, size_t index);
+/* 1 when the response takes typed in text. */
+int pg_session_response_is_input(const PgSession *session, size_t index);
+/* 1 when the current prompt is a statement, see pg_session_advance. */
+int pg_session_is_statement(const PgSession *session);
+/* 1 once the conversation has ended. */
+int pg_session_is_finished(const PgSession *session);
+
+/* Answers with the response numbered index, PG_OK or a PG_ERR_* status. */
+int pg_session_answer(PgSession *session, size_t index);
+/* Answers a multi-select question with count responses. */
+int pg_session_answer_many(PgSession *session, const size_t *indices, size_t count);
+/* Answers with typed in text, NUL-terminated. */
+int pg_session_answer_text(PgSession *session, const char *text);
+/* Moves on from a statement. */
+int pg_session_advance(PgSession *session);
+
+/* The status of the last call that failed on this thread, PG_OK if none. */
+int pg_last_error_code(void);
+/* Why it failed, NULL if nothing has. Valid until the next failing call. */
+const char *pg_last_error_message(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PROMPTGEN_H */
@@ -0,0 +1,628 @@
+//! A C API over scripts and conversations, declared in `include/promptgen.h`.
+//!
+//! Scripts and sessions are opaque handles freed by their `_free` function.
+//! Strings handed out belong to the session and stay valid until it next
+//! changes, functions that fail return `NULL` or a `PG_ERR_*` status and
+//! leave the reason with `pg_last_error_code` and `pg_last_error_message`.
+//! Panics are caught at the boundary and fail with `PG_ERR_PANIC` rather
+//! than unwinding into C.
+
+use std::{
+    any::Any,
+    cell::RefCell,
+    convert::Infallible,
+    ffi::{c_char, c_int, CStr, CString},
+    fmt::Display,
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+use lexer::parse_located;
+use prompter::{
+    frontend::{apply, Reply},
+    owned::{OwnedPrompter, OwnedScript},
+    script::Script,
+    Prompter, PrompterErr,
+};
+
+pub const PG_OK: c_int = 0;
+pub const PG_ERR_INVALID_ARGUMENT: c_int = 1;
+pub const PG_ERR_SYNTAX: c_int = 2;
+pub const PG_ERR_NO_PROMPTS: c_int = 3;
+pub const PG_ERR_BAD_RESPONSE: c_int = 4;
+pub const PG_ERR_INPUT_REQUIRED: c_int = 5;
+pub const PG_ERR_INVALID_INPUT: c_int = 6;
+pub const PG_ERR_FINISHED: c_int = 7;
+pub const PG_ERR_NOT_A_STATEMENT: c_int = 8;
+pub const PG_ERR_SELECTION_REQUIRED: c_int = 9;
+pub const PG_ERR_SELECTION_COUNT: c_int = 10;
+pub const PG_ERR_NO_DEFAULT: c_int = 11;
+pub const PG_ERR_PANIC: c_int = 12;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<(c_int, CString)>> = const { RefCell::new(None) };
+}
+
+// C strings end at the first NUL, so any inside the text are dropped
+fn c_string(text: &str) -> CString {
+    CString::new(text.replace('\0', "")).expect("NULs are removed")
+}
+
+// Records why a call failed, giving back its status
+fn fail(code: c_int, message: impl Display) -> c_int {
+    let message = c_string(&message.to_string());
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some((code, message)));
+    code
+}
+
+// Runs a call's body, failing with `failed` if it panics
+fn guard<T>(failed: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        fail(PG_ERR_PANIC, panic_message(payload.as_ref()));
+        failed
+    })
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let reason = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str));
+    match reason {
+        Some(reason) => format!("panicked: {reason}"),
+        None => "panicked".to_string(),
+    }
+}
+
+// The script in `source`, or the status and message to fail with
+fn parse(source: &str) -> Result<Script<'_>, (c_int, String)> {
+    let prompts = parse_located(source).map_err(|error| {
+        let (line, column) = error.line_and_column(source);
+        (PG_ERR_SYNTAX, format!("{line}:{column}: {error}"))
+    })?;
+    Script::new(prompts).map_err(|_| (PG_ERR_NO_PROMPTS, "no prompts to start from".to_string()))
+}
+
+fn prompter_status(err: &PrompterErr) -> c_int {
+    match err {
+        PrompterErr::BadResponse => PG_ERR_BAD_RESPONSE,
+        PrompterErr::InputRequired => PG_ERR_INPUT_REQUIRED,
+        PrompterErr::InvalidInput(_) => PG_ERR_INVALID_INPUT,
+        PrompterErr::NoMoreQ => PG_ERR_FINISHED,
+        PrompterErr::NotAStatement => PG_ERR_NOT_A_STATEMENT,
+        PrompterErr::SelectionRequired => PG_ERR_SELECTION_REQUIRED,
+        PrompterErr::SelectionCount(_) => PG_ERR_SELECTION_COUNT,
+        PrompterErr::NoDefault => PG_ERR_NO_DEFAULT,
+    }
+}
+
+/// A parsed script along with its text, shared by the sessions started from
+/// it and freed after the last of them.
+pub struct PgScript {
+    script: OwnedScript,
+}
+
+/// One conversation, with the current prompt's text kept as C strings.
+pub struct PgSession {
+    prompter: OwnedPrompter,
+    label: Option<CString>,
+    text: CString,
+    responses: Vec<(CString, bool)>,
+}
+
+impl PgSession {
+    fn new(prompter: OwnedPrompter) -> Self {
+        let mut session = PgSession {
+            prompter,
+            label: None,
+            text: CString::default(),
+            responses: vec![],
+        };
+        session.render();
+        session
+    }
+
+    fn render(&mut self) {
+        let (label, text, responses) = self.prompter.with(|prompter| {
+            let prompt = prompter.render_next();
+            let responses = match prompter.is_finished() {
+                true => vec![],
+                false => prompt
+                    .responses
+                    .iter()
+                    .map(|response| (c_string(&response.text), response.response.input.is_some()))
+                    .collect(),
+            };
+            (
+                prompt.label.map(c_string),
+                c_string(&prompt.text),
+                responses,
+            )
+        });
+        self.label = label;
+        self.text = text;
+        self.responses = responses;
+    }
+
+    fn is_finished(&self) -> bool {
+        self.prompter.with(|prompter| prompter.is_finished())
+    }
+
+    // Answers the current prompt, keeping it as it was if that fails
+    fn reply(&mut self, reply: impl FnOnce(&Prompter<'_>) -> Result<Reply, c_int>) -> c_int {
+        if self.is_finished() {
+            let err = PrompterErr::NoMoreQ;
+            return fail(prompter_status(&err), err);
+        }
+        let reply = match self.prompter.with(reply) {
+            Ok(reply) => reply,
+            Err(status) => return status,
+        };
+        match self.prompter.update(|prompter| apply(prompter, reply)) {
+            Ok(()) => {
+                self.render();
+                PG_OK
+            }
+            Err(err) => fail(prompter_status(&err), err),
+        }
+    }
+}
+
+// The session behind a handle, or the status to return for `NULL`
+unsafe fn session<'s>(session: *mut PgSession) -> Result<&'s mut PgSession, c_int> {
+    session
+        .as_mut()
+        .ok_or_else(|| fail(PG_ERR_INVALID_ARGUMENT, "the session is NULL"))
+}
+
+unsafe fn text<'t>(text: *const c_char, what: &str) -> Result<&'t str, c_int> {
+    if text.is_null() {
+        return Err(fail(PG_ERR_INVALID_ARGUMENT, format!("{what} is NULL")));
+    }
+    CStr::from_ptr(text)
+        .to_str()
+        .map_err(|_| fail(PG_ERR_INVALID_ARGUMENT, format!("{what} isn't UTF-8")))
+}
+
+/// Parses a NUL-terminated script, `NULL` when it doesn't parse. The text
+/// is copied, so `source` can be freed right after.
+///
+/// # Safety
+/// `source` is `NULL` or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pg_script_parse(source: *const c_char) -> *mut PgScript {
+    guard(ptr::null_mut(), || {
+        let Ok(source) = text(source, "the source") else {
+            return ptr::null_mut();
+        };
+        match OwnedScript::new(source, parse) {
+            Ok(script) => Box::into_raw(Box::new(PgScript { script })),
+            Err((code, message)) => {
+                fail(code, message);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Frees the script, its text going once its sessions are freed too.
+///
+/// # Safety
+/// `script` is `NULL` or from `pg_script_parse`, and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pg_script_free(script: *mut PgScript) {
+    guard((), || {
+        if !script.is_null() {
+            drop(Box::from_raw(script));
+        }
+    })
+}
+
+/// Starts a conversation at the script's first prompt, `NULL` when `script`
+/// is.
+///
+/// # Safety
+/// `script` is `NULL` or from `pg_script_parse`, and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pg_session_new(script: *const PgScript) -> *mut PgSession {
+    guard(ptr::null_mut(), || {
+        let Some(script) = script.as_ref() else {
+            fail(PG_ERR_INVALID_ARGUMENT, "the script is NULL");
+            return ptr::null_mut();
+        };
+        let prompter = script
+            .script
+            .start_with(|script| Ok::<_, Infallible>(Prompter::from_script(script)));
+        match prompter {
+            Ok(prompter) => Box::into_raw(Box::new(PgSession::new(prompter))),
+            Err(never) => match never {},
+        }
+    })
+}
+
+/// # Safety
+/// `session` is `NULL` or from `pg_session_new`, and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pg_session_free(session: *mut PgSession) {
+    guard((), || {
+        if !session.is_null() {
+            drop(Box::from_raw(session));
+        }
+    })
+}
+
+/// The current prompt's label, `NULL` when it has none.
+///
+/// # Safety
+/// `session` is from `pg_session_new`, and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pg_session_prompt_label(session: *const PgSession) -> *const c_char {
+    guard(ptr::null(), || {
+        session
+            .as_ref()
+            .and_then(|session| session.label.as_ref())
+            .map_or(ptr::null(), |label| label.as_ptr())
+    })
+}
+
+/// The current prompt's text with placeholders filled in, or the closing
+/// prompt's once finished.
+///
+/// # Safety
+/// `session` is from `pg_session_new`, and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pg_session_prompt_text(session: *const PgSession) -> *const c_char {
+    guard(ptr::null(), || {
+        session
+            .as_ref()
+            .map_or(ptr::null(), |session| session.text.as_ptr())
+    })
+}
+
+/// How many responses are on offer, none once finished or on statements.
+///
+/// # Safety
+/// `session` is from `pg_session_new`, and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pg_session_response_count(session: *const PgSession) -> usize {
+    guard(0, || {
+        session
+            .as_ref()
+            .map_or(0, |session| session.responses.len())
+    })
+}
+
+/// The text of the response numbered `index` in the order offered, `NULL`
+/// past the last one.
+///
+/// # Safety
+/// `session` is from `pg_session_new`, and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pg_session_response_text(
+    session: *const PgSession,
+    index: usize,
+) -> *const c_char {
+    guard(ptr::null(), || {
+        session
+            .as_ref()
+            .and_then(|session| session.responses.get(index))
+            .map_or(ptr::null(), |(text, _)| text.as_ptr())
+    })
+}
+
+/// 1 when the response numbered `index` takes typed in text, answered with
+/// `pg_session_answer_text`.
+///
+/// # Safety
+/// `session` is from `pg_session_new`, and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pg_session_response_is_input(
+    session: *const PgSession,
+    index: usize,
+) -> c_int {
+    guard(0, || {
+        session
+            .as_ref()
+            .and_then(|session| session.responses.get(index))
+            .map_or(0, |(_, input)| c_int::from(*input))
+    })
+}
+
+/// 1 when the current prompt is a statement, moved on from with
+/// `pg_session_advance`.
+///
+/// # Safety
+/// `session` is from `pg_session_new`, and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pg_session_is_statement(session: *const PgSession) -> c_int {
+    guard(0, || {
+        session.as_ref().map_or(0, |session| {
+            c_int::from(session.prompter.with(|prompter| prompter.is_statement()))
+        })
+    })
+}
+
+/// 1 once the conversation has ended.
+///
+/// # Safety
+/// `session` is from `pg_session_new`, and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pg_session_is_finished(session: *const PgSession) -> c_int {
+    guard(1, || {
+        session
+            .as_ref()
+            .map_or(1, |session| c_int::from(session.is_finished()))
+    })
+}
+
+/// Answers with the response numbered `index`.
+///
+/// # Safety
+/// `session` is from `pg_session_new`, and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pg_session_answer(session: *mut PgSession, index: usize) -> c_int {
+    guard(PG_ERR_PANIC, || {
+        let session = match self::session(session) {
+            Ok(session) => session,
+            Err(status) => return status,
+        };
+        session.reply(|prompter| {
+            let prompt = prompter.render_next();
+            match prompt.responses.get(index) {
+                Some(response) => Ok(Reply::Choose(response.id)),
+                None => Err(fail(PG_ERR_BAD_RESPONSE, PrompterErr::BadResponse)),
+            }
+        })
+    })
+}
+
+/// Answers a multi-select question with the `count` responses numbered in
+/// `indices`.
+///
+/// # Safety
+/// `session` is from `pg_session_new`, and not freed yet. `indices` points
+/// to `count` numbers, or is `NULL` when `count` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn pg_session_answer_many(
+    session: *mut PgSession,
+    indices: *const usize,
+    count: usize,
+) -> c_int {
+    guard(PG_ERR_PANIC, || {
+        let session = match self::session(session) {
+            Ok(session) => session,
+            Err(status) => return status,
+        };
+        let indices = match (indices.is_null(), count) {
+            (_, 0) => &[][..],
+            (true, _) => return fail(PG_ERR_INVALID_ARGUMENT, "the indices are NULL"),
+            (false, _) => slice::from_raw_parts(indices, count),
+        };
+        session.reply(|prompter| {
+            let prompt = prompter.render_next();
+            indices
+                .iter()
+                .map(|&index| prompt.responses.get(index).map(|response| response.id))
+                .collect::<Option<_>>()
+                .map(Reply::ChooseMany)
+                .ok_or_else(|| fail(PG_ERR_BAD_RESPONSE, PrompterErr::BadResponse))
+        })
+    })
+}
+
+/// Answers with typed in text, checked against the input response's rules.
+///
+/// # Safety
+/// `session` is from `pg_session_new`, and not freed yet. `text` is a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pg_session_answer_text(
+    session: *mut PgSession,
+    text: *const c_char,
+) -> c_int {
+    guard(PG_ERR_PANIC, || {
+        let session = match self::session(session) {
+            Ok(session) => session,
+            Err(status) => return status,
+        };
+        let text = match self::text(text, "the text") {
+            Ok(text) => text,
+            Err(status) => return status,
+        };
+        session.reply(|_| Ok(Reply::Type(text.to_string())))
+    })
+}
+
+/// Moves on from a statement.
+///
+/// # Safety
+/// `session` is from `pg_session_new`, and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn pg_session_advance(session: *mut PgSession) -> c_int {
+    guard(PG_ERR_PANIC, || {
+        let session = match self::session(session) {
+            Ok(session) => session,
+            Err(status) => return status,
+        };
+        if session.is_finished() {
+            let err = PrompterErr::NoMoreQ;
+            return fail(prompter_status(&err), err);
+        }
+        match session.prompter.update(|prompter| prompter.advance()) {
+            Ok(()) => {
+                session.render();
+                PG_OK
+            }
+            Err(err) => fail(prompter_status(&err), err),
+        }
+    })
+}
+
+/// The status of the last call that failed on this thread, `PG_OK` when
+/// none has.
+#[no_mangle]
+pub extern "C" fn pg_last_error_code() -> c_int {
+    guard(PG_ERR_PANIC, || {
+        LAST_ERROR.with(|last| last.borrow().as_ref().map_or(PG_OK, |(code, _)| *code))
+    })
+}
+
+/// Why the last call on this thread failed, `NULL` when none has. Valid
+/// until the next call that fails.
+#[no_mangle]
+pub extern "C" fn pg_last_error_message() -> *const c_char {
+    guard(ptr::null(), || {
+        LAST_ERROR.with(|last| {
+            last.borrow()
+                .as_ref()
+                .map_or(ptr::null(), |(_, message)| message.as_ptr())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ffi::{CStr, CString},
+        fs::read_to_string,
+        ptr,
+    };
+
+    use super::*;
+
+    // The C for a Rust type used in the API
+    fn c_type(rust: &str) -> String {
+        if let Some(pointee) = rust.strip_prefix("*const ") {
+            return format!("const {} *", c_type(pointee));
+        }
+        if let Some(pointee) = rust.strip_prefix("*mut ") {
+            return format!("{} *", c_type(pointee));
+        }
+        match rust {
+            "c_int" => "int",
+            "c_char" => "char",
+            "usize" => "size_t",
+            "PgScript" | "PgSession" => rust,
+            _ => panic!("no C type for {rust}"),
+        }
+        .to_string()
+    }
+
+    // A name declared with its C type, keeping `*` next to the name
+    fn c_declare(ty: &str, name: &str) -> String {
+        match ty.ends_with('*') {
+            true => format!("{ty}{name}"),
+            false => format!("{ty} {name}"),
+        }
+    }
+
+    // The header's line for each function and status defined here
+    fn c_declarations(source: &str) -> Vec<String> {
+        let source = &source[..source.find("#[cfg(test)]").unwrap()];
+        let functions = source.split("extern \"C\" fn ").skip(1).map(|rest| {
+            let signature = rest[..rest.find('{').unwrap()]
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            let (name, rest) = signature.split_once('(').unwrap();
+            let (params, ret) = rest.rsplit_once(')').unwrap();
+            let params = params
+                .split(',')
+                .map(str::trim)
+                .filter(|param| !param.is_empty())
+                .map(|param| {
+                    let (name, ty) = param.split_once(": ").unwrap();
+                    c_declare(&c_type(ty), name)
+                })
+                .collect::<Vec<_>>();
+            let params = match params.is_empty() {
+                true => "void".to_string(),
+                false => params.join(", "),
+            };
+            let ret = ret
+                .trim()
+                .strip_prefix("-> ")
+                .map_or("void".to_string(), c_type);
+            format!("{}({params});", c_declare(&ret, name))
+        });
+        let statuses = source.lines().filter_map(|line| {
+            let (name, value) = line.strip_prefix("pub const ")?.split_once(": c_int = ")?;
+            Some(format!("#define {name} {}", value.trim_end_matches(';')))
+        });
+        functions.chain(statuses).collect()
+    }
+
+    // Every function and status defined here is declared the same in the
+    // header
+    #[test]
+    fn header_matches_the_api() {
+        let header = read_to_string("./include/promptgen.h").unwrap();
+        let header = header.split_whitespace().collect::<Vec<_>>().join(" ");
+        let source = read_to_string("./src/lib.rs").unwrap();
+
+        let declarations = c_declarations(&source);
+        assert_eq!(declarations.len(), 30);
+        for declaration in declarations {
+            assert!(
+                header.contains(&declaration),
+                "`{declaration}` isn't in promptgen.h"
+            );
+        }
+    }
+
+    #[test]
+    fn fails_calls_that_panic() {
+        assert_eq!(guard(PG_ERR_PANIC, || panic!("oh no")), PG_ERR_PANIC);
+        assert_eq!(pg_last_error_code(), PG_ERR_PANIC);
+        let message = unsafe { CStr::from_ptr(pg_last_error_message()) };
+        assert_eq!(message.to_str().unwrap(), "panicked: oh no");
+    }
+
+    #[test]
+    fn plays_a_conversation() {
+        let source =
+            CString::new(read_to_string("../prompter/simple_prompt.txt").unwrap()).unwrap();
+        let text = |ptr| unsafe { CStr::from_ptr(ptr).to_str().unwrap() };
+
+        unsafe {
+            let script = pg_script_parse(source.as_ptr());
+            let session = pg_session_new(script);
+            // The session keeps the script's text
+            pg_script_free(script);
+            assert_eq!(text(pg_session_prompt_label(session)), "START");
+            assert_eq!(text(pg_session_prompt_text(session)), "Are you a human?");
+            assert_eq!(pg_session_response_count(session), 2);
+            assert_eq!(text(pg_session_response_text(session, 1)), "No");
+            assert!(pg_session_response_text(session, 2).is_null());
+
+            assert_eq!(pg_session_answer(session, 5), PG_ERR_BAD_RESPONSE);
+            assert_eq!(pg_last_error_code(), PG_ERR_BAD_RESPONSE);
+            assert_eq!(pg_session_advance(session), PG_ERR_NOT_A_STATEMENT);
+            assert_eq!(pg_session_answer(session, 0), PG_OK);
+            assert_eq!(pg_session_is_finished(session), 1);
+            assert_eq!(pg_session_response_count(session), 0);
+            assert_eq!(pg_session_answer(session, 0), PG_ERR_FINISHED);
+            assert_eq!(
+                text(pg_last_error_message()),
+                "the conversation has already ended"
+            );
+
+            pg_session_free(session);
+        }
+    }
+
+    #[test]
+    fn reports_scripts_that_dont_parse() {
+        let source = CString::new("> (A) \"Hi\"\n< oops").unwrap();
+        unsafe {
+            assert!(pg_script_parse(source.as_ptr()).is_null());
+            assert_eq!(pg_last_error_code(), PG_ERR_SYNTAX);
+            let message = CStr::from_ptr(pg_last_error_message()).to_str().unwrap();
+            assert!(message.starts_with("2:"), "{message}");
+
+            assert!(pg_script_parse(ptr::null()).is_null());
+            assert_eq!(pg_last_error_code(), PG_ERR_INVALID_ARGUMENT);
+            assert!(pg_session_new(ptr::null()).is_null());
+        }
+    }
+}
@@ -0,0 +1,99 @@
+/* Plays scripts through the C API, exiting non-zero on the first surprise. */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "promptgen.h"
+
+#define EXPECT(cond)                                                          \
+    do {                                                                      \
+        if (!(cond)) {                                                        \
+            fprintf(stderr, "%s:%d: expected %s\n", __FILE__, __LINE__, #cond); \
+            exit(1);                                                          \
+        }                                                                     \
+    } while (0)
+
+static char *read_file(const char *path) {
+    FILE *file = fopen(path, "rb");
+    EXPECT(file != NULL);
+    fseek(file, 0, SEEK_END);
+    long length = ftell(file);
+    rewind(file);
+
+    char *data = malloc(length + 1);
+    EXPECT(fread(data, 1, length, file) == (size_t)length);
+    data[length] = '\0';
+    fclose(file);
+    return data;
+}
+
+static void plays_simple_prompt(const char *dir) {
+    char path[4096];
+    snprintf(path, sizeof path, "%s/simple_prompt.txt", dir);
+    char *source = read_file(path);
+    PgScript *script = pg_script_parse(source);
+    free(source);
+    EXPECT(script != NULL);
+
+    PgSession *session = pg_session_new(script);
+    EXPECT(strcmp(pg_session_prompt_label(session), "START") == 0);
+    EXPECT(strcmp(pg_session_prompt_text(session), "Are you a human?") == 0);
+    EXPECT(pg_session_response_count(session) == 2);
+    EXPECT(strcmp(pg_session_response_text(session, 1), "No") == 0);
+
+    EXPECT(pg_session_answer(session, 1) == PG_OK);
+    EXPECT(strcmp(pg_session_prompt_text(session), "That's very weird! Care to try again?") == 0);
+    EXPECT(pg_session_answer(session, 3) == PG_ERR_BAD_RESPONSE);
+    EXPECT(pg_last_error_code() == PG_ERR_BAD_RESPONSE);
+    EXPECT(pg_session_answer(session, 0) == PG_OK);
+    EXPECT(pg_session_answer(session, 0) == PG_OK);
+
+    EXPECT(pg_session_is_finished(session));
+    EXPECT(strcmp(pg_session_prompt_text(session), "Nice! Glad to meet you human!") == 0);
+    EXPECT(pg_session_answer(session, 0) == PG_ERR_FINISHED);
+
+    /* Sessions outlive the handle of the script they came from */
+    PgSession *other = pg_session_new(script);
+    pg_script_free(script);
+    EXPECT(pg_session_answer(other, 0) == PG_OK);
+    EXPECT(pg_session_is_finished(other));
+
+    pg_session_free(other);
+    pg_session_free(session);
+}
+
+static void checks_typed_in_text(void) {
+    PgScript *script = pg_script_parse(
+        "> (START) \"How old are you?\"\n"
+        "< (DONE) input age [int 1..120] [retry \"A number please\"]\n"
+        "> (DONE) \"Thanks!\"\n");
+    EXPECT(script != NULL);
+
+    PgSession *session = pg_session_new(script);
+    EXPECT(pg_session_response_is_input(session, 0));
+    EXPECT(pg_session_answer(session, 0) == PG_ERR_INPUT_REQUIRED);
+    EXPECT(pg_session_answer_text(session, "old") == PG_ERR_INVALID_INPUT);
+    EXPECT(strstr(pg_last_error_message(), "A number please") != NULL);
+    EXPECT(pg_session_answer_text(session, "42") == PG_OK);
+    EXPECT(strcmp(pg_session_prompt_text(session), "Thanks!") == 0);
+
+    pg_session_free(session);
+    pg_script_free(script);
+}
+
+static void reports_syntax_errors(void) {
+    EXPECT(pg_script_parse("> (START) \"Hi\"\n< oops\n") == NULL);
+    EXPECT(pg_last_error_code() == PG_ERR_SYNTAX);
+    EXPECT(strncmp(pg_last_error_message(), "2:", 2) == 0);
+    EXPECT(pg_session_new(NULL) == NULL);
+    EXPECT(pg_last_error_code() == PG_ERR_INVALID_ARGUMENT);
+}
+
+int main(int argc, char **argv) {
+    EXPECT(argc == 2);
+    plays_simple_prompt(argv[1]);
+    checks_typed_in_text();
+    reports_syntax_errors();
+    return 0;
+}
@@ -0,0 +1,41 @@
+#![cfg(target_os = "linux")]
+
+use std::{env, path::PathBuf, process::Command};
+
+// Compiles tests/c/play.c against the static library and runs it
+#[test]
+fn plays_conversations_from_c() {
+    // Tests only get the rlib, the static library is built on its own
+    let mut build = Command::new(env!("CARGO"));
+    build.args(["build", "--quiet", "--package", "promptgen-ffi", "--lib"]);
+    if !cfg!(debug_assertions) {
+        build.arg("--release");
+    }
+    assert!(
+        build.status().unwrap().success(),
+        "the library doesn't build"
+    );
+
+    // Integration tests run from target/<profile>/deps, next to the library
+    let exe = env::current_exe().unwrap();
+    let lib_dir = exe.parent().unwrap().parent().unwrap();
+    let out = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("play");
+
+    let status = Command::new(env::var("CC").unwrap_or("cc".to_string()))
+        .args([
+            "-std=c99",
+            "-Wall",
+            "-Werror",
+            "-Iinclude",
+            "tests/c/play.c",
+        ])
+        .arg(lib_dir.join("libpromptgen_ffi.a"))
+        .args(["-lpthread", "-ldl", "-lm", "-o"])
+        .arg(&out)
+        .status()
+        .expect("a C compiler");
+    assert!(status.success(), "play.c doesn't compile");
+
+    let status = Command::new(&out).arg("../prompter").status().unwrap();
+    assert!(status.success(), "play.c failed");
+}
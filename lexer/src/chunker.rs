@@ -1,3 +1,5 @@
+use std::fmt::{self, Display};
+
 use crate::lexer::Token;
 
 #[derive(PartialEq, Eq, Debug)]
@@ -11,6 +13,7 @@ pub(crate) struct Chunk<'a> {
     pub(crate) variant: ChunkVariant,
     pub(crate) text: &'a str,
     pub(crate) label: Option<&'a str>,
+    pub(crate) modifiers: Vec<Token<'a>>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -18,13 +21,23 @@ pub(crate) enum ChunkingError {
     InvalidSyntax,
 }
 
+impl Display for ChunkingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkingError::InvalidSyntax => {
+                write!(f, "expected a `>` question or `<` answer with its text")
+            }
+        }
+    }
+}
+
 type ParsedTextAndLabel<'a> = (usize, Option<&'a str>, &'a str);
 
 /// Parsed prompt part, Label, Text or just Text
 fn parse_label_and_text_greedily<'a>(
     tokens: &[Token<'a>],
 ) -> Result<ParsedTextAndLabel<'a>, ChunkingError> {
-    let first_token = tokens.get(0);
+    let first_token = tokens.first();
     let second_token = tokens.get(1);
 
     match (first_token, second_token) {
@@ -32,54 +45,87 @@ fn parse_label_and_text_greedily<'a>(
             Ok((1, Some(label_text), string_text))
         }
         (Some(Token::StringLiteral(string_text)), _) => Ok((0, None, string_text)),
+        // Free text input stands in for the text
+        (Some(Token::LabelLiteral(label_text)), Some(Token::InputLiteral(_))) => {
+            Ok((1, Some(label_text), ""))
+        }
+        (Some(Token::InputLiteral(_)), _) => Ok((0, None, "")),
         _ => Err(ChunkingError::InvalidSyntax),
     }
 }
 
+/// Blocks trailing the text of a prompt or response, e.g. `{colour = "blue"}`
+/// or `[if score > 3]`, gotos, and free text input in place of the text
+fn parse_modifiers_greedily<'a>(tokens: &[Token<'a>]) -> Vec<Token<'a>> {
+    tokens
+        .iter()
+        .take_while(|token| {
+            matches!(
+                token,
+                Token::BraceLiteral(_)
+                    | Token::BracketLiteral(_)
+                    | Token::InputLiteral(_)
+                    | Token::GotoLiteral(_)
+            )
+        })
+        .cloned()
+        .collect()
+}
+
 pub struct Chunker {
-    scan_position: usize,
+    pub(crate) scan_position: usize,
+    /// Index of the token each chunk starts at.
+    pub(crate) starts: Vec<usize>,
+    /// Index of the token each chunk's modifiers start at.
+    pub(crate) modifier_starts: Vec<usize>,
 }
 
 impl Chunker {
     pub fn new() -> Self {
-        Self { scan_position: 0 }
+        Self {
+            scan_position: 0,
+            starts: vec![],
+            modifier_starts: vec![],
+        }
     }
 
-    pub fn parse_tokens<'a, 'b>(
-        self: &'b mut Self,
+    pub fn parse_tokens<'a>(
+        &mut self,
         tokens: Vec<Token<'a>>,
     ) -> Result<Vec<Chunk<'a>>, ChunkingError> {
         let mut chunks: Vec<Chunk<'a>> = vec![];
 
         loop {
-            match tokens.get(self.scan_position) {
-                Some(Token::RightAngular) => {
-                    let (relative_end_index, label, text) =
-                        parse_label_and_text_greedily(&tokens[(self.scan_position + 1)..])?;
-                    self.scan_position = self.scan_position + relative_end_index + 2; // Because we
-                                                                                      // are starting scanning from a extra one
-                    chunks.push(Chunk {
-                        variant: ChunkVariant::Prompt,
-                        text,
-                        label,
-                    });
-                }
-                Some(Token::LeftAngular) => {
-                    let (relative_end_index, label, text) =
-                        parse_label_and_text_greedily(&tokens[(self.scan_position + 1)..])?;
-                    self.scan_position = self.scan_position + relative_end_index + 2;
-
-                    chunks.push(Chunk {
-                        variant: ChunkVariant::Response,
-                        text,
-                        label,
-                    })
-                }
+            let variant = match tokens.get(self.scan_position) {
+                Some(Token::RightAngular) => ChunkVariant::Prompt,
+                Some(Token::LeftAngular) => ChunkVariant::Response,
                 Some(_) => {
                     return Err(ChunkingError::InvalidSyntax);
                 }
                 None => break, // TODO
-            }
+            };
+            let start = self.scan_position;
+
+            let (relative_end_index, label, text) =
+                parse_label_and_text_greedily(&tokens[(self.scan_position + 1)..])?;
+            // The text comes after the `>` or `<`. Input in place of the text
+            // is kept along with the modifiers, the text proper is skipped.
+            let text_position = self.scan_position + 1 + relative_end_index;
+            self.scan_position = match tokens[text_position] {
+                Token::InputLiteral(_) => text_position,
+                _ => text_position + 1,
+            };
+            let modifiers = parse_modifiers_greedily(&tokens[self.scan_position..]);
+            self.modifier_starts.push(self.scan_position);
+            self.scan_position += modifiers.len();
+
+            chunks.push(Chunk {
+                variant,
+                text,
+                label,
+                modifiers,
+            });
+            self.starts.push(start);
         }
 
         Ok(chunks)
@@ -111,11 +157,81 @@ mod test {
                 variant: ChunkVariant::Prompt,
                 label: None,
                 text: "Hello world",
+                modifiers: vec![],
             },
             Chunk {
                 variant: ChunkVariant::Response,
                 label: Some("LABEL_1"),
                 text: "Hello me",
+                modifiers: vec![],
+            },
+        ];
+
+        assert_eq!(parsing_results, Ok(expect_chunks));
+    }
+
+    // Parse tokens with trailing blocks
+    #[test]
+    fn parse_tokens_with_modifiers() {
+        let input_tokens = vec![
+            Token::LeftAngular,
+            Token::StringLiteral("Blue"),
+            Token::BraceLiteral("colour = \"blue\""),
+            Token::LeftAngular,
+            Token::StringLiteral("Red"),
+        ];
+
+        let mut chunker = Chunker::new();
+        let parsing_results = chunker.parse_tokens(input_tokens);
+
+        let expect_chunks = vec![
+            Chunk {
+                variant: ChunkVariant::Response,
+                label: None,
+                text: "Blue",
+                modifiers: vec![Token::BraceLiteral("colour = \"blue\"")],
+            },
+            Chunk {
+                variant: ChunkVariant::Response,
+                label: None,
+                text: "Red",
+                modifiers: vec![],
+            },
+        ];
+
+        assert_eq!(parsing_results, Ok(expect_chunks));
+    }
+
+    // Parse free text input responses
+    #[test]
+    fn parse_input_tokens() {
+        let input_tokens = vec![
+            Token::LeftAngular,
+            Token::LabelLiteral("NEXT"),
+            Token::InputLiteral("name"),
+            Token::BraceLiteral("asked = true"),
+            Token::LeftAngular,
+            Token::InputLiteral("nickname"),
+        ];
+
+        let mut chunker = Chunker::new();
+        let parsing_results = chunker.parse_tokens(input_tokens);
+
+        let expect_chunks = vec![
+            Chunk {
+                variant: ChunkVariant::Response,
+                label: Some("NEXT"),
+                text: "",
+                modifiers: vec![
+                    Token::InputLiteral("name"),
+                    Token::BraceLiteral("asked = true"),
+                ],
+            },
+            Chunk {
+                variant: ChunkVariant::Response,
+                label: None,
+                text: "",
+                modifiers: vec![Token::InputLiteral("nickname")],
             },
         ];
 
@@ -140,7 +256,7 @@ mod test {
     // Parse prompt tokens
     #[test]
     fn parse_prompt_part_with_label() {
-        let input_tokens = vec![
+        let input_tokens = [
             Token::RightAngular,
             Token::LabelLiteral("LABEL_1"),
             Token::StringLiteral("Hello World"),
@@ -156,7 +272,7 @@ mod test {
     // Parse prompt tokens
     #[test]
     fn parse_prompt_part_without_label() {
-        let input_tokens = vec![
+        let input_tokens = [
             Token::LeftAngular,
             Token::StringLiteral("Hello World"),
             Token::RightAngular,
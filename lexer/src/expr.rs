@@ -0,0 +1,436 @@
+use std::fmt::{self, Display};
+
+/// A value written literally in a script.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Literal<'a> {
+    Str(&'a str),
+    Int(i64),
+    Bool(bool),
+}
+
+/// `name = value`, as found in a response's `{...}` block.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Assignment<'a> {
+    pub name: &'a str,
+    pub value: Literal<'a>,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum BinaryOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+    Add,
+    Sub,
+}
+
+/// An expression over conversation variables, as used by `[if ...]` guards.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Expr<'a> {
+    Literal(Literal<'a>),
+    Variable(&'a str),
+    Not(Box<Expr<'a>>),
+    Binary(BinaryOp, Box<Expr<'a>>, Box<Expr<'a>>),
+}
+
+/// Deepest an expression can nest, so parsing, evaluating and dropping it
+/// can't overflow the stack.
+pub const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ExprError {
+    UnexpectedEnd,
+    UnexpectedToken,
+    UnterminatedStringLiteral,
+    InvalidNumber,
+    TooDeep,
+}
+
+impl Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprError::UnexpectedEnd => write!(f, "block ends unexpectedly"),
+            ExprError::UnexpectedToken => write!(f, "unexpected token in block"),
+            ExprError::UnterminatedStringLiteral => {
+                write!(f, "text in block is missing its closing `\"`")
+            }
+            ExprError::InvalidNumber => write!(f, "invalid number in block"),
+            ExprError::TooDeep => write!(f, "expression nests more than {MAX_DEPTH} deep"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum ExprToken<'a> {
+    Ident(&'a str),
+    Str(&'a str),
+    Int(i64),
+    Symbol(&'static str),
+}
+
+// Longest symbols first so `==` doesn't lex as two `=`
+const SYMBOLS: [&str; 17] = [
+    "==", "!=", "<=", ">=", "&&", "||", "..", "=", ":", ",", "<", ">", "!", "(", ")", "+", "-",
+];
+
+fn is_ident_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+pub(crate) fn tokenize(data: &str) -> Result<Vec<ExprToken<'_>>, ExprError> {
+    let bytes = data.as_bytes();
+    let mut tokens = vec![];
+    let mut idx = 0;
+
+    while let Some(byte) = bytes.get(idx) {
+        if byte.is_ascii_whitespace() {
+            idx += 1;
+        } else if *byte == b'"' {
+            let len = data[idx + 1..]
+                .find('"')
+                .ok_or(ExprError::UnterminatedStringLiteral)?;
+            tokens.push(ExprToken::Str(&data[idx + 1..idx + 1 + len]));
+            idx += len + 2;
+        } else if byte.is_ascii_digit() {
+            let len = bytes[idx..]
+                .iter()
+                .take_while(|b| is_ident_byte(**b))
+                .count();
+            let number = data[idx..idx + len]
+                .parse()
+                .map_err(|_| ExprError::InvalidNumber)?;
+            tokens.push(ExprToken::Int(number));
+            idx += len;
+        } else if is_ident_byte(*byte) {
+            let len = bytes[idx..]
+                .iter()
+                .take_while(|b| is_ident_byte(**b))
+                .count();
+            tokens.push(ExprToken::Ident(&data[idx..idx + len]));
+            idx += len;
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| data[idx..].starts_with(*symbol))
+                .ok_or(ExprError::UnexpectedToken)?;
+            tokens.push(ExprToken::Symbol(symbol));
+            idx += symbol.len();
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Walks a token list, shared by the block parsers.
+pub(crate) struct TokenCursor<'a, 'b> {
+    tokens: &'b [ExprToken<'a>],
+    position: usize,
+    depth: usize,
+}
+
+impl<'a, 'b> TokenCursor<'a, 'b> {
+    pub(crate) fn new(tokens: &'b [ExprToken<'a>]) -> Self {
+        Self {
+            tokens,
+            position: 0,
+            depth: 0,
+        }
+    }
+
+    pub(crate) fn peek(&self) -> Option<ExprToken<'a>> {
+        self.tokens.get(self.position).copied()
+    }
+
+    pub(crate) fn peek_nth(&self, n: usize) -> Option<ExprToken<'a>> {
+        self.tokens.get(self.position + n).copied()
+    }
+
+    pub(crate) fn next(&mut self) -> Result<ExprToken<'a>, ExprError> {
+        let token = self.peek().ok_or(ExprError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    pub(crate) fn eat(&mut self, symbol: &str) -> bool {
+        match self.peek() {
+            Some(ExprToken::Symbol(s)) if s == symbol => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn ident(&mut self) -> Result<&'a str, ExprError> {
+        match self.next()? {
+            ExprToken::Ident(name) => Ok(name),
+            _ => Err(ExprError::UnexpectedToken),
+        }
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    // Runs `parse` a level further in, failing past `MAX_DEPTH`
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ExprError>,
+    ) -> Result<T, ExprError> {
+        if self.depth == MAX_DEPTH {
+            return Err(ExprError::TooDeep);
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+}
+
+pub(crate) fn parse_literal<'a>(
+    cursor: &mut TokenCursor<'a, '_>,
+) -> Result<Literal<'a>, ExprError> {
+    match cursor.next()? {
+        ExprToken::Symbol("-") => match cursor.next()? {
+            ExprToken::Int(n) => Ok(Literal::Int(-n)),
+            _ => Err(ExprError::UnexpectedToken),
+        },
+        ExprToken::Str(s) => Ok(Literal::Str(s)),
+        ExprToken::Int(n) => Ok(Literal::Int(n)),
+        ExprToken::Ident("true") => Ok(Literal::Bool(true)),
+        ExprToken::Ident("false") => Ok(Literal::Bool(false)),
+        _ => Err(ExprError::UnexpectedToken),
+    }
+}
+
+/// Parses a comma separated list of assignments, e.g.
+/// `colour = "blue", likes_cake = true`
+pub(crate) fn parse_assignments(data: &str) -> Result<Vec<Assignment<'_>>, ExprError> {
+    let tokens = tokenize(data)?;
+    let mut cursor = TokenCursor::new(&tokens);
+    let mut assignments = vec![];
+
+    while !cursor.is_done() {
+        let name = cursor.ident()?;
+        if !cursor.eat("=") {
+            return Err(ExprError::UnexpectedToken);
+        }
+        let value = parse_literal(&mut cursor)?;
+        assignments.push(Assignment { name, value });
+
+        if !cursor.eat(",") && !cursor.is_done() {
+            return Err(ExprError::UnexpectedToken);
+        }
+    }
+
+    Ok(assignments)
+}
+
+// Operators from loosest to tightest binding
+const PRECEDENCE: [&[(&str, BinaryOp)]; 4] = [
+    &[("||", BinaryOp::Or)],
+    &[("&&", BinaryOp::And)],
+    &[
+        ("==", BinaryOp::Eq),
+        ("!=", BinaryOp::Ne),
+        ("<=", BinaryOp::Le),
+        (">=", BinaryOp::Ge),
+        ("<", BinaryOp::Lt),
+        (">", BinaryOp::Gt),
+    ],
+    &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+];
+
+// The depth of an expression built on one `depth` deep
+fn deeper(depth: usize) -> Result<usize, ExprError> {
+    match depth < MAX_DEPTH {
+        true => Ok(depth + 1),
+        false => Err(ExprError::TooDeep),
+    }
+}
+
+// Parsers give back how deep the expression nests along with it, chains of
+// operators nest without the parser going any deeper
+fn parse_binary<'a>(
+    cursor: &mut TokenCursor<'a, '_>,
+    level: usize,
+) -> Result<(Expr<'a>, usize), ExprError> {
+    let Some(operators) = PRECEDENCE.get(level) else {
+        return parse_unary(cursor);
+    };
+
+    let (mut lhs, mut depth) = parse_binary(cursor, level + 1)?;
+    while let Some((_, op)) = operators.iter().find(|(symbol, _)| cursor.eat(symbol)) {
+        let (rhs, rhs_depth) = parse_binary(cursor, level + 1)?;
+        depth = deeper(depth.max(rhs_depth))?;
+        lhs = Expr::Binary(*op, Box::new(lhs), Box::new(rhs));
+    }
+
+    Ok((lhs, depth))
+}
+
+fn parse_unary<'a>(cursor: &mut TokenCursor<'a, '_>) -> Result<(Expr<'a>, usize), ExprError> {
+    if cursor.eat("!") {
+        let (expr, depth) = cursor.nested(parse_unary)?;
+        return Ok((Expr::Not(Box::new(expr)), deeper(depth)?));
+    }
+    if cursor.eat("(") {
+        let expr = cursor.nested(|cursor| parse_binary(cursor, 0))?;
+        return match cursor.eat(")") {
+            true => Ok(expr),
+            false => Err(ExprError::UnexpectedToken),
+        };
+    }
+
+    let expr = match cursor.peek() {
+        Some(ExprToken::Ident(name)) if name != "true" && name != "false" => {
+            cursor.next()?;
+            Expr::Variable(name)
+        }
+        _ => Expr::Literal(parse_literal(cursor)?),
+    };
+    Ok((expr, 1))
+}
+
+/// Parses a whole expression, e.g. `score > 3 && colour == "blue"`
+pub(crate) fn parse_expr(data: &str) -> Result<Expr<'_>, ExprError> {
+    let tokens = tokenize(data)?;
+    let mut cursor = TokenCursor::new(&tokens);
+    let (expr, _) = parse_binary(&mut cursor, 0)?;
+
+    match cursor.is_done() {
+        true => Ok(expr),
+        false => Err(ExprError::UnexpectedToken),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        parse_assignments, parse_expr, tokenize, Assignment, BinaryOp, Expr, ExprError, ExprToken,
+        Literal, MAX_DEPTH,
+    };
+
+    #[test]
+    fn tokenize_a_block() {
+        let result = tokenize("colour = \"dark blue\", age=42");
+        let expected_tokens = vec![
+            ExprToken::Ident("colour"),
+            ExprToken::Symbol("="),
+            ExprToken::Str("dark blue"),
+            ExprToken::Symbol(","),
+            ExprToken::Ident("age"),
+            ExprToken::Symbol("="),
+            ExprToken::Int(42),
+        ];
+
+        assert_eq!(result, Ok(expected_tokens));
+    }
+
+    #[test]
+    fn parse_typed_assignments() {
+        let result = parse_assignments("colour = \"blue\", age = 7, likes_cake = true");
+        let expected_assignments = vec![
+            Assignment {
+                name: "colour",
+                value: Literal::Str("blue"),
+            },
+            Assignment {
+                name: "age",
+                value: Literal::Int(7),
+            },
+            Assignment {
+                name: "likes_cake",
+                value: Literal::Bool(true),
+            },
+        ];
+
+        assert_eq!(result, Ok(expected_assignments));
+    }
+
+    #[test]
+    fn fail_to_parse_bad_assignments() {
+        assert_eq!(
+            parse_assignments("colour \"blue\""),
+            Err(ExprError::UnexpectedToken)
+        );
+        assert_eq!(parse_assignments("colour ="), Err(ExprError::UnexpectedEnd));
+        assert_eq!(
+            parse_assignments("a = 1 b = 2"),
+            Err(ExprError::UnexpectedToken)
+        );
+        assert_eq!(parse_assignments("a = 1x"), Err(ExprError::InvalidNumber));
+    }
+
+    #[test]
+    fn parse_an_expression_with_precedence() {
+        let result = parse_expr("score > 3 && !(colour == \"blue\") || bonus");
+
+        let score = Expr::Binary(
+            BinaryOp::Gt,
+            Box::new(Expr::Variable("score")),
+            Box::new(Expr::Literal(Literal::Int(3))),
+        );
+        let colour = Expr::Not(Box::new(Expr::Binary(
+            BinaryOp::Eq,
+            Box::new(Expr::Variable("colour")),
+            Box::new(Expr::Literal(Literal::Str("blue"))),
+        )));
+        let expected_expr = Expr::Binary(
+            BinaryOp::Or,
+            Box::new(Expr::Binary(
+                BinaryOp::And,
+                Box::new(score),
+                Box::new(colour),
+            )),
+            Box::new(Expr::Variable("bonus")),
+        );
+
+        assert_eq!(result, Ok(expected_expr));
+    }
+
+    #[test]
+    fn parse_arithmetic_and_negative_literals() {
+        let result = parse_expr("a - 1 >= -2");
+        let expected_expr = Expr::Binary(
+            BinaryOp::Ge,
+            Box::new(Expr::Binary(
+                BinaryOp::Sub,
+                Box::new(Expr::Variable("a")),
+                Box::new(Expr::Literal(Literal::Int(1))),
+            )),
+            Box::new(Expr::Literal(Literal::Int(-2))),
+        );
+
+        assert_eq!(result, Ok(expected_expr));
+    }
+
+    #[test]
+    fn fail_to_parse_bad_expressions() {
+        assert_eq!(parse_expr("score >"), Err(ExprError::UnexpectedEnd));
+        assert_eq!(parse_expr("(score > 1"), Err(ExprError::UnexpectedToken));
+        assert_eq!(parse_expr("score 1"), Err(ExprError::UnexpectedToken));
+        assert_eq!(parse_expr("score = 1"), Err(ExprError::UnexpectedToken));
+    }
+
+    #[test]
+    fn fail_to_parse_expressions_nested_too_deep() {
+        let nots = |count| format!("{}x", "!".repeat(count));
+        assert!(parse_expr(&nots(MAX_DEPTH - 1)).is_ok());
+        assert_eq!(parse_expr(&nots(MAX_DEPTH)), Err(ExprError::TooDeep));
+        assert_eq!(parse_expr(&nots(200_000)), Err(ExprError::TooDeep));
+
+        let parens = |count| format!("{}x{}", "(".repeat(count), ")".repeat(count));
+        assert!(parse_expr(&parens(MAX_DEPTH)).is_ok());
+        assert_eq!(parse_expr(&parens(200_000)), Err(ExprError::TooDeep));
+
+        let sums = |count| format!("x{}", " + 1".repeat(count));
+        assert!(parse_expr(&sums(MAX_DEPTH - 1)).is_ok());
+        assert_eq!(parse_expr(&sums(200_000)), Err(ExprError::TooDeep));
+    }
+}
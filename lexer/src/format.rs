@@ -0,0 +1,102 @@
+use std::iter;
+
+use crate::{
+    chunker::{ChunkVariant, Chunker},
+    lexer::{Lexxer, Token},
+    parse_located, SyntaxError,
+};
+
+fn write_modifier(out: &mut String, token: &Token) {
+    match token {
+        Token::BraceLiteral(block) => *out += &format!(" {{{}}}", block.trim()),
+        Token::BracketLiteral(block) => *out += &format!(" [{}]", block.trim()),
+        Token::InputLiteral(variable) => *out += &format!(" input {variable}"),
+        Token::GotoLiteral(None) => *out += " ->",
+        Token::GotoLiteral(Some(label)) => *out += &format!(" -> {label}"),
+        _ => unreachable!("only blocks, inputs and gotos trail a text"),
+    }
+}
+
+/// Rewrites a script in canonical style: one question or answer per line,
+/// single spaces between its parts and blocks trimmed inside. Text is kept as
+/// is. Scripts that don't parse, or have stray text the parser would skip
+/// over, are left alone.
+pub fn format(data: &str) -> Result<String, SyntaxError> {
+    parse_located(data)?;
+
+    let mut lexer = Lexxer::new();
+    let tokens = lexer.parse(data).expect("the script parses");
+    let mut end = 0;
+    let ending = data.len()..data.len();
+    for span in lexer.spans.iter().cloned().chain(iter::once(ending)) {
+        if let Some(stray) = data[end..span.start].find(|c: char| !c.is_whitespace()) {
+            return Err(SyntaxError {
+                message: "stray text that isn't part of a question or answer".to_string(),
+                offset: end + stray,
+            });
+        }
+        end = span.end;
+    }
+
+    let chunks = Chunker::new()
+        .parse_tokens(tokens)
+        .expect("the script parses");
+    let mut out = String::new();
+
+    for chunk in chunks {
+        out += match chunk.variant {
+            ChunkVariant::Prompt => ">",
+            ChunkVariant::Response => "<",
+        };
+        if let Some(label) = chunk.label {
+            out += &format!(" ({label})");
+        }
+        // Input stands in for the text, and comes first of the modifiers
+        if !matches!(chunk.modifiers.first(), Some(Token::InputLiteral(_))) {
+            out += &format!(" \"{}\"", chunk.text);
+        }
+        for modifier in &chunk.modifiers {
+            write_modifier(&mut out, modifier);
+        }
+        out.push('\n');
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::read_to_string;
+
+    use super::format;
+    use crate::parse;
+
+    #[test]
+    fn format_into_canonical_style() {
+        let data = "  >(START)   \"Hi\"\n\n<(NEXT)\"Hello\"{ name = \"x\" }   [ if true ]\n\
+                    <  input name [ int 1..3]\n>(NEXT) \"Hmm\"->   START\n";
+        assert_eq!(
+            format(data).unwrap(),
+            "> (START) \"Hi\"\n\
+             < (NEXT) \"Hello\" {name = \"x\"} [if true]\n\
+             < input name [int 1..3]\n\
+             > (NEXT) \"Hmm\" -> START\n"
+        );
+    }
+
+    #[test]
+    fn format_keeps_meaning() {
+        let data = read_to_string("./sample_prompts.txt").unwrap();
+        let formatted = format(&data).unwrap();
+
+        assert_eq!(parse(&formatted).unwrap(), parse(&data).unwrap());
+        assert_eq!(format(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn format_refuses_stray_text() {
+        let err = format("> \"Hi\"\n# not a comment\n< \"Hello\"").unwrap_err();
+        assert_eq!(err.offset, 7);
+        assert!(format("> \"Hi\n").is_err());
+    }
+}
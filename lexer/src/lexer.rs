@@ -1,3 +1,8 @@
+use std::{
+    fmt::{self, Display},
+    ops::Range,
+};
+
 #[derive(Debug, PartialEq, Eq)]
 struct StringyParseResult<'a> {
     relative_end_index: usize,
@@ -6,21 +11,41 @@ struct StringyParseResult<'a> {
 
 #[derive(Debug, PartialEq, Eq)]
 pub(crate) enum LexxerError {
-    InvalidSyntax,
     UnterminatedLabelLiteral,
     UnterminatedStringLiteral,
+    UnterminatedBlockLiteral,
     InvalidLabelCharacter,
+    InvalidInputName,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+impl Display for LexxerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexxerError::UnterminatedLabelLiteral => write!(f, "label is missing its `)`"),
+            LexxerError::UnterminatedStringLiteral => write!(f, "text is missing its closing `\"`"),
+            LexxerError::UnterminatedBlockLiteral => {
+                write!(f, "block is missing its closing bracket")
+            }
+            LexxerError::InvalidLabelCharacter => write!(f, "labels can't contain whitespace"),
+            LexxerError::InvalidInputName => write!(f, "`input` needs a variable name"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub(crate) enum Token<'a> {
     RightAngular,
     LeftAngular,
     StringLiteral(&'a str),
     LabelLiteral(&'a str),
+    BraceLiteral(&'a str),
+    BracketLiteral(&'a str),
+    InputLiteral(&'a str),
+    /// `->` on its own moves on to the next prompt, `-> LABEL` to the label.
+    GotoLiteral(Option<&'a str>),
 }
 
-fn parse_label_block_greedily<'a>(data: &'a str) -> Result<StringyParseResult, LexxerError> {
+fn parse_label_block_greedily(data: &str) -> Result<StringyParseResult<'_>, LexxerError> {
     let mut idx: usize = 1;
 
     loop {
@@ -32,7 +57,7 @@ fn parse_label_block_greedily<'a>(data: &'a str) -> Result<StringyParseResult, L
             Some(x) if *x == ")".as_bytes()[0] => break,
             _ => (),
         }
-        idx = idx + 1;
+        idx += 1;
     }
 
     Ok(StringyParseResult {
@@ -41,7 +66,7 @@ fn parse_label_block_greedily<'a>(data: &'a str) -> Result<StringyParseResult, L
     })
 }
 
-fn parse_string_literal_greedily<'a>(data: &'a str) -> Result<StringyParseResult, LexxerError> {
+fn parse_string_literal_greedily(data: &str) -> Result<StringyParseResult<'_>, LexxerError> {
     let mut idx: usize = 1;
 
     loop {
@@ -52,7 +77,36 @@ fn parse_string_literal_greedily<'a>(data: &'a str) -> Result<StringyParseResult
             _ => (),
         }
 
-        idx = idx + 1;
+        idx += 1;
+    }
+
+    Ok(StringyParseResult {
+        relative_end_index: idx,
+        data: &data[1..idx],
+    })
+}
+
+// Blocks run up to the closing character, skipping over any string literals
+// inside them so those can contain the closing character too.
+fn parse_block_greedily(data: &str, close: u8) -> Result<StringyParseResult<'_>, LexxerError> {
+    let mut idx: usize = 1;
+
+    loop {
+        let d = data.as_bytes().get(idx);
+        match d {
+            None => return Err(LexxerError::UnterminatedBlockLiteral),
+            Some(x) if *x == close => break,
+            Some(x) if *x == "\"".as_bytes()[0] => {
+                let StringyParseResult {
+                    relative_end_index, ..
+                } = parse_string_literal_greedily(&data[idx..])
+                    .map_err(|_| LexxerError::UnterminatedBlockLiteral)?;
+                idx += relative_end_index;
+            }
+            _ => (),
+        }
+
+        idx += 1;
     }
 
     Ok(StringyParseResult {
@@ -61,20 +115,100 @@ fn parse_string_literal_greedily<'a>(data: &'a str) -> Result<StringyParseResult
     })
 }
 
+const INPUT_KEYWORD: &str = "input";
+
+fn is_word_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+// `input` is the only bare word the lexer cares about, only as a whole word
+// followed by a space. Compared as bytes, `position` can be in the middle of
+// a character
+fn starts_input_keyword(data: &str, position: usize) -> bool {
+    let bytes = data.as_bytes();
+    let follows_word = position > 0 && is_word_byte(bytes[position - 1]);
+    let next = bytes.get(position + INPUT_KEYWORD.len());
+
+    !follows_word
+        && bytes[position..].starts_with(INPUT_KEYWORD.as_bytes())
+        && matches!(next, Some(b' ' | b'\t'))
+}
+
+// `input name`, the name ends the token
+fn parse_input_greedily(data: &str) -> Result<StringyParseResult<'_>, LexxerError> {
+    let mut idx = INPUT_KEYWORD.len();
+    while let Some(b' ' | b'\t') = data.as_bytes().get(idx) {
+        idx += 1;
+    }
+
+    let start = idx;
+    while data.as_bytes().get(idx).is_some_and(|x| is_word_byte(*x)) {
+        idx += 1;
+    }
+
+    if start == idx {
+        return Err(LexxerError::InvalidInputName);
+    }
+
+    Ok(StringyParseResult {
+        relative_end_index: idx - 1,
+        data: &data[start..idx],
+    })
+}
+
+const GOTO_ARROW: &str = "->";
+
+// `->` and the label after it on the same line, if any
+fn parse_goto_greedily(data: &str) -> StringyParseResult<'_> {
+    let bytes = data.as_bytes();
+    let mut idx = GOTO_ARROW.len();
+    while let Some(b' ' | b'\t') = bytes.get(idx) {
+        idx += 1;
+    }
+
+    let start = idx;
+    while bytes
+        .get(idx)
+        .is_some_and(|x| !x.is_ascii_whitespace() && !b"[{".contains(x))
+    {
+        idx += 1;
+    }
+
+    StringyParseResult {
+        relative_end_index: idx - 1,
+        data: &data[start..idx],
+    }
+}
+
 pub(crate) struct Lexxer {
-    scan_position: usize,
+    pub(crate) scan_position: usize,
+    /// Bytes each token spans.
+    pub(crate) spans: Vec<Range<usize>>,
 }
 
 impl Lexxer {
     pub fn new() -> Self {
-        Lexxer { scan_position: 0 }
+        Lexxer {
+            scan_position: 0,
+            spans: vec![],
+        }
     }
 
-    pub fn parse<'a, 'b>(self: &'b mut Self, data: &'a str) -> Result<Vec<Token<'a>>, LexxerError> {
+    pub fn parse<'a>(&mut self, data: &'a str) -> Result<Vec<Token<'a>>, LexxerError> {
         let mut result: Vec<Token<'a>> = vec![];
 
         while let Some(char) = data.as_bytes().get(self.scan_position) {
-            if *char == ">".as_bytes()[0] {
+            let start = self.scan_position;
+            let count = result.len();
+
+            if data.as_bytes()[self.scan_position..].starts_with(GOTO_ARROW.as_bytes()) {
+                let StringyParseResult {
+                    relative_end_index,
+                    data,
+                } = parse_goto_greedily(&data[self.scan_position..]);
+                self.scan_position += relative_end_index;
+                result.push(Token::GotoLiteral(Some(data).filter(|d| !d.is_empty())))
+            } else if *char == ">".as_bytes()[0] {
                 result.push(Token::RightAngular);
             } else if *char == "<".as_bytes()[0] {
                 result.push(Token::LeftAngular);
@@ -83,17 +217,41 @@ impl Lexxer {
                     relative_end_index,
                     data,
                 } = parse_string_literal_greedily(&data[self.scan_position..])?;
-                self.scan_position = self.scan_position + relative_end_index;
+                self.scan_position += relative_end_index;
                 result.push(Token::StringLiteral(data))
             } else if *char == "(".as_bytes()[0] {
                 let StringyParseResult {
                     relative_end_index,
                     data,
                 } = parse_label_block_greedily(&data[self.scan_position..])?;
-                self.scan_position = self.scan_position + relative_end_index;
+                self.scan_position += relative_end_index;
                 result.push(Token::LabelLiteral(data))
+            } else if *char == "{".as_bytes()[0] {
+                let StringyParseResult {
+                    relative_end_index,
+                    data,
+                } = parse_block_greedily(&data[self.scan_position..], "}".as_bytes()[0])?;
+                self.scan_position += relative_end_index;
+                result.push(Token::BraceLiteral(data))
+            } else if *char == "[".as_bytes()[0] {
+                let StringyParseResult {
+                    relative_end_index,
+                    data,
+                } = parse_block_greedily(&data[self.scan_position..], "]".as_bytes()[0])?;
+                self.scan_position += relative_end_index;
+                result.push(Token::BracketLiteral(data))
+            } else if starts_input_keyword(data, self.scan_position) {
+                let StringyParseResult {
+                    relative_end_index,
+                    data,
+                } = parse_input_greedily(&data[self.scan_position..])?;
+                self.scan_position += relative_end_index;
+                result.push(Token::InputLiteral(data))
             }
 
+            if result.len() > count {
+                self.spans.push(start..self.scan_position + 1);
+            }
             self.scan_position += 1;
         }
 
@@ -105,7 +263,9 @@ impl Lexxer {
 mod test {
     use crate::lexer::{Lexxer, LexxerError, StringyParseResult};
 
-    use super::{parse_label_block_greedily, parse_string_literal_greedily, Token};
+    use super::{
+        parse_block_greedily, parse_label_block_greedily, parse_string_literal_greedily, Token,
+    };
 
     // A whole prompt chunk, e.g.
     // > (LABEL) \"Hello World\"
@@ -160,6 +320,112 @@ mod test {
         assert_eq!(result, expected_result);
     }
 
+    // A response with an assignment block
+    #[test]
+    fn lex_a_brace_block() {
+        let input = "< (NEXT) \"Blue\" {colour = \"blue\"}";
+
+        let expected_tokens = vec![
+            Token::LeftAngular,
+            Token::LabelLiteral("NEXT"),
+            Token::StringLiteral("Blue"),
+            Token::BraceLiteral("colour = \"blue\""),
+        ];
+        let expected_result: Result<Vec<Token>, LexxerError> = Ok(expected_tokens);
+
+        let mut lexxer = Lexxer::new();
+        let result = lexxer.parse(input);
+        assert_eq!(result, expected_result);
+    }
+
+    // A guarded prompt
+    #[test]
+    fn lex_a_bracket_block() {
+        let input = "> (WIN) \"You won!\" [if score > 3]";
+
+        let expected_tokens = vec![
+            Token::RightAngular,
+            Token::LabelLiteral("WIN"),
+            Token::StringLiteral("You won!"),
+            Token::BracketLiteral("if score > 3"),
+        ];
+        let expected_result: Result<Vec<Token>, LexxerError> = Ok(expected_tokens);
+
+        let mut lexxer = Lexxer::new();
+        let result = lexxer.parse(input);
+        assert_eq!(result, expected_result);
+    }
+
+    // A free text input response
+    #[test]
+    fn lex_an_input_response() {
+        let input = "< (NEXT) input name\n< \"input\" inputs (input)";
+
+        let expected_tokens = vec![
+            Token::LeftAngular,
+            Token::LabelLiteral("NEXT"),
+            Token::InputLiteral("name"),
+            Token::LeftAngular,
+            Token::StringLiteral("input"),
+            Token::LabelLiteral("input"),
+        ];
+        let expected_result: Result<Vec<Token>, LexxerError> = Ok(expected_tokens);
+
+        let mut lexxer = Lexxer::new();
+        let result = lexxer.parse(input);
+        assert_eq!(result, expected_result);
+    }
+
+    // Text outside quotes is skipped a byte at a time, so it can be anything
+    #[test]
+    fn lex_past_multibyte_text_outside_quotes() {
+        let input = "> \"Hi\" Zoë était là → -> NEXT\n< input name\nZoë was here";
+
+        let expected_tokens = vec![
+            Token::RightAngular,
+            Token::StringLiteral("Hi"),
+            Token::GotoLiteral(Some("NEXT")),
+            Token::LeftAngular,
+            Token::InputLiteral("name"),
+        ];
+        let expected_result: Result<Vec<Token>, LexxerError> = Ok(expected_tokens);
+
+        let mut lexxer = Lexxer::new();
+        let result = lexxer.parse(input);
+        assert_eq!(result, expected_result);
+    }
+
+    #[test]
+    fn lex_gotos() {
+        let input = "> \"Once upon a time\" ->\n> \"The end\" -> END:happy [if done]";
+
+        let expected_tokens = vec![
+            Token::RightAngular,
+            Token::StringLiteral("Once upon a time"),
+            Token::GotoLiteral(None),
+            Token::RightAngular,
+            Token::StringLiteral("The end"),
+            Token::GotoLiteral(Some("END:happy")),
+            Token::BracketLiteral("if done"),
+        ];
+        let expected_result: Result<Vec<Token>, LexxerError> = Ok(expected_tokens);
+
+        let mut lexxer = Lexxer::new();
+        let result = lexxer.parse(input);
+        assert_eq!(result, expected_result);
+    }
+
+    #[test]
+    fn fail_to_lex_input_without_name() {
+        let input = "< (NEXT) input \"name\"";
+
+        let expected_result: Result<Vec<Token>, LexxerError> = Err(LexxerError::InvalidInputName);
+
+        let mut lexxer = Lexxer::new();
+        let result = lexxer.parse(input);
+        assert_eq!(result, expected_result);
+    }
+
     // Bad syntaxes
     #[test]
     fn fail_to_lex_bad_labels_1() {
@@ -297,6 +563,31 @@ mod test {
         assert_eq!(result, expected_result);
     }
 
+    // Blocks skip over closing characters inside strings
+    #[test]
+    fn lex_a_block_containing_strings() {
+        let input = "{a = \"}\"}";
+
+        let result = parse_block_greedily(input, b'}');
+        let expected_result: Result<StringyParseResult, LexxerError> = Ok(StringyParseResult {
+            relative_end_index: 8,
+            data: "a = \"}\"",
+        });
+
+        assert_eq!(result, expected_result);
+    }
+
+    #[test]
+    fn fail_to_parse_unterminated_block() {
+        let input = "{a = \"}";
+
+        let result = parse_block_greedily(input, b'}');
+        let expected_result: Result<StringyParseResult, LexxerError> =
+            Err(LexxerError::UnterminatedBlockLiteral);
+
+        assert_eq!(result, expected_result);
+    }
+
     #[test]
     fn fail_to_parse_unterminated_string_literal() {
         let input = "\"Hello";
@@ -1,42 +1,92 @@
-use chunker::ChunkingError;
-use lexer::LexxerError;
-use parser::{ParserError, Prompt};
+use std::fmt::{self, Display};
+
+use parser::Prompt;
 
 mod chunker;
+pub mod expr;
+pub mod format;
 mod lexer;
 pub mod parser;
+pub mod template;
 
 #[derive(Debug)]
 pub enum PromptgenErr {
     InvalidSyntax,
 }
 
-impl Into<PromptgenErr> for LexxerError {
-    fn into(self) -> PromptgenErr {
-        PromptgenErr::InvalidSyntax
-    }
+/// Why a script failed to parse, and the byte offset it failed at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub message: String,
+    pub offset: usize,
 }
 
-impl Into<PromptgenErr> for ChunkingError {
-    fn into(self) -> PromptgenErr {
-        PromptgenErr::InvalidSyntax
+impl SyntaxError {
+    fn new(err: impl Display, offset: usize) -> Self {
+        Self {
+            message: err.to_string(),
+            offset,
+        }
+    }
+
+    /// One-based line and column of the offset in `data`, counting columns in
+    /// characters.
+    pub fn line_and_column(&self, data: &str) -> (usize, usize) {
+        line_and_column(data, self.offset)
     }
 }
 
-impl Into<PromptgenErr> for ParserError {
-    fn into(self) -> PromptgenErr {
-        PromptgenErr::InvalidSyntax
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
     }
 }
 
+/// One-based line and column of a byte offset in `data`.
+pub fn line_and_column(data: &str, offset: usize) -> (usize, usize) {
+    let before = &data[..offset.min(data.len())];
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
 pub fn parse<'a>(data: &'a str) -> Result<Vec<Prompt<'a>>, PromptgenErr> {
+    parse_located(data).map_err(|_| PromptgenErr::InvalidSyntax)
+}
+
+/// Like `parse`, failing with what went wrong and where.
+pub fn parse_located<'a>(data: &'a str) -> Result<Vec<Prompt<'a>>, SyntaxError> {
     let mut lexer = lexer::Lexxer::new();
     let mut chunker = chunker::Chunker::new();
     let mut parser = parser::Parser::new();
 
-    let lexed_result = lexer.parse(data).map_err(|e| e.into())?;
-    let chunked_result = chunker.parse_tokens(lexed_result).map_err(|e| e.into())?;
-    let parsed_result = parser.parse_chunks(chunked_result);
+    let lexed_result = lexer
+        .parse(data)
+        .map_err(|err| SyntaxError::new(err, lexer.scan_position))?;
+
+    // Chunks are located by the token they start at
+    let token_offset = |token: Option<&usize>| {
+        token
+            .and_then(|token| lexer.spans.get(*token))
+            .map(|span| span.start)
+            .unwrap_or(data.len())
+    };
+    let chunked_result = chunker
+        .parse_tokens(lexed_result)
+        .map_err(|err| SyntaxError::new(err, token_offset(Some(&chunker.scan_position))))?;
+    // Parse errors point at the modifier at fault, if it's down to one
+    let parsed_result = parser.parse_chunks(chunked_result).map_err(|err| {
+        let token = match parser.failed_modifier {
+            Some(modifier) => chunker
+                .modifier_starts
+                .get(parser.scan_position)
+                .map(|start| start + modifier),
+            None => chunker.starts.get(parser.scan_position).copied(),
+        };
+        SyntaxError::new(err, token_offset(token.as_ref()))
+    })?;
 
     Ok(parsed_result)
 }
@@ -46,10 +96,56 @@ mod test {
     use std::fs::read_to_string;
 
     use crate::{
-        parse,
-        parser::{Prompt, Response},
+        parse, parse_located,
+        parser::{Input, Prompt, Response},
     };
 
+    #[test]
+    fn locate_syntax_errors() {
+        let data = "> \"Hi\"\n< \"Hello\"\n< \"Bye\" [nope]\n";
+        let err = parse_located(data).unwrap_err();
+        assert_eq!(err.message, "unknown directive");
+        assert_eq!(err.line_and_column(data), (3, 9));
+
+        let data = "> \"Hi\" [shuffle] [shuffle]\n< \"Hello\"\n";
+        let err = parse_located(data).unwrap_err();
+        assert_eq!(err.message, "directive is given more than once");
+        assert_eq!(err.line_and_column(data), (1, 18));
+
+        let data = "> \"Hi\" ->\n< \"Hello\"\n";
+        let err = parse_located(data).unwrap_err();
+        assert_eq!(err.line_and_column(data), (1, 1));
+
+        let data = "> \"Hi\"\n  < \"Hel";
+        let err = parse_located(data).unwrap_err();
+        assert_eq!(err.message, "text is missing its closing `\"`");
+        assert_eq!(err.line_and_column(data), (2, 5));
+
+        let data = "> \"Hi\"\n(LOST)";
+        let err = parse_located(data).unwrap_err();
+        assert_eq!(err.line_and_column(data), (2, 1));
+
+        let data = format!("> \"Hi\"\n< \"Hello\" [if {}x]\n", "!".repeat(200_000));
+        let err = parse_located(&data).unwrap_err();
+        assert_eq!(err.message, "expression nests more than 64 deep");
+        assert_eq!(err.line_and_column(&data), (2, 11));
+    }
+
+    #[test]
+    fn parse_input_prompts() {
+        let result = parse("> (START) \"What's your name?\"\n< (GREET) input name");
+        assert_eq!(
+            result.unwrap()[0].responses[0].input,
+            Some(Input {
+                variable: "name",
+                ..Default::default()
+            })
+        );
+
+        let result = parse("> (START) input name");
+        assert!(result.is_err());
+    }
+
     #[test]
     fn parse_a_bit_of_text() {
         let data = read_to_string("./sample_prompts.txt").unwrap();
@@ -62,12 +158,15 @@ mod test {
                     Response {
                         text: "Yes, I am",
                         label: Some("YES"),
+                        ..Default::default()
                     },
                     Response {
                         text: "No",
                         label: Some("ANS_NO"),
+                        ..Default::default()
                     },
                 ],
+                ..Default::default()
             },
             Prompt {
                 text: "That's very weird! Care to try again?",
@@ -75,12 +174,15 @@ mod test {
                 responses: vec![Response {
                     text: "Please!",
                     label: Some("NO"),
+                    ..Default::default()
                 }],
+                ..Default::default()
             },
             Prompt {
                 text: "Nice! Glad to meet you human!",
                 label: Some("YES"),
                 responses: vec![],
+                ..Default::default()
             },
         ];
 
@@ -1,26 +1,502 @@
+use std::{
+    fmt::{self, Display},
+    time::Duration,
+};
+
 use crate::chunker::{Chunk, ChunkVariant};
+use crate::expr::{
+    parse_assignments, parse_expr, parse_literal, tokenize, Assignment, Expr, ExprError, ExprToken,
+    Literal, TokenCursor,
+};
+use crate::lexer::Token;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ParserError {
+    InvalidBlock(ExprError),
+    UnexpectedModifier,
+    UnknownDirective,
+    DuplicateDirective,
+    /// A response with both a label and `[bands ...]`.
+    ConflictingTarget,
+    /// A statement, which moves on by itself, with responses.
+    StatementWithResponses,
+    /// More than one `[default]` response on a prompt.
+    DuplicateDefault,
+}
+
+impl Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParserError::InvalidBlock(err) => err.fmt(f),
+            ParserError::UnexpectedModifier => write!(f, "block or directive isn't allowed here"),
+            ParserError::UnknownDirective => write!(f, "unknown directive"),
+            ParserError::DuplicateDirective => write!(f, "directive is given more than once"),
+            ParserError::ConflictingTarget => {
+                write!(f, "an answer with a label can't pick another one too")
+            }
+            ParserError::StatementWithResponses => write!(f, "statements can't have answers"),
+            ParserError::DuplicateDefault => {
+                write!(f, "a question can only have one `[default]` answer")
+            }
+        }
+    }
+}
 
-pub(crate) enum ParserError {}
+impl From<ExprError> for ParserError {
+    fn from(err: ExprError) -> Self {
+        ParserError::InvalidBlock(err)
+    }
+}
 
+/// What typed in text has to look like, from directives like `[int 1..10]`.
 #[derive(PartialEq, Eq, Debug, Clone)]
+pub enum InputCheck<'a> {
+    /// A whole number, optionally within inclusive bounds, from `[int]`,
+    /// `[int 1..10]`, `[int 1..]` or `[int ..10]`. Stored as an integer.
+    Int { min: Option<i64>, max: Option<i64> },
+    /// `yes`, `y`, `no` or `n` in any case, from `[yesno]`. Stored as a
+    /// boolean.
+    YesNo,
+    /// A `YYYY-MM-DD` calendar date, from `[date]`.
+    Date,
+    /// Something shaped like an email address, from `[email]`.
+    Email,
+    /// One of the listed options, from `[one_of "red", "blue"]`.
+    OneOf(Vec<&'a str>),
+    /// The whole text matches a pattern, from `[regex "[A-Z]{3}"]`.
+    Regex(&'a str),
+}
+
+/// Free text typed in by the user, from `input name`.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Input<'a> {
+    /// Variable the text is stored in.
+    pub variable: &'a str,
+    pub check: Option<InputCheck<'a>>,
+    /// Shown when the text doesn't pass the check, from `[retry "..."]`.
+    pub retry: Option<&'a str>,
+}
+
+/// Points added to a named score, from `[score 2]` or
+/// `[score geography 1, history -1]`.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ScoreDelta<'a> {
+    pub name: &'a str,
+    pub delta: i64,
+}
+
+/// Score the default `[score ...]` points go to.
+pub const DEFAULT_SCORE: &str = "score";
+
+/// One inclusive score range of `[bands ...]`, either bound can be left out.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Band<'a> {
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    pub label: &'a str,
+}
+
+impl Band<'_> {
+    pub fn contains(&self, score: i64) -> bool {
+        self.min.is_none_or(|min| score >= min) && self.max.is_none_or(|max| score <= max)
+    }
+}
+
+/// A label `[random ...]` can pick, `weight` times as likely as a label
+/// weighing 1.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct WeightedLabel<'a> {
+    pub label: &'a str,
+    pub weight: u32,
+}
+
+/// Where a response goes when it's worked out while prompting rather than
+/// written as its label. On a prompt, it's where its responses without a
+/// label go instead of the next prompt.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Target<'a> {
+    /// The label of the first band the score falls in, from
+    /// `[bands 8.. GOLD, 4..7 SILVER, BRONZE]`. Uses the total of all scores
+    /// unless one is named, like `[bands geography: ...]`.
+    Bands {
+        score: Option<&'a str>,
+        bands: Vec<Band<'a>>,
+    },
+    /// A label picked at random, from `[random WIN 3, LOSE]`. Labels weigh 1
+    /// unless given a weight.
+    Random(Vec<WeightedLabel<'a>>),
+    /// Always the same label, from `[continue LABEL]`.
+    Label(&'a str),
+}
+
+/// How many responses a multi-select prompt takes, from `[multi]` or
+/// `[multi 1..3]`. Leaving out the lower bound allows none.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Selection {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl Selection {
+    pub fn allows(&self, count: usize) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
 pub struct Response<'a> {
     pub text: &'a str,
     pub label: Option<&'a str>,
+    /// Variables set when this response is chosen.
+    pub assignments: Vec<Assignment<'a>>,
+    /// Only offered while this holds, from `[if ...]`.
+    pub guard: Option<Expr<'a>>,
+    /// Set when this response takes free text instead of being picked.
+    pub input: Option<Input<'a>>,
+    /// Points added when this response is chosen.
+    pub scores: Vec<ScoreDelta<'a>>,
+    pub target: Option<Target<'a>>,
+    /// Taken when the prompt times out or gets empty input, from
+    /// `[default]`.
+    pub default: bool,
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+/// Where a statement prompt moves on to by itself.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Advance<'a> {
+    /// From `->`, to the prompt's `[random ...]` or `[bands ...]` target if it
+    /// has one, otherwise the next prompt.
+    Next,
+    /// From `-> LABEL`.
+    Goto(&'a str),
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
 pub struct Prompt<'a> {
     pub text: &'a str,
     pub label: Option<&'a str>,
     pub responses: Vec<Response<'a>>,
+    /// Skipped over while this doesn't hold, from `[if ...]`.
+    pub guard: Option<Expr<'a>>,
+    pub target: Option<Target<'a>>,
+    /// Responses are offered in a random order, from `[shuffle]`.
+    pub shuffle: bool,
+    /// Set on statements, which are shown without responses.
+    pub advance: Option<Advance<'a>>,
+    /// Set when several responses are chosen at once. They're followed by
+    /// the prompt's target, or the next prompt, never their own labels.
+    pub multi: Option<Selection>,
+    /// How long to wait before taking the `[default]` response, from
+    /// `[timeout 30]` in seconds.
+    pub timeout: Option<Duration>,
 }
 
 // TODO: Really weird design here, clean it up
 type ResponseParsingResult<'a> = (Option<usize>, Vec<Response<'a>>);
 
+// Bracketed directives, e.g. `[if score > 3]`
+enum Directive<'a> {
+    Guard(Expr<'a>),
+    Check(InputCheck<'a>),
+    Retry(&'a str),
+    Score(Vec<ScoreDelta<'a>>),
+    Target(Target<'a>),
+    Shuffle,
+    Multi(Selection),
+    Default,
+    Timeout(Duration),
+}
+
+fn parse_directive(block: &str) -> Result<Directive<'_>, ParserError> {
+    let block = block.trim();
+    let (name, rest) = block.split_once(char::is_whitespace).unwrap_or((block, ""));
+    let tokens = tokenize(rest)?;
+    let mut cursor = TokenCursor::new(&tokens);
+
+    let directive = match name {
+        "if" => return Ok(Directive::Guard(parse_expr(rest)?)),
+        "int" => Directive::Check(parse_int_check(&mut cursor)?),
+        "yesno" => Directive::Check(InputCheck::YesNo),
+        "date" => Directive::Check(InputCheck::Date),
+        "email" => Directive::Check(InputCheck::Email),
+        "one_of" => Directive::Check(parse_one_of_check(&mut cursor)?),
+        "regex" => Directive::Check(InputCheck::Regex(parse_str(&mut cursor)?)),
+        "retry" => Directive::Retry(parse_str(&mut cursor)?),
+        "score" => Directive::Score(parse_score_deltas(&mut cursor)?),
+        "bands" => Directive::Target(parse_bands(&mut cursor)?),
+        "random" => Directive::Target(parse_random(&mut cursor)?),
+        "shuffle" => Directive::Shuffle,
+        "continue" => Directive::Target(Target::Label(parse_label(&mut cursor)?)),
+        "multi" => Directive::Multi(parse_selection(&mut cursor)?),
+        "default" => Directive::Default,
+        "timeout" => Directive::Timeout(parse_timeout(&mut cursor)?),
+        _ => return Err(ParserError::UnknownDirective),
+    };
+
+    match cursor.is_done() {
+        true => Ok(directive),
+        false => Err(ExprError::UnexpectedToken.into()),
+    }
+}
+
+fn parse_str<'a>(cursor: &mut TokenCursor<'a, '_>) -> Result<&'a str, ExprError> {
+    match cursor.next()? {
+        ExprToken::Str(s) => Ok(s),
+        _ => Err(ExprError::UnexpectedToken),
+    }
+}
+
+fn parse_int(cursor: &mut TokenCursor) -> Result<i64, ExprError> {
+    cursor.eat("+");
+    match parse_literal(cursor)? {
+        Literal::Int(n) => Ok(n),
+        _ => Err(ExprError::UnexpectedToken),
+    }
+}
+
+fn is_int_next(cursor: &TokenCursor) -> bool {
+    matches!(
+        cursor.peek(),
+        Some(ExprToken::Int(_) | ExprToken::Symbol("-" | "+"))
+    )
+}
+
+// A range with either bound left out, e.g. `1..10`, `1..` or `..10`
+fn parse_range(cursor: &mut TokenCursor) -> Result<(Option<i64>, Option<i64>), ExprError> {
+    let min = is_int_next(cursor).then(|| parse_int(cursor)).transpose()?;
+    if !cursor.eat("..") {
+        return Err(ExprError::UnexpectedToken);
+    }
+    let max = is_int_next(cursor).then(|| parse_int(cursor)).transpose()?;
+
+    Ok((min, max))
+}
+
+fn parse_int_check<'a>(cursor: &mut TokenCursor<'a, '_>) -> Result<InputCheck<'a>, ExprError> {
+    let (min, max) = match cursor.is_done() {
+        true => (None, None),
+        false => parse_range(cursor)?,
+    };
+
+    Ok(InputCheck::Int { min, max })
+}
+
+// Comma separated points, each for the default score unless named
+fn parse_score_deltas<'a>(
+    cursor: &mut TokenCursor<'a, '_>,
+) -> Result<Vec<ScoreDelta<'a>>, ExprError> {
+    let mut deltas = vec![];
+    loop {
+        let name = match cursor.peek() {
+            Some(ExprToken::Ident(_)) => cursor.ident()?,
+            _ => DEFAULT_SCORE,
+        };
+        deltas.push(ScoreDelta {
+            name,
+            delta: parse_int(cursor)?,
+        });
+
+        if !cursor.eat(",") {
+            return Ok(deltas);
+        }
+    }
+}
+
+fn parse_label<'a>(cursor: &mut TokenCursor<'a, '_>) -> Result<&'a str, ExprError> {
+    match cursor.next()? {
+        ExprToken::Ident(label) | ExprToken::Str(label) => Ok(label),
+        _ => Err(ExprError::UnexpectedToken),
+    }
+}
+
+// An optional `name:`, then comma separated bands, each an optional range and
+// a label, which can be quoted like `"END:gold"`
+fn parse_bands<'a>(cursor: &mut TokenCursor<'a, '_>) -> Result<Target<'a>, ExprError> {
+    let score = match cursor.peek() {
+        Some(ExprToken::Ident(name))
+            if matches!(cursor.peek_nth(1), Some(ExprToken::Symbol(":"))) =>
+        {
+            cursor.next()?;
+            cursor.next()?;
+            Some(name)
+        }
+        _ => None,
+    };
+
+    let mut bands = vec![];
+    loop {
+        let (min, max) =
+            match is_int_next(cursor) || matches!(cursor.peek(), Some(ExprToken::Symbol(".."))) {
+                true => parse_range(cursor)?,
+                false => (None, None),
+            };
+        bands.push(Band {
+            min,
+            max,
+            label: parse_label(cursor)?,
+        });
+
+        if !cursor.eat(",") {
+            return Ok(Target::Bands { score, bands });
+        }
+    }
+}
+
+fn parse_one_of_check<'a>(cursor: &mut TokenCursor<'a, '_>) -> Result<InputCheck<'a>, ExprError> {
+    let mut options = vec![parse_str(cursor)?];
+    while cursor.eat(",") {
+        options.push(parse_str(cursor)?);
+    }
+
+    Ok(InputCheck::OneOf(options))
+}
+
+fn parse_selection(cursor: &mut TokenCursor) -> Result<Selection, ExprError> {
+    let (min, max) = match cursor.is_done() {
+        true => (None, None),
+        false => parse_range(cursor)?,
+    };
+    let count = |bound: i64| usize::try_from(bound).map_err(|_| ExprError::InvalidNumber);
+
+    Ok(Selection {
+        min: min.map(count).transpose()?.unwrap_or(0),
+        max: max.map(count).transpose()?,
+    })
+}
+
+// A positive number of seconds
+fn parse_timeout(cursor: &mut TokenCursor) -> Result<Duration, ExprError> {
+    match cursor.next()? {
+        ExprToken::Int(seconds) if seconds > 0 => Ok(Duration::from_secs(seconds as u64)),
+        ExprToken::Int(_) => Err(ExprError::InvalidNumber),
+        _ => Err(ExprError::UnexpectedToken),
+    }
+}
+
+// Comma separated labels, each with an optional positive weight
+fn parse_random<'a>(cursor: &mut TokenCursor<'a, '_>) -> Result<Target<'a>, ExprError> {
+    let mut labels = vec![];
+    loop {
+        let label = parse_label(cursor)?;
+        let weight = match cursor.peek() {
+            Some(ExprToken::Int(weight)) => {
+                cursor.next()?;
+                u32::try_from(weight)
+                    .ok()
+                    .filter(|weight| *weight > 0)
+                    .ok_or(ExprError::InvalidNumber)?
+            }
+            _ => 1,
+        };
+        labels.push(WeightedLabel { label, weight });
+
+        if !cursor.eat(",") {
+            return Ok(Target::Random(labels));
+        }
+    }
+}
+
+fn set_once<T>(slot: &mut Option<T>, value: T) -> Result<(), ParserError> {
+    match slot.replace(value) {
+        Some(_) => Err(ParserError::DuplicateDirective),
+        None => Ok(()),
+    }
+}
+
+// Fails with the index of the modifier at fault
+fn parse_response<'a>(chunk: &Chunk<'a>) -> Result<Response<'a>, (usize, ParserError)> {
+    let mut response = Response {
+        text: chunk.text,
+        label: chunk.label,
+        ..Default::default()
+    };
+
+    for (idx, modifier) in chunk.modifiers.iter().enumerate() {
+        parse_response_modifier(&mut response, modifier).map_err(|err| (idx, err))?;
+    }
+
+    Ok(response)
+}
+
+fn parse_response_modifier<'a>(
+    response: &mut Response<'a>,
+    modifier: &Token<'a>,
+) -> Result<(), ParserError> {
+    match modifier {
+        Token::BraceLiteral(block) => response.assignments.extend(parse_assignments(block)?),
+        Token::BracketLiteral(block) => match (parse_directive(block)?, &mut response.input) {
+            (Directive::Guard(expr), _) => set_once(&mut response.guard, expr)?,
+            // Checks only make sense on input, which comes first
+            (Directive::Check(check), Some(input)) => set_once(&mut input.check, check)?,
+            (Directive::Retry(retry), Some(input)) => set_once(&mut input.retry, retry)?,
+            (Directive::Score(deltas), _) => response.scores.extend(deltas),
+            (Directive::Target(_), _) if response.label.is_some() => {
+                return Err(ParserError::ConflictingTarget)
+            }
+            (Directive::Target(target), _) => set_once(&mut response.target, target)?,
+            (Directive::Default, None) if !response.default => response.default = true,
+            (Directive::Default, None) => return Err(ParserError::DuplicateDirective),
+            _ => return Err(ParserError::UnexpectedModifier),
+        },
+        Token::InputLiteral(variable) if response.input.is_none() => {
+            response.input = Some(Input {
+                variable,
+                ..Default::default()
+            })
+        }
+        _ => return Err(ParserError::UnexpectedModifier),
+    }
+
+    Ok(())
+}
+
+// Fails with the index of the modifier at fault, if it's down to one
+fn parse_prompt_modifiers<'a>(
+    prompt: &mut Prompt<'a>,
+    modifiers: &[Token<'a>],
+) -> Result<(), (Option<usize>, ParserError)> {
+    for (idx, modifier) in modifiers.iter().enumerate() {
+        parse_prompt_modifier(prompt, modifier).map_err(|err| (Some(idx), err))?;
+    }
+
+    if prompt.advance.is_some() && !prompt.responses.is_empty() {
+        return Err((None, ParserError::StatementWithResponses));
+    }
+    match prompt.responses.iter().filter(|r| r.default).count() > 1 {
+        true => Err((None, ParserError::DuplicateDefault)),
+        false => Ok(()),
+    }
+}
+
+fn parse_prompt_modifier<'a>(
+    prompt: &mut Prompt<'a>,
+    modifier: &Token<'a>,
+) -> Result<(), ParserError> {
+    match modifier {
+        Token::BracketLiteral(block) => match parse_directive(block)? {
+            Directive::Guard(expr) => set_once(&mut prompt.guard, expr)?,
+            Directive::Target(target) => set_once(&mut prompt.target, target)?,
+            Directive::Shuffle if !prompt.shuffle => prompt.shuffle = true,
+            Directive::Shuffle => return Err(ParserError::DuplicateDirective),
+            Directive::Multi(selection) => set_once(&mut prompt.multi, selection)?,
+            Directive::Timeout(timeout) => set_once(&mut prompt.timeout, timeout)?,
+            _ => return Err(ParserError::UnexpectedModifier),
+        },
+        Token::GotoLiteral(label) => {
+            let advance = label.map_or(Advance::Next, Advance::Goto);
+            set_once(&mut prompt.advance, advance)?
+        }
+        _ => return Err(ParserError::UnexpectedModifier),
+    }
+
+    Ok(())
+}
+
 // Scans given slice greedily and returns the index after the scan of the slice
 // and a vector of possible responses.
-fn parse_response_chunks_greedily<'a>(chunks: &[Chunk<'a>]) -> ResponseParsingResult<'a> {
+fn parse_response_chunks_greedily<'a>(
+    chunks: &[Chunk<'a>],
+) -> Result<ResponseParsingResult<'a>, ParserError> {
     let mut scan_position: usize = 0;
 
     while let Some(Chunk {
@@ -33,44 +509,69 @@ fn parse_response_chunks_greedily<'a>(chunks: &[Chunk<'a>]) -> ResponseParsingRe
 
     let response: Vec<Response> = chunks[0..scan_position]
         .iter()
-        .map(|c| Response {
-            text: c.text,
-            label: c.label,
-        })
-        .collect();
+        .map(|chunk| parse_response(chunk).map_err(|(_, err)| err))
+        .collect::<Result<_, _>>()?;
 
     if scan_position == 0 {
-        return (None, response);
+        Ok((None, response))
     } else {
-        return (Some(scan_position - 1), response);
+        Ok((Some(scan_position - 1), response))
     }
 }
 
 pub(crate) struct Parser {
-    scan_position: usize,
+    pub(crate) scan_position: usize,
+    /// Index of the modifier, within the chunk at `scan_position`, that
+    /// parsing failed at.
+    pub(crate) failed_modifier: Option<usize>,
 }
 
 impl Parser {
     pub(crate) fn new() -> Self {
-        Parser { scan_position: 0 }
+        Parser {
+            scan_position: 0,
+            failed_modifier: None,
+        }
     }
 
-    pub(crate) fn parse_chunks<'a>(&mut self, chunks: Vec<Chunk<'a>>) -> Vec<Prompt<'a>> {
+    pub(crate) fn parse_chunks<'a>(
+        &mut self,
+        chunks: Vec<Chunk<'a>>,
+    ) -> Result<Vec<Prompt<'a>>, ParserError> {
         let mut prompts = vec![];
 
         while let Some(Chunk {
             variant: ChunkVariant::Prompt,
             text,
             label,
+            modifiers,
         }) = chunks.get(self.scan_position)
         {
-            let (relative_scan_position, responses) =
-                parse_response_chunks_greedily(&chunks[self.scan_position + 1..]);
-            prompts.push(Prompt {
+            let (relative_scan_position, responses) = parse_response_chunks_greedily(
+                &chunks[self.scan_position + 1..],
+            )
+            .inspect_err(|_| {
+                // Left at the response that failed
+                let failed = chunks[self.scan_position + 1..]
+                    .iter()
+                    .enumerate()
+                    .find_map(|(idx, chunk)| Some((idx, parse_response(chunk).err()?.0)));
+                if let Some((idx, modifier)) = failed {
+                    self.scan_position += 1 + idx;
+                    self.failed_modifier = Some(modifier);
+                }
+            })?;
+            let mut prompt = Prompt {
                 text,
                 label: *label,
                 responses,
-            });
+                ..Default::default()
+            };
+            parse_prompt_modifiers(&mut prompt, modifiers).map_err(|(modifier, err)| {
+                self.failed_modifier = modifier;
+                err
+            })?;
+            prompts.push(prompt);
 
             match relative_scan_position {
                 Some(relative_scan_pos) => self.scan_position += relative_scan_pos + 2,
@@ -78,18 +579,25 @@ impl Parser {
             }
         }
 
-        prompts
+        Ok(prompts)
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::time::Duration;
+
     use crate::{
         chunker::{Chunk, ChunkVariant},
+        expr::{Assignment, BinaryOp, Expr, ExprError, Literal},
+        lexer::Token,
         parser::{parse_response_chunks_greedily, Prompt},
     };
 
-    use super::{Parser, Response};
+    use super::{
+        Advance, Band, Input, InputCheck, Parser, ParserError, Response, ScoreDelta, Selection,
+        Target, WeightedLabel,
+    };
 
     // Parsing chunks fully
     #[test]
@@ -99,21 +607,25 @@ mod test {
                 variant: ChunkVariant::Prompt,
                 text: "Are you human?",
                 label: Some("NONHUMAN"),
+                modifiers: vec![],
             },
             Chunk {
                 variant: ChunkVariant::Response,
                 text: "Yes",
                 label: Some("HUMAN"),
+                modifiers: vec![],
             },
             Chunk {
                 variant: ChunkVariant::Response,
                 text: "No",
                 label: Some("NONHUMAN"),
+                modifiers: vec![],
             },
             Chunk {
                 variant: ChunkVariant::Prompt,
                 text: "Nice to meet you",
                 label: Some("HUMAN"),
+                modifiers: vec![],
             },
         ];
 
@@ -125,24 +637,28 @@ mod test {
                     Response {
                         text: "Yes",
                         label: Some("HUMAN"),
+                        ..Default::default()
                     },
                     Response {
                         text: "No",
                         label: Some("NONHUMAN"),
+                        ..Default::default()
                     },
                 ],
+                ..Default::default()
             },
             Prompt {
                 text: "Nice to meet you",
                 label: Some("HUMAN"),
                 responses: vec![],
+                ..Default::default()
             },
         ];
 
         let mut parser = Parser::new();
         let parse_results = parser.parse_chunks(input);
 
-        assert_eq!(parse_results, expected_result);
+        assert_eq!(parse_results, Ok(expected_result));
     }
 
     // Parsing some chunks into responses, until the next prompt.
@@ -153,16 +669,19 @@ mod test {
                 variant: ChunkVariant::Response,
                 label: Some("NICE"),
                 text: "Hello world?",
+                modifiers: vec![],
             },
             Chunk {
                 variant: ChunkVariant::Response,
                 label: Some("NICE2"),
                 text: "Hello me!",
+                modifiers: vec![],
             },
             Chunk {
                 variant: ChunkVariant::Prompt,
                 label: None,
                 text: "Who are you?",
+                modifiers: vec![],
             },
         ];
 
@@ -172,14 +691,16 @@ mod test {
             Response {
                 text: "Hello world?",
                 label: Some("NICE"),
+                ..Default::default()
             },
             Response {
                 text: "Hello me!",
                 label: Some("NICE2"),
+                ..Default::default()
             },
         ];
 
-        assert_eq!(result, (Some(1), expected_result));
+        assert_eq!(result, Ok((Some(1), expected_result)));
     }
 
     #[test]
@@ -188,6 +709,7 @@ mod test {
             variant: ChunkVariant::Prompt,
             label: None,
             text: "foobar",
+            modifiers: vec![],
         }];
 
         let results = parse_response_chunks_greedily(&input);
@@ -195,6 +717,498 @@ mod test {
         let expected_relative_index: Option<usize> = None;
         let resultant_vec: Vec<Response> = vec![];
 
-        assert_eq!(results, (expected_relative_index, resultant_vec));
+        assert_eq!(results, Ok((expected_relative_index, resultant_vec)));
+    }
+
+    // Responses pick up assignments from their blocks
+    #[test]
+    fn parse_response_assignments() {
+        let input = vec![Chunk {
+            variant: ChunkVariant::Response,
+            label: Some("NEXT"),
+            text: "Blue",
+            modifiers: vec![Token::BraceLiteral("colour = \"blue\", picked = true")],
+        }];
+
+        let result = parse_response_chunks_greedily(&input);
+
+        let expected_result = vec![Response {
+            text: "Blue",
+            label: Some("NEXT"),
+            assignments: vec![
+                Assignment {
+                    name: "colour",
+                    value: Literal::Str("blue"),
+                },
+                Assignment {
+                    name: "picked",
+                    value: Literal::Bool(true),
+                },
+            ],
+            ..Default::default()
+        }];
+
+        assert_eq!(result, Ok((Some(0), expected_result)));
+    }
+
+    // Prompts and responses pick up guards from their directives
+    #[test]
+    fn parse_guards() {
+        let input = vec![
+            Chunk {
+                variant: ChunkVariant::Prompt,
+                label: Some("WIN"),
+                text: "You won!",
+                modifiers: vec![Token::BracketLiteral("if won")],
+            },
+            Chunk {
+                variant: ChunkVariant::Response,
+                label: None,
+                text: "Bonus round",
+                modifiers: vec![Token::BracketLiteral(" if score > 3 ")],
+            },
+        ];
+
+        let expected_result = vec![Prompt {
+            text: "You won!",
+            label: Some("WIN"),
+            responses: vec![Response {
+                text: "Bonus round",
+                guard: Some(Expr::Binary(
+                    BinaryOp::Gt,
+                    Box::new(Expr::Variable("score")),
+                    Box::new(Expr::Literal(Literal::Int(3))),
+                )),
+                ..Default::default()
+            }],
+            guard: Some(Expr::Variable("won")),
+            ..Default::default()
+        }];
+
+        let mut parser = Parser::new();
+        let parse_results = parser.parse_chunks(input);
+
+        assert_eq!(parse_results, Ok(expected_result));
+    }
+
+    // Input responses carry the variable they fill in
+    #[test]
+    fn parse_input_responses() {
+        let input = vec![Chunk {
+            variant: ChunkVariant::Response,
+            label: Some("GREET"),
+            text: "",
+            modifiers: vec![Token::InputLiteral("name")],
+        }];
+
+        let result = parse_response_chunks_greedily(&input);
+
+        let expected_result = vec![Response {
+            label: Some("GREET"),
+            input: Some(Input {
+                variable: "name",
+                ..Default::default()
+            }),
+            ..Default::default()
+        }];
+
+        assert_eq!(result, Ok((Some(0), expected_result)));
+    }
+
+    // Input checks and their retry message
+    #[test]
+    fn parse_input_checks() {
+        let checks = [
+            (
+                "int -5..10",
+                InputCheck::Int {
+                    min: Some(-5),
+                    max: Some(10),
+                },
+            ),
+            (
+                "int ..10",
+                InputCheck::Int {
+                    min: None,
+                    max: Some(10),
+                },
+            ),
+            (
+                "int",
+                InputCheck::Int {
+                    min: None,
+                    max: None,
+                },
+            ),
+            ("yesno", InputCheck::YesNo),
+            ("date", InputCheck::Date),
+            ("email", InputCheck::Email),
+            (
+                "one_of \"red\", \"blue\"",
+                InputCheck::OneOf(vec!["red", "blue"]),
+            ),
+            ("regex \"[A-Z]{3}\"", InputCheck::Regex("[A-Z]{3}")),
+        ];
+
+        for (block, check) in checks {
+            let input = vec![Chunk {
+                variant: ChunkVariant::Response,
+                label: None,
+                text: "",
+                modifiers: vec![
+                    Token::InputLiteral("answer"),
+                    Token::BracketLiteral(block),
+                    Token::BracketLiteral("retry \"Try again\""),
+                ],
+            }];
+
+            let result = parse_response_chunks_greedily(&input).unwrap();
+            let expected_input = Input {
+                variable: "answer",
+                check: Some(check),
+                retry: Some("Try again"),
+            };
+            assert_eq!(result.1[0].input, Some(expected_input));
+        }
+    }
+
+    // Responses pick up points and score bands
+    #[test]
+    fn parse_scores_and_bands() {
+        let input = vec![Chunk {
+            variant: ChunkVariant::Response,
+            label: None,
+            text: "Paris",
+            modifiers: vec![
+                Token::BracketLiteral("score +2, geography 1"),
+                Token::BracketLiteral("score history -1"),
+                Token::BracketLiteral("bands geography: 8.. GOLD, 4..7 \"END:silver\", BRONZE"),
+            ],
+        }];
+
+        let result = parse_response_chunks_greedily(&input);
+
+        let expected_result = vec![Response {
+            text: "Paris",
+            scores: vec![
+                ScoreDelta {
+                    name: "score",
+                    delta: 2,
+                },
+                ScoreDelta {
+                    name: "geography",
+                    delta: 1,
+                },
+                ScoreDelta {
+                    name: "history",
+                    delta: -1,
+                },
+            ],
+            target: Some(Target::Bands {
+                score: Some("geography"),
+                bands: vec![
+                    Band {
+                        min: Some(8),
+                        max: None,
+                        label: "GOLD",
+                    },
+                    Band {
+                        min: Some(4),
+                        max: Some(7),
+                        label: "END:silver",
+                    },
+                    Band {
+                        min: None,
+                        max: None,
+                        label: "BRONZE",
+                    },
+                ],
+            }),
+            ..Default::default()
+        }];
+
+        assert_eq!(result, Ok((Some(0), expected_result)));
+
+        let labelled = [Chunk {
+            variant: ChunkVariant::Response,
+            label: Some("NEXT"),
+            text: "Paris",
+            modifiers: vec![Token::BracketLiteral("bands GOLD")],
+        }];
+        assert_eq!(
+            parse_response_chunks_greedily(&labelled),
+            Err(ParserError::ConflictingTarget)
+        );
+    }
+
+    // Prompts can shuffle their responses and send them somewhere random
+    #[test]
+    fn parse_random_targets() {
+        let input = vec![
+            Chunk {
+                variant: ChunkVariant::Prompt,
+                label: None,
+                text: "Heads or tails?",
+                modifiers: vec![
+                    Token::BracketLiteral("shuffle"),
+                    Token::BracketLiteral("random WIN 3, \"END:lost\""),
+                ],
+            },
+            Chunk {
+                variant: ChunkVariant::Response,
+                label: None,
+                text: "Heads",
+                modifiers: vec![],
+            },
+        ];
+
+        let expected_result = vec![Prompt {
+            text: "Heads or tails?",
+            responses: vec![Response {
+                text: "Heads",
+                ..Default::default()
+            }],
+            target: Some(Target::Random(vec![
+                WeightedLabel {
+                    label: "WIN",
+                    weight: 3,
+                },
+                WeightedLabel {
+                    label: "END:lost",
+                    weight: 1,
+                },
+            ])),
+            shuffle: true,
+            ..Default::default()
+        }];
+
+        let mut parser = Parser::new();
+        assert_eq!(parser.parse_chunks(input), Ok(expected_result));
+
+        let zero_weight = [Chunk {
+            variant: ChunkVariant::Response,
+            label: None,
+            text: "Heads",
+            modifiers: vec![Token::BracketLiteral("random WIN 0")],
+        }];
+        assert_eq!(
+            parse_response_chunks_greedily(&zero_weight),
+            Err(ParserError::InvalidBlock(ExprError::InvalidNumber))
+        );
+
+        let shuffled_response = [Chunk {
+            variant: ChunkVariant::Response,
+            label: None,
+            text: "Heads",
+            modifiers: vec![Token::BracketLiteral("shuffle")],
+        }];
+        assert_eq!(
+            parse_response_chunks_greedily(&shuffled_response),
+            Err(ParserError::UnexpectedModifier)
+        );
+    }
+
+    // Multi-select prompts carry their selection bounds and continuation
+    #[test]
+    fn parse_multi_select() {
+        let prompt = |modifiers| Chunk {
+            variant: ChunkVariant::Prompt,
+            label: None,
+            text: "Which of these do you like?",
+            modifiers,
+        };
+
+        let mut parser = Parser::new();
+        let result = parser.parse_chunks(vec![
+            prompt(vec![
+                Token::BracketLiteral("multi 1..3"),
+                Token::BracketLiteral("continue NEXT"),
+            ]),
+            prompt(vec![Token::BracketLiteral("multi")]),
+        ]);
+
+        let prompts = result.unwrap();
+        assert_eq!(
+            prompts[0].multi,
+            Some(Selection {
+                min: 1,
+                max: Some(3)
+            })
+        );
+        assert_eq!(prompts[0].target, Some(Target::Label("NEXT")));
+        assert_eq!(prompts[1].multi, Some(Selection { min: 0, max: None }));
+        assert!(prompts[1].multi.unwrap().allows(0));
+
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.parse_chunks(vec![prompt(vec![Token::BracketLiteral("multi -1..")])]),
+            Err(ParserError::InvalidBlock(ExprError::InvalidNumber))
+        );
+    }
+
+    // Prompts carry a timeout and one of their responses can be the default
+    #[test]
+    fn parse_defaults_and_timeouts() {
+        let chunks = |default_modifiers: fn() -> Vec<Token<'static>>| {
+            vec![
+                Chunk {
+                    variant: ChunkVariant::Prompt,
+                    label: None,
+                    text: "Still there?",
+                    modifiers: vec![Token::BracketLiteral("timeout 30")],
+                },
+                Chunk {
+                    variant: ChunkVariant::Response,
+                    label: None,
+                    text: "Yes",
+                    modifiers: default_modifiers(),
+                },
+                Chunk {
+                    variant: ChunkVariant::Response,
+                    label: None,
+                    text: "No",
+                    modifiers: vec![Token::BracketLiteral("default")],
+                },
+            ]
+        };
+
+        let mut parser = Parser::new();
+        let prompts = parser.parse_chunks(chunks(Vec::new)).unwrap();
+        assert_eq!(prompts[0].timeout, Some(Duration::from_secs(30)));
+        let defaults: Vec<bool> = prompts[0].responses.iter().map(|r| r.default).collect();
+        assert_eq!(defaults, [false, true]);
+
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.parse_chunks(chunks(|| vec![Token::BracketLiteral("default")])),
+            Err(ParserError::DuplicateDefault)
+        );
+
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.parse_chunks(chunks(|| vec![
+                Token::InputLiteral("answer"),
+                Token::BracketLiteral("default")
+            ])),
+            Err(ParserError::UnexpectedModifier)
+        );
+    }
+
+    // Statements carry where they move on to and can't have responses
+    #[test]
+    fn parse_statements() {
+        let statement = |goto| Chunk {
+            variant: ChunkVariant::Prompt,
+            label: None,
+            text: "Once upon a time",
+            modifiers: vec![Token::GotoLiteral(goto)],
+        };
+
+        let mut parser = Parser::new();
+        let result = parser.parse_chunks(vec![statement(None), statement(Some("END"))]);
+        let advances: Vec<_> = result.unwrap().iter().map(|p| p.advance).collect();
+        assert_eq!(advances, [Some(Advance::Next), Some(Advance::Goto("END"))]);
+
+        let response = Chunk {
+            variant: ChunkVariant::Response,
+            label: None,
+            text: "Go on",
+            modifiers: vec![],
+        };
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.parse_chunks(vec![statement(None), response]),
+            Err(ParserError::StatementWithResponses)
+        );
+    }
+
+    #[test]
+    fn fail_to_parse_bad_input_checks() {
+        let parse = |modifiers| {
+            parse_response_chunks_greedily(&[Chunk {
+                variant: ChunkVariant::Response,
+                label: None,
+                text: "Blue",
+                modifiers,
+            }])
+        };
+
+        assert_eq!(
+            parse(vec![Token::BracketLiteral("int")]),
+            Err(ParserError::UnexpectedModifier)
+        );
+        assert_eq!(
+            parse(vec![
+                Token::InputLiteral("age"),
+                Token::BracketLiteral("int"),
+                Token::BracketLiteral("date"),
+            ]),
+            Err(ParserError::DuplicateDirective)
+        );
+        assert_eq!(
+            parse(vec![
+                Token::InputLiteral("age"),
+                Token::BracketLiteral("int 1 10"),
+            ]),
+            Err(ParserError::InvalidBlock(ExprError::UnexpectedToken))
+        );
+        assert_eq!(
+            parse(vec![
+                Token::InputLiteral("age"),
+                Token::BracketLiteral("email \"x\""),
+            ]),
+            Err(ParserError::InvalidBlock(ExprError::UnexpectedToken))
+        );
+    }
+
+    #[test]
+    fn fail_to_parse_bad_directives() {
+        let mut parser = Parser::new();
+        let input = vec![Chunk {
+            variant: ChunkVariant::Prompt,
+            label: None,
+            text: "Pick one",
+            modifiers: vec![Token::BracketLiteral("when score > 3")],
+        }];
+        assert_eq!(
+            parser.parse_chunks(input),
+            Err(ParserError::UnknownDirective)
+        );
+
+        let input = vec![Chunk {
+            variant: ChunkVariant::Response,
+            label: None,
+            text: "Pick me",
+            modifiers: vec![Token::BracketLiteral("if a"), Token::BracketLiteral("if b")],
+        }];
+        assert_eq!(
+            parse_response_chunks_greedily(&input),
+            Err(ParserError::DuplicateDirective)
+        );
+    }
+
+    #[test]
+    fn fail_to_parse_bad_blocks() {
+        let input = vec![Chunk {
+            variant: ChunkVariant::Response,
+            label: None,
+            text: "Blue",
+            modifiers: vec![Token::BraceLiteral("colour = ")],
+        }];
+        let result = parse_response_chunks_greedily(&input);
+        assert_eq!(
+            result,
+            Err(ParserError::InvalidBlock(ExprError::UnexpectedEnd))
+        );
+
+        let input = vec![Chunk {
+            variant: ChunkVariant::Prompt,
+            label: None,
+            text: "Pick one",
+            modifiers: vec![Token::BraceLiteral("colour = \"blue\"")],
+        }];
+        let mut parser = Parser::new();
+        let result = parser.parse_chunks(input);
+        assert_eq!(result, Err(ParserError::UnexpectedModifier));
     }
 }
@@ -0,0 +1,171 @@
+use std::fmt::{self, Display};
+
+/// `{name|upper}` and friends, applied left to right.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Filter<'a> {
+    Upper,
+    Lower,
+    /// Used when the variable is unset or empty, from `default:value`.
+    Default(&'a str),
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Placeholder<'a> {
+    pub name: &'a str,
+    pub filters: Vec<Filter<'a>>,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    Placeholder(Placeholder<'a>),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TemplateError {
+    UnclosedPlaceholder,
+    UnmatchedBrace,
+    InvalidVariableName,
+    UnknownFilter(String),
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::UnclosedPlaceholder => write!(f, "unclosed `{{` placeholder"),
+            TemplateError::UnmatchedBrace => write!(f, "unmatched `}}`, use `}}}}` for a brace"),
+            TemplateError::InvalidVariableName => write!(f, "invalid variable name"),
+            TemplateError::UnknownFilter(name) => write!(f, "unknown filter `{name}`"),
+        }
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+}
+
+fn parse_filter(filter: &str) -> Result<Filter<'_>, TemplateError> {
+    let filter = filter.trim();
+    match filter.split_once(':') {
+        Some(("default", value)) => Ok(Filter::Default(value)),
+        None if filter == "upper" => Ok(Filter::Upper),
+        None if filter == "lower" => Ok(Filter::Lower),
+        _ => Err(TemplateError::UnknownFilter(filter.to_string())),
+    }
+}
+
+fn parse_placeholder(data: &str) -> Result<Placeholder<'_>, TemplateError> {
+    let mut parts = data.split('|');
+    let name = parts.next().unwrap_or_default().trim();
+    if !is_variable_name(name) {
+        return Err(TemplateError::InvalidVariableName);
+    }
+
+    let filters = parts.map(parse_filter).collect::<Result<_, _>>()?;
+    Ok(Placeholder { name, filters })
+}
+
+/// Splits prompt or response text into literal text and `{var}`
+/// placeholders. `{{` and `}}` stand for literal braces.
+pub fn parse_template(text: &str) -> Result<Vec<Segment<'_>>, TemplateError> {
+    let bytes = text.as_bytes();
+    let mut segments = vec![];
+    let mut text_start = 0;
+    let mut idx = 0;
+
+    while let Some(byte) = bytes.get(idx) {
+        match (byte, bytes.get(idx + 1)) {
+            (b'{', Some(b'{')) | (b'}', Some(b'}')) => {
+                // Keep the first brace as text and drop the second
+                segments.push(Segment::Text(&text[text_start..idx + 1]));
+                idx += 2;
+                text_start = idx;
+            }
+            (b'{', _) => {
+                let len = text[idx..]
+                    .find('}')
+                    .ok_or(TemplateError::UnclosedPlaceholder)?;
+                segments.push(Segment::Text(&text[text_start..idx]));
+                segments.push(Segment::Placeholder(parse_placeholder(
+                    &text[idx + 1..idx + len],
+                )?));
+                idx += len + 1;
+                text_start = idx;
+            }
+            (b'}', _) => return Err(TemplateError::UnmatchedBrace),
+            _ => idx += 1,
+        }
+    }
+
+    segments.push(Segment::Text(&text[text_start..]));
+    segments.retain(|segment| segment != &Segment::Text(""));
+
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_template, Filter, Placeholder, Segment, TemplateError};
+
+    #[test]
+    fn parse_text_without_placeholders() {
+        let result = parse_template("Happy birthday!");
+        assert_eq!(result, Ok(vec![Segment::Text("Happy birthday!")]));
+    }
+
+    #[test]
+    fn parse_placeholders_with_filters() {
+        let result = parse_template("Happy birthday, {name|default:friend|upper}!");
+
+        let expected_segments = vec![
+            Segment::Text("Happy birthday, "),
+            Segment::Placeholder(Placeholder {
+                name: "name",
+                filters: vec![Filter::Default("friend"), Filter::Upper],
+            }),
+            Segment::Text("!"),
+        ];
+
+        assert_eq!(result, Ok(expected_segments));
+    }
+
+    #[test]
+    fn parse_escaped_braces() {
+        let result = parse_template("{{not a var}} {age}");
+
+        let expected_segments = vec![
+            Segment::Text("{"),
+            Segment::Text("not a var}"),
+            Segment::Text(" "),
+            Segment::Placeholder(Placeholder {
+                name: "age",
+                filters: vec![],
+            }),
+        ];
+
+        assert_eq!(result, Ok(expected_segments));
+    }
+
+    #[test]
+    fn fail_to_parse_bad_templates() {
+        assert_eq!(
+            parse_template("Hi {name"),
+            Err(TemplateError::UnclosedPlaceholder)
+        );
+        assert_eq!(
+            parse_template("Hi name}"),
+            Err(TemplateError::UnmatchedBrace)
+        );
+        assert_eq!(
+            parse_template("Hi {first name}"),
+            Err(TemplateError::InvalidVariableName)
+        );
+        assert_eq!(
+            parse_template("Hi {name|shout}"),
+            Err(TemplateError::UnknownFilter("shout".to_string()))
+        );
+    }
+}
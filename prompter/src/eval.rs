@@ -0,0 +1,191 @@
+use lexer::expr::{BinaryOp, Expr};
+
+use crate::variables::{Value, Variables};
+
+/// Evaluates an expression against the conversation variables. Anything that
+/// can't be worked out, like an unset variable, comparing a string to a
+/// number or an overflow, evaluates to `None` rather than failing.
+pub fn evaluate(expr: &Expr, variables: &Variables) -> Option<Value> {
+    match expr {
+        Expr::Literal(literal) => Some(Value::from(literal)),
+        Expr::Variable(name) => variables.get(name).cloned(),
+        Expr::Not(inner) => Some(Value::Bool(!holds(inner, variables))),
+        Expr::Binary(BinaryOp::And, lhs, rhs) => {
+            Some(Value::Bool(holds(lhs, variables) && holds(rhs, variables)))
+        }
+        Expr::Binary(BinaryOp::Or, lhs, rhs) => {
+            Some(Value::Bool(holds(lhs, variables) || holds(rhs, variables)))
+        }
+        Expr::Binary(op, lhs, rhs) => {
+            let lhs = evaluate(lhs, variables)?;
+            let rhs = evaluate(rhs, variables)?;
+            binary(*op, lhs, rhs)
+        }
+    }
+}
+
+fn binary(op: BinaryOp, lhs: Value, rhs: Value) -> Option<Value> {
+    let ordering = match (&lhs, &rhs) {
+        (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+        (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+
+    match (op, lhs, rhs) {
+        (BinaryOp::Add, Value::Int(a), Value::Int(b)) => a.checked_add(b).map(Value::Int),
+        (BinaryOp::Sub, Value::Int(a), Value::Int(b)) => a.checked_sub(b).map(Value::Int),
+        (BinaryOp::Add | BinaryOp::Sub, _, _) => None,
+        (BinaryOp::Eq, lhs, rhs) => Some(Value::Bool(lhs == rhs)),
+        (BinaryOp::Ne, lhs, rhs) => Some(Value::Bool(lhs != rhs)),
+        (BinaryOp::Lt, _, _) => ordering.map(|o| Value::Bool(o.is_lt())),
+        (BinaryOp::Le, _, _) => ordering.map(|o| Value::Bool(o.is_le())),
+        (BinaryOp::Gt, _, _) => ordering.map(|o| Value::Bool(o.is_gt())),
+        (BinaryOp::Ge, _, _) => ordering.map(|o| Value::Bool(o.is_ge())),
+        (BinaryOp::And | BinaryOp::Or, _, _) => unreachable!("handled by evaluate"),
+    }
+}
+
+/// Whether an expression is truthy: `true`, a non-zero number or a non-empty
+/// string.
+pub fn holds(expr: &Expr, variables: &Variables) -> bool {
+    match evaluate(expr, variables) {
+        Some(Value::Bool(b)) => b,
+        Some(Value::Int(n)) => n != 0,
+        Some(Value::Str(s)) => !s.is_empty(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use lexer::{
+        expr::{BinaryOp, Expr, Literal},
+        parse,
+    };
+
+    use super::{evaluate, holds};
+    use crate::{
+        variables::{Value, Variables},
+        Ending, Prompter, PrompterErr,
+    };
+
+    fn binary<'a>(op: BinaryOp, lhs: Expr<'a>, rhs: Expr<'a>) -> Expr<'a> {
+        Expr::Binary(op, Box::new(lhs), Box::new(rhs))
+    }
+
+    #[test]
+    fn evaluates_against_variables() {
+        let mut variables = Variables::new();
+        variables.set("score", 4);
+        variables.set("name", "Ada");
+
+        let score = binary(
+            BinaryOp::Sub,
+            Expr::Variable("score"),
+            Expr::Literal(Literal::Int(1)),
+        );
+        assert_eq!(evaluate(&score, &variables), Some(Value::Int(3)));
+
+        let name = binary(
+            BinaryOp::Eq,
+            Expr::Variable("name"),
+            Expr::Literal(Literal::Str("Ada")),
+        );
+        assert!(holds(&name, &variables));
+        assert!(!holds(&Expr::Not(Box::new(name)), &variables));
+    }
+
+    #[test]
+    fn unknowns_never_hold() {
+        let variables = Variables::new();
+
+        let missing = binary(
+            BinaryOp::Gt,
+            Expr::Variable("score"),
+            Expr::Literal(Literal::Int(3)),
+        );
+        assert_eq!(evaluate(&missing, &variables), None);
+        assert!(!holds(&missing, &variables));
+
+        let mismatch = binary(
+            BinaryOp::Lt,
+            Expr::Literal(Literal::Str("3")),
+            Expr::Literal(Literal::Int(3)),
+        );
+        assert!(!holds(&mismatch, &variables));
+
+        let overflow = binary(
+            BinaryOp::Add,
+            Expr::Literal(Literal::Int(i64::MAX)),
+            Expr::Literal(Literal::Int(1)),
+        );
+        assert_eq!(evaluate(&overflow, &variables), None);
+    }
+
+    #[test]
+    fn offers_only_available_responses() {
+        let data = read_to_string("./guards_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+
+        let response = prompter.current().responses[0].clone();
+        let lots = prompter.clone().answer(&response).unwrap();
+        assert_eq!(lots.current().text, "Wow, that's a lot of candles!");
+
+        let response = prompter.current().responses[1].clone();
+        let few = prompter.answer(&response).unwrap();
+        assert_eq!(few.current().text, "Still plenty to wish on!");
+        assert_eq!(few.next().responses.len(), 1);
+        assert_eq!(few.current().responses.len(), 2);
+
+        let secret = few.current().responses[1].clone();
+        assert!(matches!(
+            few.clone().answer(&secret),
+            Err(PrompterErr::BadResponse)
+        ));
+    }
+
+    #[test]
+    fn skips_guarded_prompts_until_the_end() {
+        let data = read_to_string("./guards_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap())
+            .unwrap()
+            .with_variable("shy", false);
+
+        let response = prompter.current().responses[0].clone();
+        let lots = prompter.answer(&response).unwrap();
+        let response = lots.current().responses[1].clone();
+        let secret = lots.answer(&response).unwrap();
+        assert_eq!(secret.current().label, Some("SECRET"));
+
+        let response = secret.current().responses[0].clone();
+        let finished = secret.answer(&response).unwrap();
+        assert_eq!(finished.ending(), Some(&Ending::DeadEnd));
+    }
+
+    #[test]
+    fn starts_over_with_preset_variables() {
+        let data = "> (START) \"Regular hi\" [if !vip]\n\
+                    < (END) \"Hi\"\n\
+                    > (VIP) \"Welcome back\"\n\
+                    < (END) \"Thanks\" [if vip]\n";
+        let prompter = Prompter::new(parse(data).unwrap()).unwrap();
+        assert_eq!(prompter.current().text, "Regular hi");
+
+        let vip = prompter.clone().with_variable("vip", true);
+        assert_eq!(vip.current().text, "Welcome back");
+        assert!(!vip.is_finished());
+        assert_eq!(vip.next().responses.len(), 1);
+
+        // Only answer guarded away, until the guard holds
+        let data = "> (START) \"Members only\"\n< (END) \"Enter\" [if vip]\n";
+        let prompter = Prompter::new(parse(data).unwrap()).unwrap();
+        assert_eq!(prompter.ending(), Some(&Ending::DeadEnd));
+        let vip = prompter.with_variable("vip", true);
+        assert_eq!(vip.ending(), None);
+        let response = vip.current().responses[0].clone();
+        assert!(vip.answer(&response).unwrap().is_finished());
+    }
+}
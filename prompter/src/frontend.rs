@@ -0,0 +1,345 @@
+use std::time::Duration;
+
+use crate::{
+    render::RenderedPrompt, select::ResponseId, variables::Variables, Ending, Prompter, PrompterErr,
+};
+
+/// What the user did with a question.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    /// Picked one of the offered responses.
+    Choose(ResponseId),
+    /// Picked several responses of a multi-select question.
+    ChooseMany(Vec<ResponseId>),
+    /// Typed in free text.
+    Type(String),
+    /// Didn't answer within the question's timeout.
+    TimedOut,
+    /// Left the conversation before it ended.
+    Quit,
+}
+
+/// The I/O side of a conversation, `run` does the rest.
+pub trait Frontend {
+    type Error;
+
+    /// Shows a prompt the conversation has reached, statements and the
+    /// closing prompt included.
+    fn show_prompt(&mut self, prompt: &RenderedPrompt) -> Result<(), Self::Error>;
+
+    /// Asks for an answer to the prompt last shown. `timeout` is how long to
+    /// wait before replying `TimedOut`, when the question has one.
+    fn collect_answer(
+        &mut self,
+        prompt: &RenderedPrompt,
+        timeout: Option<Duration>,
+    ) -> Result<Reply, Self::Error>;
+
+    /// Shows how the conversation ended.
+    fn show_end(&mut self, ending: &Ending) -> Result<(), Self::Error>;
+
+    /// Shows why a reply was turned down before asking again. `Display` on
+    /// `InvalidInput` gives the script's retry message.
+    fn show_error(&mut self, _error: &PrompterErr) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Values the host application provides to placeholders.
+    fn host_variables(&self) -> Option<&Variables> {
+        None
+    }
+}
+
+/// The async version of `Frontend`, for UIs that wait on I/O without
+/// blocking.
+#[cfg(feature = "async")]
+pub trait AsyncFrontend {
+    type Error;
+
+    fn show_prompt(
+        &mut self,
+        prompt: &RenderedPrompt,
+    ) -> impl std::future::Future<Output = Result<(), Self::Error>>;
+
+    fn collect_answer(
+        &mut self,
+        prompt: &RenderedPrompt,
+        timeout: Option<Duration>,
+    ) -> impl std::future::Future<Output = Result<Reply, Self::Error>>;
+
+    fn show_end(
+        &mut self,
+        ending: &Ending,
+    ) -> impl std::future::Future<Output = Result<(), Self::Error>>;
+
+    fn show_error(
+        &mut self,
+        _error: &PrompterErr,
+    ) -> impl std::future::Future<Output = Result<(), Self::Error>> {
+        async { Ok(()) }
+    }
+
+    fn host_variables(&self) -> Option<&Variables> {
+        None
+    }
+}
+
+// Where the conversation goes after a prompt has been shown
+enum Step {
+    End,
+    Advance,
+    Ask(Option<Duration>),
+}
+
+fn step(prompter: &Prompter) -> Step {
+    match prompter.is_finished() {
+        true => Step::End,
+        false if prompter.is_statement() => Step::Advance,
+        false => Step::Ask(prompter.timeout_duration()),
+    }
+}
+
+/// Answers the current question with a reply, for hosts that drive the
+/// conversation themselves instead of through `run`. Quitting leaves it where
+/// it is.
+pub fn apply<'a>(prompter: Prompter<'a>, reply: Reply) -> Result<Prompter<'a>, PrompterErr> {
+    match reply {
+        Reply::Choose(id) => {
+            let response = prompter
+                .response_ids()
+                .contains(&id)
+                .then(|| prompter.current().responses[id.index()].clone());
+            match response {
+                Some(response) => prompter.answer(&response),
+                None => Err(PrompterErr::BadResponse),
+            }
+        }
+        Reply::ChooseMany(ids) => prompter.answer_many(&ids),
+        Reply::Type(text) => prompter.answer_input(&text),
+        Reply::TimedOut => prompter.timeout(),
+        Reply::Quit => Ok(prompter),
+    }
+}
+
+/// Runs a conversation to its end over a frontend, handing back the finished
+/// prompter, or the one the user quit on.
+pub fn run<'a, F: Frontend>(
+    mut prompter: Prompter<'a>,
+    frontend: &mut F,
+) -> Result<Prompter<'a>, F::Error> {
+    let empty = Variables::new();
+    let mut shown = None;
+
+    loop {
+        let host = frontend.host_variables().unwrap_or(&empty);
+        let prompt = prompter.render_next_with(host);
+        if shown != Some(prompter.entered) {
+            frontend.show_prompt(&prompt)?;
+            shown = Some(prompter.entered);
+        }
+
+        prompter = match step(&prompter) {
+            Step::End => {
+                let ending = prompter
+                    .ending()
+                    .expect("finished prompters have an ending");
+                frontend.show_end(ending)?;
+                return Ok(prompter);
+            }
+            Step::Advance => prompter.advance().expect("statements can be advanced"),
+            Step::Ask(timeout) => match frontend.collect_answer(&prompt, timeout)? {
+                Reply::Quit => return Ok(prompter),
+                // Turned down replies leave the question as it was
+                reply => match apply(prompter.clone(), reply) {
+                    Ok(answered) => answered,
+                    Err(error) => {
+                        frontend.show_error(&error)?;
+                        prompter
+                    }
+                },
+            },
+        };
+    }
+}
+
+/// Like `run`, over an `AsyncFrontend`. It needs no particular runtime.
+#[cfg(feature = "async")]
+pub async fn run_async<'a, F: AsyncFrontend>(
+    mut prompter: Prompter<'a>,
+    frontend: &mut F,
+) -> Result<Prompter<'a>, F::Error> {
+    let empty = Variables::new();
+    let mut shown = None;
+
+    loop {
+        let host = frontend.host_variables().unwrap_or(&empty);
+        let prompt = prompter.render_next_with(host);
+        if shown != Some(prompter.entered) {
+            frontend.show_prompt(&prompt).await?;
+            shown = Some(prompter.entered);
+        }
+
+        prompter = match step(&prompter) {
+            Step::End => {
+                let ending = prompter
+                    .ending()
+                    .expect("finished prompters have an ending");
+                frontend.show_end(ending).await?;
+                return Ok(prompter);
+            }
+            Step::Advance => prompter.advance().expect("statements can be advanced"),
+            Step::Ask(timeout) => match frontend.collect_answer(&prompt, timeout).await? {
+                Reply::Quit => return Ok(prompter),
+                // Turned down replies leave the question as it was
+                reply => match apply(prompter.clone(), reply) {
+                    Ok(answered) => answered,
+                    Err(error) => {
+                        frontend.show_error(&error).await?;
+                        prompter
+                    }
+                },
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, convert::Infallible, fs::read_to_string, time::Duration};
+
+    use lexer::parse;
+
+    use super::{run, Frontend, Reply};
+    use crate::{render::RenderedPrompt, Ending, Prompter, PrompterErr};
+
+    // Replies with response texts, or the text itself for inputs
+    struct Scripted {
+        replies: VecDeque<&'static str>,
+        log: Vec<String>,
+    }
+
+    impl Scripted {
+        fn new(replies: &[&'static str]) -> Self {
+            Self {
+                replies: replies.iter().copied().collect(),
+                log: vec![],
+            }
+        }
+
+        fn reply(&mut self, prompt: &RenderedPrompt, timeout: Option<Duration>) -> Reply {
+            let Some(reply) = self.replies.pop_front() else {
+                return Reply::Quit;
+            };
+            if reply.is_empty() && timeout.is_some() {
+                return Reply::TimedOut;
+            }
+            match prompt.responses.iter().find(|r| r.text == reply) {
+                Some(response) => Reply::Choose(response.id),
+                None => Reply::Type(reply.to_string()),
+            }
+        }
+    }
+
+    impl Frontend for Scripted {
+        type Error = Infallible;
+
+        fn show_prompt(&mut self, prompt: &RenderedPrompt) -> Result<(), Infallible> {
+            self.log.push(prompt.text.clone());
+            Ok(())
+        }
+
+        fn collect_answer(
+            &mut self,
+            prompt: &RenderedPrompt,
+            timeout: Option<Duration>,
+        ) -> Result<Reply, Infallible> {
+            Ok(self.reply(prompt, timeout))
+        }
+
+        fn show_end(&mut self, ending: &Ending) -> Result<(), Infallible> {
+            self.log.push(format!("{ending:?}"));
+            Ok(())
+        }
+
+        fn show_error(&mut self, error: &PrompterErr) -> Result<(), Infallible> {
+            if let PrompterErr::InvalidInput(invalid) = error {
+                self.log.push(invalid.to_string());
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "async")]
+    impl super::AsyncFrontend for Scripted {
+        type Error = Infallible;
+
+        async fn show_prompt(&mut self, prompt: &RenderedPrompt<'_>) -> Result<(), Infallible> {
+            Frontend::show_prompt(self, prompt)
+        }
+
+        async fn collect_answer(
+            &mut self,
+            prompt: &RenderedPrompt<'_>,
+            timeout: Option<Duration>,
+        ) -> Result<Reply, Infallible> {
+            Ok(self.reply(prompt, timeout))
+        }
+
+        async fn show_end(&mut self, ending: &Ending<'_>) -> Result<(), Infallible> {
+            Frontend::show_end(self, ending)
+        }
+    }
+
+    #[test]
+    fn runs_to_the_end() {
+        let data = read_to_string("./input_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+        let mut frontend = Scripted::new(&["Ada", "200", "37", "Thanks!"]);
+
+        let finished = run(prompter, &mut frontend).unwrap();
+        assert!(finished.is_finished());
+        assert_eq!(finished.variables().get_int("age"), Some(37));
+        assert_eq!(
+            frontend.log[2..],
+            [
+                "Come on Ada, between 1 and 120 please.",
+                "37 candles coming up!",
+                "Completed { outcome: None }",
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_on_quit() {
+        let data = read_to_string("./input_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+        let mut frontend = Scripted::new(&["Ada"]);
+
+        let stopped = run(prompter, &mut frontend).unwrap();
+        assert!(!stopped.is_finished());
+        assert_eq!(frontend.log.len(), 2);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn runs_async_frontends() {
+        use std::{
+            future::Future,
+            pin::pin,
+            task::{Context, Poll, Waker},
+        };
+
+        use super::run_async;
+
+        let data = read_to_string("./input_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+        let mut frontend = Scripted::new(&["Ada", "37"]);
+
+        let mut running = pin!(run_async(prompter, &mut frontend));
+        let mut context = Context::from_waker(Waker::noop());
+        let Poll::Ready(finished) = running.as_mut().poll(&mut context) else {
+            panic!("nothing here waits");
+        };
+        assert_eq!(finished.unwrap().variables().get_int("age"), Some(37));
+    }
+}
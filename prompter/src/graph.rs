@@ -0,0 +1,418 @@
+use std::collections::VecDeque;
+
+use lexer::parser::{Advance, Target};
+
+use crate::{script::Script, Ending};
+
+/// Where an edge of the conversation graph goes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node<'a> {
+    Prompt(usize),
+    /// The conversation ends, through an `END` or `END:outcome` label.
+    End(Option<&'a str>),
+    /// A label no prompt carries.
+    Unresolved(&'a str),
+    /// Falling through past the last prompt.
+    DeadEnd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Following a label, or a statement's `-> LABEL`.
+    Label,
+    /// Moving on to the next prompt without a label.
+    FallThrough,
+    /// One of the labels `[random ...]` or `[bands ...]` can pick.
+    Picked,
+}
+
+/// A way from one prompt to the next, taken by answering with `response` or,
+/// without one, by a statement or multi-select prompt moving on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge<'a> {
+    pub from: usize,
+    pub to: Node<'a>,
+    pub response: Option<usize>,
+    pub kind: EdgeKind,
+}
+
+/// How `Graph::to_dot` and `Graph::to_mermaid` draw prompts.
+#[derive(Debug, Clone, Default)]
+pub struct GraphOptions {
+    /// Texts longer than this many characters are cut short.
+    pub max_text: Option<usize>,
+    /// Only show prompt labels, and no response texts on edges.
+    pub labels_only: bool,
+    /// Mark prompts that can't be reached from the start.
+    pub highlight_unreachable: bool,
+}
+
+/// Every way a script's conversation can go, as `Prompter` would follow it.
+/// Guards aren't evaluated, so every guarded prompt and response is included.
+pub struct Graph<'s, 'a> {
+    script: &'s Script<'a>,
+    pub edges: Vec<Edge<'a>>,
+}
+
+pub(crate) fn target_labels<'a>(target: &Target<'a>) -> Vec<&'a str> {
+    match target {
+        Target::Bands { bands, .. } => bands.iter().map(|band| band.label).collect(),
+        Target::Random(labels) => labels.iter().map(|weighted| weighted.label).collect(),
+        Target::Label(label) => vec![label],
+    }
+}
+
+impl<'s, 'a> Graph<'s, 'a> {
+    pub fn new(script: &'s Script<'a>) -> Self {
+        let mut graph = Self {
+            script,
+            edges: vec![],
+        };
+        for idx in 0..script.prompts().len() {
+            graph.add_edges(idx);
+        }
+        graph
+    }
+
+    fn resolve(&self, label: &'a str) -> Node<'a> {
+        match (self.script.find(label), Ending::from_label(label)) {
+            (Some(idx), _) => Node::Prompt(idx),
+            (None, Some(ending)) => Node::End(ending.outcome()),
+            (None, None) => Node::Unresolved(label),
+        }
+    }
+
+    fn add_edge(&mut self, from: usize, to: Node<'a>, response: Option<usize>, kind: EdgeKind) {
+        self.edges.push(Edge {
+            from,
+            to,
+            response,
+            kind,
+        });
+    }
+
+    // Edges out of a prompt when nothing more specific applies: its own
+    // target, or the next prompt
+    fn add_onward_edges(&mut self, idx: usize, response: Option<usize>) {
+        match &self.script.prompts()[idx].target {
+            Some(Target::Label(label)) => {
+                self.add_edge(idx, self.resolve(label), response, EdgeKind::Label);
+            }
+            Some(target) => {
+                for label in target_labels(target) {
+                    self.add_edge(idx, self.resolve(label), response, EdgeKind::Picked);
+                }
+            }
+            None => {
+                let next = match idx + 1 < self.script.prompts().len() {
+                    true => Node::Prompt(idx + 1),
+                    false => Node::DeadEnd,
+                };
+                self.add_edge(idx, next, response, EdgeKind::FallThrough);
+            }
+        }
+    }
+
+    fn add_edges(&mut self, idx: usize) {
+        let script = self.script;
+        let prompt = &script.prompts()[idx];
+
+        match prompt.advance {
+            Some(Advance::Goto(label)) => {
+                self.add_edge(idx, self.resolve(label), None, EdgeKind::Label);
+                return;
+            }
+            Some(Advance::Next) => return self.add_onward_edges(idx, None),
+            None => (),
+        }
+        // Multi-select answers all go the prompt's way
+        if prompt.multi.is_some() {
+            return self.add_onward_edges(idx, None);
+        }
+
+        for (response_idx, response) in prompt.responses.iter().enumerate() {
+            let response_idx = Some(response_idx);
+            match (&response.target, response.label) {
+                (Some(target), _) => {
+                    for label in target_labels(target) {
+                        let to = self.resolve(label);
+                        self.add_edge(idx, to, response_idx, EdgeKind::Picked);
+                    }
+                }
+                (None, Some(label)) => {
+                    self.add_edge(idx, self.resolve(label), response_idx, EdgeKind::Label);
+                }
+                (None, None) => self.add_onward_edges(idx, response_idx),
+            }
+        }
+    }
+
+    /// Which prompts can be reached from the start, by index.
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.script.prompts().len()];
+        let mut queue = VecDeque::from([self.script.start()]);
+
+        while let Some(idx) = queue.pop_front() {
+            if std::mem::replace(&mut reachable[idx], true) {
+                continue;
+            }
+            for edge in self.edges.iter().filter(|edge| edge.from == idx) {
+                if let Node::Prompt(next) = edge.to {
+                    queue.push_back(next);
+                }
+            }
+        }
+        reachable
+    }
+
+    fn node_id(node: &Node) -> String {
+        let sanitize = |name: &str| -> String {
+            name.chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect()
+        };
+        match node {
+            Node::Prompt(idx) => format!("p{idx}"),
+            Node::End(None) => "end".to_string(),
+            Node::End(Some(outcome)) => format!("end_{}", sanitize(outcome)),
+            Node::Unresolved(label) => format!("missing_{}", sanitize(label)),
+            Node::DeadEnd => "dead_end".to_string(),
+        }
+    }
+
+    // Ending nodes each edge leads to, once each in order of appearance, with
+    // their ids. Labels sanitized the same, like `END:a-b` and `END:a_b`, get
+    // their index on the end of the id to tell them apart.
+    fn endings(&self) -> Vec<(&Node<'a>, String)> {
+        let mut endings: Vec<(&Node, String)> = vec![];
+        for edge in &self.edges {
+            if matches!(edge.to, Node::Prompt(_)) || endings.iter().any(|(n, _)| *n == &edge.to) {
+                continue;
+            }
+            let mut id = Self::node_id(&edge.to);
+            while endings.iter().any(|(_, taken)| *taken == id) {
+                id = format!("{id}_{}", endings.len());
+            }
+            endings.push((&edge.to, id));
+        }
+        endings
+    }
+
+    fn id_in(endings: &[(&Node, String)], node: &Node) -> String {
+        endings
+            .iter()
+            .find(|(ending, _)| *ending == node)
+            .map_or_else(|| Self::node_id(node), |(_, id)| id.clone())
+    }
+
+    fn ending_text(node: &Node) -> String {
+        match node {
+            Node::End(None) => "END".to_string(),
+            Node::End(Some(outcome)) => format!("END:{outcome}"),
+            Node::Unresolved(label) => format!("missing {label}"),
+            Node::DeadEnd => "dead end".to_string(),
+            Node::Prompt(_) => unreachable!("prompts aren't endings"),
+        }
+    }
+
+    // A prompt's label and text as shown, the label on its own with
+    // `labels_only`
+    fn prompt_text(&self, idx: usize, options: &GraphOptions) -> (String, Option<String>) {
+        let prompt = &self.script.prompts()[idx];
+        let label = prompt.label.map_or(format!("#{idx}"), str::to_string);
+        match options.labels_only {
+            true => (label, None),
+            false => (label, Some(collapse(prompt.text, options))),
+        }
+    }
+
+    fn edge_text(&self, edge: &Edge, options: &GraphOptions) -> Option<String> {
+        let response = &self.script.prompts()[edge.from].responses[edge.response?];
+        let text = match &response.input {
+            Some(input) => format!("input {}", input.variable),
+            None => response.text.to_string(),
+        };
+        (!options.labels_only).then(|| collapse(&text, options))
+    }
+
+    /// The graph in Graphviz DOT. Fall-through edges are dashed and picked
+    /// ones dotted.
+    pub fn to_dot(&self, options: &GraphOptions) -> String {
+        let escape = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+        let reachable = self.reachable();
+        let mut out = "digraph promptgen {\n  node [shape=box];\n".to_string();
+
+        for (idx, reachable) in reachable.into_iter().enumerate() {
+            let text = match self.prompt_text(idx, options) {
+                (label, Some(text)) => format!("{}\\n{}", escape(&label), escape(&text)),
+                (label, None) => escape(&label),
+            };
+            let style = match options.highlight_unreachable && !reachable {
+                true => ", style=filled, fillcolor=\"#f4a6a6\"",
+                false => "",
+            };
+            out += &format!("  p{idx} [label=\"{text}\"{style}];\n");
+        }
+        let endings = self.endings();
+        for (node, id) in &endings {
+            let text = escape(&Self::ending_text(node));
+            out += &format!("  {id} [label=\"{text}\", shape=oval];\n");
+        }
+
+        for edge in &self.edges {
+            let mut attributes = vec![];
+            if let Some(text) = self.edge_text(edge, options) {
+                attributes.push(format!("label=\"{}\"", escape(&text)));
+            }
+            match edge.kind {
+                EdgeKind::Label => (),
+                EdgeKind::FallThrough => attributes.push("style=dashed".to_string()),
+                EdgeKind::Picked => attributes.push("style=dotted".to_string()),
+            }
+            let attributes = match attributes.is_empty() {
+                true => String::new(),
+                false => format!(" [{}]", attributes.join(", ")),
+            };
+            out += &format!(
+                "  p{} -> {}{attributes};\n",
+                edge.from,
+                Self::id_in(&endings, &edge.to)
+            );
+        }
+
+        out + "}\n"
+    }
+
+    /// The graph as a Mermaid flowchart. Fall-through and picked edges are
+    /// dotted.
+    pub fn to_mermaid(&self, options: &GraphOptions) -> String {
+        let escape = |text: &str| text.replace('"', "#quot;");
+        let reachable = self.reachable();
+        let mut out = "flowchart TD\n".to_string();
+
+        for idx in 0..self.script.prompts().len() {
+            let text = match self.prompt_text(idx, options) {
+                (label, Some(text)) => format!("{}: {}", escape(&label), escape(&text)),
+                (label, None) => escape(&label),
+            };
+            out += &format!("  p{idx}[\"{text}\"]\n");
+        }
+        let endings = self.endings();
+        for (node, id) in &endings {
+            let text = escape(&Self::ending_text(node));
+            out += &format!("  {id}([\"{text}\"])\n");
+        }
+
+        for edge in &self.edges {
+            let arrow = match edge.kind {
+                EdgeKind::Label => "-->",
+                EdgeKind::FallThrough | EdgeKind::Picked => "-.->",
+            };
+            let text = match self.edge_text(edge, options) {
+                Some(text) => format!("|\"{}\"|", escape(&text)),
+                None => String::new(),
+            };
+            let to = Self::id_in(&endings, &edge.to);
+            out += &format!("  p{} {arrow}{text} {to}\n", edge.from);
+        }
+
+        let unreachable: Vec<String> = (0..reachable.len())
+            .filter(|idx| options.highlight_unreachable && !reachable[*idx])
+            .map(|idx| format!("p{idx}"))
+            .collect();
+        if !unreachable.is_empty() {
+            out += "  classDef unreachable fill:#f4a6a6\n";
+            out += &format!("  class {} unreachable\n", unreachable.join(","));
+        }
+        out
+    }
+}
+
+// Text on one line, cut short to `max_text` characters
+fn collapse(text: &str, options: &GraphOptions) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match options.max_text {
+        Some(max) if text.chars().count() > max => {
+            let cut: String = text.chars().take(max.saturating_sub(1)).collect();
+            format!("{cut}…")
+        }
+        _ => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use lexer::parse;
+
+    use super::{Edge, EdgeKind, Graph, GraphOptions, Node};
+    use crate::script::Script;
+
+    #[test]
+    fn follows_labels_and_fall_through() {
+        let data = "> (START) \"Hi\"\n< (LATER) \"Skip\"\n< \"Next\"\n\
+                    > \"Middle\" ->\n> (LATER) \"Pick\" [random \"END:win\", LOST]\n< \"Go\"\n\
+                    > (ORPHAN) \"Nobody comes here\"\n< \"Ok\"\n";
+        let script = Script::new(parse(data).unwrap()).unwrap();
+        let graph = Graph::new(&script);
+
+        let edge = |from, to, response, kind| Edge {
+            from,
+            to,
+            response,
+            kind,
+        };
+        assert_eq!(
+            graph.edges,
+            [
+                edge(0, Node::Prompt(2), Some(0), EdgeKind::Label),
+                edge(0, Node::Prompt(1), Some(1), EdgeKind::FallThrough),
+                edge(1, Node::Prompt(2), None, EdgeKind::FallThrough),
+                edge(2, Node::End(Some("win")), Some(0), EdgeKind::Picked),
+                edge(2, Node::Unresolved("LOST"), Some(0), EdgeKind::Picked),
+                edge(3, Node::DeadEnd, Some(0), EdgeKind::FallThrough),
+            ]
+        );
+        assert_eq!(graph.reachable(), [true, true, true, false]);
+    }
+
+    #[test]
+    fn tells_apart_labels_sanitized_the_same() {
+        let data = "> (START) \"Hi\"\n< (A-B) \"One\"\n< (A_B) \"Two\"\n< (END:a-b) \"Three\"\n";
+        let script = Script::new(parse(data).unwrap()).unwrap();
+        let mermaid = Graph::new(&script).to_mermaid(&GraphOptions::default());
+
+        assert!(mermaid.contains("  missing_A_B([\"missing A-B\"])\n"));
+        assert!(mermaid.contains("  missing_A_B_1([\"missing A_B\"])\n"));
+        assert!(mermaid.contains("  p0 -->|\"Two\"| missing_A_B_1\n"));
+        assert!(mermaid.contains("  p0 -->|\"Three\"| end_a_b\n"));
+    }
+
+    #[test]
+    fn draws_dot_and_mermaid() {
+        let data = read_to_string("./simple_prompt.txt").unwrap();
+        let script = Script::new(parse(&data).unwrap()).unwrap();
+        let graph = Graph::new(&script);
+
+        let options = GraphOptions {
+            max_text: Some(10),
+            ..Default::default()
+        };
+        let dot = graph.to_dot(&options);
+        assert!(dot.starts_with("digraph promptgen {\n"));
+        assert!(dot.contains("  p0 [label=\"START\\nAre you a…\"];\n"));
+        assert!(dot.contains("  p0 -> p2 [label=\"Yes, I am\"];\n"));
+
+        let options = GraphOptions {
+            labels_only: true,
+            highlight_unreachable: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            graph.to_mermaid(&options),
+            "flowchart TD\n  p0[\"START\"]\n  p1[\"ANS_NO\"]\n  p2[\"YES\"]\n  \
+             p0 --> p2\n  p0 --> p1\n  p1 --> p0\n"
+        );
+    }
+}
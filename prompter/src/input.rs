@@ -0,0 +1,304 @@
+use std::fmt::{self, Display};
+
+use lexer::parser::{Input, InputCheck};
+
+use crate::{pattern::Pattern, render::render, variables::Value, Prompter, PrompterErr};
+
+/// Why typed in text didn't pass an input's check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    NotAnInt,
+    OutOfRange {
+        min: Option<i64>,
+        max: Option<i64>,
+    },
+    NotYesNo,
+    NotADate,
+    NotAnEmail,
+    NotOneOf(Vec<String>),
+    /// Didn't match the `[regex ...]` pattern, or the pattern itself is
+    /// invalid, which `validate` reports.
+    NoMatch,
+}
+
+/// Typed in text that was turned down, with the script's `[retry ...]`
+/// message, placeholders filled in, to show before asking again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidInput {
+    pub mismatch: Mismatch,
+    pub retry: Option<String>,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::NotAnInt => write!(f, "not a whole number"),
+            Mismatch::OutOfRange {
+                min: Some(min),
+                max: Some(max),
+            } => write!(f, "not between {min} and {max}"),
+            Mismatch::OutOfRange { min: Some(min), .. } => write!(f, "less than {min}"),
+            Mismatch::OutOfRange { max: Some(max), .. } => write!(f, "more than {max}"),
+            Mismatch::OutOfRange { .. } => write!(f, "out of range"),
+            Mismatch::NotYesNo => write!(f, "not yes or no"),
+            Mismatch::NotADate => write!(f, "not a YYYY-MM-DD date"),
+            Mismatch::NotAnEmail => write!(f, "not an email address"),
+            Mismatch::NotOneOf(options) => write!(f, "not one of {}", options.join(", ")),
+            Mismatch::NoMatch => write!(f, "not in the expected format"),
+        }
+    }
+}
+
+impl Display for InvalidInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.retry {
+            Some(retry) => f.write_str(retry),
+            None => self.mismatch.fmt(f),
+        }
+    }
+}
+
+fn is_leap_year(year: u32) -> bool {
+    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+}
+
+fn is_date(text: &str) -> bool {
+    let parts: Vec<&str> = text.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return false;
+    };
+    let digits = |part: &str, len| part.len() == len && part.bytes().all(|b| b.is_ascii_digit());
+    if !digits(year, 4) || !digits(month, 2) || !digits(day, 2) {
+        return false;
+    }
+    let (Ok(year), Ok(month), Ok(day)) = (year.parse(), month.parse(), day.parse::<u32>()) else {
+        return false;
+    };
+
+    let days_in_month = match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return false,
+    };
+    (1..=days_in_month).contains(&day)
+}
+
+fn is_email(text: &str) -> bool {
+    let Some((local, domain)) = text.split_once('@') else {
+        return false;
+    };
+
+    !local.is_empty()
+        && !text.contains(char::is_whitespace)
+        && !domain.contains('@')
+        && domain.split('.').count() > 1
+        && domain.split('.').all(|part| !part.is_empty())
+}
+
+/// Checks typed in text, giving the value to store. Surrounding whitespace is
+/// ignored except by `[regex ...]`, and text is stored as is without a check.
+/// A `[regex ...]` pattern is compiled for the one check, prompters use the
+/// ones their script compiled.
+pub fn check(check: Option<&InputCheck>, text: &str) -> Result<Value, Mismatch> {
+    let pattern = match check {
+        Some(InputCheck::Regex(source)) => Pattern::new(source).ok(),
+        _ => None,
+    };
+    check_with(check, pattern.as_ref(), text)
+}
+
+// `pattern` is the compiled `[regex ...]` of the check, if it has one
+fn check_with(
+    check: Option<&InputCheck>,
+    pattern: Option<&Pattern>,
+    text: &str,
+) -> Result<Value, Mismatch> {
+    let trimmed = text.trim();
+
+    match check {
+        None => Ok(Value::from(text)),
+        Some(InputCheck::Int { min, max }) => {
+            let n: i64 = trimmed.parse().map_err(|_| Mismatch::NotAnInt)?;
+            match min.is_none_or(|min| n >= min) && max.is_none_or(|max| n <= max) {
+                true => Ok(Value::Int(n)),
+                false => Err(Mismatch::OutOfRange {
+                    min: *min,
+                    max: *max,
+                }),
+            }
+        }
+        Some(InputCheck::YesNo) => match trimmed.to_lowercase().as_str() {
+            "yes" | "y" => Ok(Value::Bool(true)),
+            "no" | "n" => Ok(Value::Bool(false)),
+            _ => Err(Mismatch::NotYesNo),
+        },
+        Some(InputCheck::Date) if is_date(trimmed) => Ok(Value::from(trimmed)),
+        Some(InputCheck::Date) => Err(Mismatch::NotADate),
+        Some(InputCheck::Email) if is_email(trimmed) => Ok(Value::from(trimmed)),
+        Some(InputCheck::Email) => Err(Mismatch::NotAnEmail),
+        Some(InputCheck::OneOf(options)) => match options.contains(&trimmed) {
+            true => Ok(Value::from(trimmed)),
+            false => Err(Mismatch::NotOneOf(
+                options.iter().map(|o| o.to_string()).collect(),
+            )),
+        },
+        Some(InputCheck::Regex(_)) => match pattern {
+            Some(pattern) if pattern.is_match(text) => Ok(Value::from(text)),
+            _ => Err(Mismatch::NoMatch),
+        },
+    }
+}
+
+impl<'a> Prompter<'a> {
+    // First `input` response on offer right now
+    fn available_input(&self) -> Option<(usize, &Input<'a>)> {
+        self.current()
+            .responses
+            .iter()
+            .enumerate()
+            .filter(|(_, response)| self.is_available(response))
+            .find_map(|(index, response)| Some((index, response.input.as_ref()?)))
+    }
+
+    // The `input` response at `index`, when it's on offer right now
+    fn input_at(&self, index: usize) -> Option<&Input<'a>> {
+        let response = self.current().responses.get(index)?;
+        response
+            .input
+            .as_ref()
+            .filter(|_| self.is_available(response))
+    }
+
+    fn check_against(&self, input: &Input, text: &str) -> Result<Value, PrompterErr> {
+        let pattern = match input.check {
+            Some(InputCheck::Regex(source)) => self.script().pattern(source),
+            _ => None,
+        };
+        check_with(input.check.as_ref(), pattern, text).map_err(|mismatch| {
+            PrompterErr::InvalidInput(InvalidInput {
+                mismatch,
+                retry: input.retry.map(|retry| render(retry, &[self.variables()])),
+            })
+        })
+    }
+
+    /// Checks free text without answering, giving the value `answer_input`
+    /// would store.
+    pub fn check_input(&self, text: &str) -> Result<Value, PrompterErr> {
+        if self.is_finished() {
+            return Err(PrompterErr::NoMoreQ);
+        }
+        let (_, input) = self.available_input().ok_or(PrompterErr::BadResponse)?;
+        self.check_against(input, text)
+    }
+
+    /// Answers with free text, which is checked and stored in the variable of
+    /// the first available `input` response before following it. Blank text
+    /// takes the prompt's `[default]` response instead, when it has one.
+    pub fn answer_input(self, text: &str) -> Result<Prompter<'a>, PrompterErr> {
+        if text.trim().is_empty() && self.default_response().is_some() {
+            return self.timeout();
+        }
+        if self.is_finished() {
+            return Err(PrompterErr::NoMoreQ);
+        }
+        let (index, _) = self.available_input().ok_or(PrompterErr::BadResponse)?;
+        self.answer_input_at(index, text)
+    }
+
+    // Answers the `input` response at `index`, as replaying a transcript
+    // does with the one that was recorded
+    pub(crate) fn answer_input_at(
+        self,
+        index: usize,
+        text: &str,
+    ) -> Result<Prompter<'a>, PrompterErr> {
+        if self.is_finished() {
+            return Err(PrompterErr::NoMoreQ);
+        }
+        let input = self.input_at(index).ok_or(PrompterErr::BadResponse)?;
+        let value = self.check_against(input, text)?;
+        let variable = input.variable;
+
+        Ok(self.take(index, text, Some((variable, value))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use lexer::{parse, parser::InputCheck};
+
+    use super::{check, InvalidInput, Mismatch};
+    use crate::{variables::Value, Prompter, PrompterErr};
+
+    #[test]
+    fn checks_typed_in_text() {
+        let range = InputCheck::Int {
+            min: Some(1),
+            max: Some(120),
+        };
+        assert_eq!(check(Some(&range), " 36 "), Ok(Value::Int(36)));
+        assert_eq!(check(Some(&range), "thirty"), Err(Mismatch::NotAnInt));
+        assert_eq!(
+            check(Some(&range), "0").unwrap_err().to_string(),
+            "not between 1 and 120"
+        );
+
+        assert_eq!(check(Some(&InputCheck::YesNo), "Y"), Ok(Value::Bool(true)));
+        assert_eq!(
+            check(Some(&InputCheck::YesNo), "maybe"),
+            Err(Mismatch::NotYesNo)
+        );
+
+        assert!(check(Some(&InputCheck::Date), "2024-02-29").is_ok());
+        assert!(check(Some(&InputCheck::Date), "2023-02-29").is_err());
+        assert!(check(Some(&InputCheck::Date), "2024-2-1").is_err());
+        assert!(check(Some(&InputCheck::Date), "+024-02-01").is_err());
+        assert!(check(Some(&InputCheck::Date), "2024-+2-01").is_err());
+
+        assert!(check(Some(&InputCheck::Email), "ada@example.com").is_ok());
+        assert!(check(Some(&InputCheck::Email), "ada@example").is_err());
+        assert!(check(Some(&InputCheck::Email), "a da@example.com").is_err());
+
+        let colours = InputCheck::OneOf(vec!["red", "blue"]);
+        assert_eq!(check(Some(&colours), "blue "), Ok(Value::from("blue")));
+        assert_eq!(
+            check(Some(&colours), "green").unwrap_err().to_string(),
+            "not one of red, blue"
+        );
+
+        let code = InputCheck::Regex("[A-Z]{3}");
+        assert_eq!(check(Some(&code), "ABC"), Ok(Value::from("ABC")));
+        assert_eq!(check(Some(&code), " ABC"), Err(Mismatch::NoMatch));
+        assert_eq!(check(None, " as is "), Ok(Value::from(" as is ")));
+    }
+
+    #[test]
+    fn retries_invalid_input() {
+        let data = read_to_string("./input_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+        let greeted = prompter.answer_input("Ada").unwrap();
+
+        let Err(PrompterErr::InvalidInput(invalid)) = greeted.check_input("200") else {
+            panic!("200 should be out of range");
+        };
+        assert_eq!(
+            invalid,
+            InvalidInput {
+                mismatch: Mismatch::OutOfRange {
+                    min: Some(1),
+                    max: Some(120)
+                },
+                retry: Some("Come on Ada, between 1 and 120 please.".to_string()),
+            }
+        );
+
+        let cake = greeted.answer_input("37").unwrap();
+        assert_eq!(cake.current().text, "{age} candles coming up!");
+        assert_eq!(cake.variables().get_int("age"), Some(37));
+    }
+}
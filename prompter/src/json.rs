@@ -0,0 +1,444 @@
+use std::fmt::{self, Display, Write};
+
+/// A minimal JSON value, enough for transcripts and other line-oriented
+/// exports without pulling in a serialization framework.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    // Keys keep their insertion order so output is stable.
+    Object(Vec<(String, Json)>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum JsonError {
+    UnexpectedEnd,
+    UnexpectedCharacter(usize),
+    InvalidNumber(usize),
+    InvalidEscape(usize),
+    TrailingCharacters(usize),
+    /// Arrays and objects nested more than `MAX_DEPTH` deep.
+    TooDeep(usize),
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::UnexpectedEnd => write!(f, "unexpected end of JSON input"),
+            JsonError::UnexpectedCharacter(at) => write!(f, "unexpected character at byte {at}"),
+            JsonError::InvalidNumber(at) => write!(f, "invalid number at byte {at}"),
+            JsonError::InvalidEscape(at) => write!(f, "invalid escape sequence at byte {at}"),
+            JsonError::TrailingCharacters(at) => write!(f, "trailing characters at byte {at}"),
+            JsonError::TooDeep(at) => write!(f, "nested too deeply at byte {at}"),
+        }
+    }
+}
+
+impl Json {
+    pub fn object<K: Into<String>>(fields: impl IntoIterator<Item = (K, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Json::Number(n) if n.fract() == 0.0 => Some(*n as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_i64().and_then(|n| u64::try_from(n).ok())
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Json::Null)
+    }
+
+    pub fn parse(data: &str) -> Result<Json, JsonError> {
+        let mut reader = Reader {
+            data: data.as_bytes(),
+            position: 0,
+            depth: 0,
+        };
+        let value = reader.value()?;
+        reader.skip_whitespace();
+        match reader.position < reader.data.len() {
+            true => Err(JsonError::TrailingCharacters(reader.position)),
+            false => Ok(value),
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<i64> for Json {
+    fn from(value: i64) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map(Into::into).unwrap_or(Json::Null)
+    }
+}
+
+fn write_escaped(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Number(n) if n.is_finite() => write!(f, "{n}"),
+            Json::Number(_) => f.write_str("null"),
+            Json::String(s) => write_escaped(f, s),
+            Json::Array(items) => {
+                f.write_char('[')?;
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(fields) => {
+                f.write_char('{')?;
+                for (idx, (key, value)) in fields.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_escaped(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+/// How deep arrays and objects can nest, keeping untrusted input from
+/// running the recursive reader out of stack.
+pub const MAX_DEPTH: usize = 128;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+    /// Arrays and objects the reader is inside of.
+    depth: usize,
+}
+
+impl Reader<'_> {
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.data.get(self.position) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Result<u8, JsonError> {
+        self.skip_whitespace();
+        self.data
+            .get(self.position)
+            .copied()
+            .ok_or(JsonError::UnexpectedEnd)
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+        match self.peek()? {
+            b if b == byte => {
+                self.position += 1;
+                Ok(())
+            }
+            _ => Err(JsonError::UnexpectedCharacter(self.position)),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, JsonError> {
+        match self.data[self.position..].starts_with(word.as_bytes()) {
+            true => {
+                self.position += word.len();
+                Ok(value)
+            }
+            false => Err(JsonError::UnexpectedCharacter(self.position)),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        match self.peek()? {
+            b'n' => self.keyword("null", Json::Null),
+            b't' => self.keyword("true", Json::Bool(true)),
+            b'f' => self.keyword("false", Json::Bool(false)),
+            b'"' => self.string().map(Json::String),
+            b'[' | b'{' if self.depth == MAX_DEPTH => Err(JsonError::TooDeep(self.position)),
+            b'[' | b'{' => {
+                self.depth += 1;
+                let value = match self.data[self.position] {
+                    b'[' => self.array(),
+                    _ => self.object(),
+                };
+                self.depth -= 1;
+                value
+            }
+            b'-' | b'0'..=b'9' => self.number(),
+            _ => Err(JsonError::UnexpectedCharacter(self.position)),
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = self.position;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') =
+            self.data.get(self.position)
+        {
+            self.position += 1;
+        }
+        std::str::from_utf8(&self.data[start..self.position])
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .map(Json::Number)
+            .ok_or(JsonError::InvalidNumber(start))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits = self
+            .data
+            .get(self.position..self.position + 4)
+            .ok_or(JsonError::UnexpectedEnd)?;
+        let code = std::str::from_utf8(digits)
+            .ok()
+            .and_then(|s| u32::from_str_radix(s, 16).ok())
+            .ok_or(JsonError::InvalidEscape(self.position))?;
+        self.position += 4;
+        Ok(code)
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect(b'"')?;
+        let mut bytes = vec![];
+
+        loop {
+            let byte = *self
+                .data
+                .get(self.position)
+                .ok_or(JsonError::UnexpectedEnd)?;
+            self.position += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escape = *self
+                        .data
+                        .get(self.position)
+                        .ok_or(JsonError::UnexpectedEnd)?;
+                    self.position += 1;
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut code = self.hex4()?;
+                            // Surrogate pairs arrive as two consecutive escapes
+                            if (0xD800..0xDC00).contains(&code)
+                                && self.data[self.position..].starts_with(b"\\u")
+                            {
+                                self.position += 2;
+                                let low = self.hex4()?;
+                                if !(0xDC00..=0xDFFF).contains(&low) {
+                                    return Err(JsonError::InvalidEscape(self.position - 6));
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            char::from_u32(code).ok_or(JsonError::InvalidEscape(self.position))?
+                        }
+                        _ => return Err(JsonError::InvalidEscape(self.position - 1)),
+                    };
+                    let mut buffer = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+                }
+                _ => bytes.push(byte),
+            }
+        }
+
+        // The input was a &str and escapes are re-encoded, so this can't fail
+        Ok(String::from_utf8(bytes).unwrap_or_default())
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.expect(b'[')?;
+        let mut items = vec![];
+        if self.peek()? == b']' {
+            self.position += 1;
+            return Ok(Json::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+            match self.peek()? {
+                b',' => self.position += 1,
+                b']' => {
+                    self.position += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(JsonError::UnexpectedCharacter(self.position)),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.expect(b'{')?;
+        let mut fields = vec![];
+        if self.peek()? == b'}' {
+            self.position += 1;
+            return Ok(Json::Object(fields));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(b':')?;
+            fields.push((key, self.value()?));
+            match self.peek()? {
+                b',' => self.position += 1,
+                b'}' => {
+                    self.position += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err(JsonError::UnexpectedCharacter(self.position)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Json, JsonError, MAX_DEPTH};
+
+    #[test]
+    fn round_trips_values() {
+        let value = Json::object([
+            ("text", Json::from("Say \"hi\"\n")),
+            ("index", Json::from(2usize)),
+            ("label", Json::Null),
+            (
+                "items",
+                Json::Array(vec![Json::Bool(true), Json::Number(-1.5)]),
+            ),
+        ]);
+
+        let encoded = value.to_string();
+        assert_eq!(
+            encoded,
+            r#"{"text":"Say \"hi\"\n","index":2,"label":null,"items":[true,-1.5]}"#
+        );
+        assert_eq!(Json::parse(&encoded), Ok(value));
+    }
+
+    #[test]
+    fn parses_unicode_escapes() {
+        let value = Json::parse(r#""caf\u00e9 \ud83c\udf82""#).unwrap();
+        assert_eq!(value.as_str(), Some("caf\u{e9} \u{1f382}"));
+        assert_eq!(
+            Json::parse(r#""\ud800\u0041""#),
+            Err(JsonError::InvalidEscape(7))
+        );
+    }
+
+    #[test]
+    fn fails_on_bad_input() {
+        assert_eq!(Json::parse("{\"a\":"), Err(JsonError::UnexpectedEnd));
+        assert_eq!(Json::parse("[1] 2"), Err(JsonError::TrailingCharacters(4)));
+        assert_eq!(Json::parse("{a:1}"), Err(JsonError::UnexpectedCharacter(1)));
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            Json::parse(&nested(MAX_DEPTH + 1)),
+            Err(JsonError::TooDeep(MAX_DEPTH))
+        );
+        assert_eq!(
+            Json::parse(&"[{\"a\":".repeat(60_000)),
+            Err(JsonError::TooDeep(384))
+        );
+    }
+}
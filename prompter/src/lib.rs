@@ -1,4 +1,37 @@
-use lexer::parser::{Prompt, Response};
+use std::{
+    fmt::{self, Display},
+    sync::Arc,
+};
+
+use input::InvalidInput;
+use lexer::{
+    expr::Expr,
+    parser::{Advance, Prompt, Response, Selection, Target},
+};
+use observer::PrompterObserver;
+use random::{Random, SeededRandom};
+use script::Script;
+use transcript::{Transcript, TranscriptEntry};
+use variables::{Value, Variables};
+
+pub mod eval;
+pub mod frontend;
+pub mod graph;
+pub mod input;
+pub mod json;
+pub mod observer;
+pub mod owned;
+pub mod pattern;
+pub mod random;
+pub mod render;
+pub mod scenario;
+pub mod score;
+pub mod script;
+pub mod select;
+pub mod timeout;
+pub mod transcript;
+pub mod validate;
+pub mod variables;
 
 #[derive(Debug)]
 pub struct PromptStartErr;
@@ -6,74 +39,493 @@ pub struct PromptStartErr;
 #[derive(Debug)]
 pub enum PrompterErr {
     BadResponse,
+    /// The response takes free text, use `answer_input` instead.
+    InputRequired,
+    /// The free text didn't pass the input's check.
+    InvalidInput(InvalidInput),
+    /// The conversation has already ended.
     NoMoreQ,
+    /// Only statements can be advanced, questions are answered.
+    NotAStatement,
+    /// The prompt is multi-select, use `answer_many` instead.
+    SelectionRequired,
+    /// Too few or too many responses for the multi-select prompt.
+    SelectionCount(Selection),
+    /// There's no `[default]` response to take.
+    NoDefault,
+}
+
+impl Display for PrompterErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrompterErr::BadResponse => write!(f, "that's not one of the answers on offer"),
+            PrompterErr::InputRequired => write!(f, "the answer takes typed in text"),
+            PrompterErr::InvalidInput(invalid) => invalid.fmt(f),
+            PrompterErr::NoMoreQ => write!(f, "the conversation has already ended"),
+            PrompterErr::NotAStatement => write!(f, "only statements move on by themselves"),
+            PrompterErr::SelectionRequired => write!(f, "the question takes several answers"),
+            PrompterErr::SelectionCount(Selection { min, max: None }) => {
+                write!(f, "pick at least {min} answers")
+            }
+            PrompterErr::SelectionCount(Selection {
+                min,
+                max: Some(max),
+            }) => {
+                write!(f, "pick {min} to {max} answers")
+            }
+            PrompterErr::NoDefault => write!(f, "the question has no default answer"),
+        }
+    }
+}
+
+impl PrompterErr {
+    /// A stable name for the error, for hosts and test files to match on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            PrompterErr::BadResponse => "bad_response",
+            PrompterErr::InputRequired => "input_required",
+            PrompterErr::InvalidInput(_) => "invalid_input",
+            PrompterErr::NoMoreQ => "finished",
+            PrompterErr::NotAStatement => "not_a_statement",
+            PrompterErr::SelectionRequired => "selection_required",
+            PrompterErr::SelectionCount(_) => "selection_count",
+            PrompterErr::NoDefault => "no_default",
+        }
+    }
+}
+
+const END_LABEL: &str = "END";
+
+/// How a conversation ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ending<'a> {
+    /// Reached a prompt without responses or an `END` label. `(END:happy)`
+    /// completes with the `happy` outcome.
+    Completed { outcome: Option<&'a str> },
+    /// A label-less response on the last prompt had nowhere to fall through to.
+    DeadEnd,
+    /// A response pointed at a label no prompt carries.
+    UnresolvedLabel(&'a str),
+    /// Statements led back to one already shown without asking anything in
+    /// between.
+    Looped,
+}
+
+impl<'a> Ending<'a> {
+    /// The ending an `END` or `END:outcome` label stands for.
+    pub(crate) fn from_label(label: &'a str) -> Option<Ending<'a>> {
+        match label.split_once(':') {
+            Some((END_LABEL, outcome)) => Some(Ending::Completed {
+                outcome: Some(outcome),
+            }),
+            None if label == END_LABEL => Some(Ending::Completed { outcome: None }),
+            _ => None,
+        }
+    }
+
+    /// A stable name for the kind of ending, like `PrompterErr::code`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Ending::Completed { .. } => "completed",
+            Ending::DeadEnd => "dead_end",
+            Ending::UnresolvedLabel(_) => "unresolved_label",
+            Ending::Looped => "looped",
+        }
+    }
+
+    pub fn outcome(&self) -> Option<&'a str> {
+        match self {
+            Ending::Completed { outcome } => *outcome,
+            _ => None,
+        }
+    }
 }
 
+/// A cursor into a shared `Script`. Cloning a prompter is cheap, the prompts
+/// themselves are never copied.
 #[derive(Clone)]
 pub struct Prompter<'a> {
-    prompts: Vec<Prompt<'a>>,
-    next: Prompt<'a>,
+    script: Arc<Script<'a>>,
     next_idx: usize,
+    ending: Option<Ending<'a>>,
+    variables: Variables,
+    transcript: Option<Transcript>,
+    observers: Vec<Arc<dyn PrompterObserver>>,
+    random: Random,
+    /// Order the current prompt's responses are offered in when shuffled.
+    order: Option<Vec<usize>>,
+    /// Statements advanced through since the last answer.
+    statements_seen: Vec<usize>,
+    /// Prompts entered after the first, telling a new prompt from one that's
+    /// still being answered.
+    entered: usize,
+    /// Whether the conversation has moved on from where it started.
+    moved: bool,
 }
 
-const STARTING_LABEL: &str = "START";
-
 impl<'a> Prompter<'a> {
-    pub fn new(prompts: Vec<Prompt<'a>>) -> Result<Prompter, PromptStartErr> {
-        let (start_idx, start) = prompts
-            .clone()
-            .into_iter()
-            .enumerate()
-            .find(|(_, item)| item.label == Some(STARTING_LABEL))
-            .or_else(|| prompts.first().map(|x| (0, x.clone())))
-            .ok_or(PromptStartErr)?;
+    pub fn new(prompts: Vec<Prompt<'a>>) -> Result<Prompter<'a>, PromptStartErr> {
+        Ok(Self::from_script(Arc::new(Script::new(prompts)?)))
+    }
+
+    pub fn from_script(script: Arc<Script<'a>>) -> Prompter<'a> {
+        let mut prompter = Self {
+            next_idx: script.start(),
+            script,
+            ending: None,
+            variables: Variables::new(),
+            transcript: None,
+            observers: vec![],
+            random: Random::new(SeededRandom::from_entropy()),
+            order: None,
+            statements_seen: vec![],
+            entered: 0,
+            moved: false,
+        };
+        (prompter.next_idx, prompter.ending) = prompter.starting_point();
+        prompter.arrange();
+        prompter
+    }
+
+    // The first prompt whose guard holds, and how that ends the conversation
+    // straight away if it does
+    fn starting_point(&self) -> (usize, Option<Ending<'a>>) {
+        let start = self.script.start();
+        match self.skip_guarded(start) {
+            Some(idx) => (idx, self.ending_at(idx)),
+            None => (start, Some(Ending::DeadEnd)),
+        }
+    }
+
+    pub fn script(&self) -> &Arc<Script<'a>> {
+        &self.script
+    }
+
+    /// Starts recording every prompt shown and answer given from here on.
+    pub fn with_transcript(mut self) -> Self {
+        let mut transcript = Transcript::new();
+        transcript.push(Self::prompt_entry(self.current()));
+        self.transcript = Some(transcript);
+        self
+    }
+
+    /// Attaches an observer, which is told about the current prompt straight
+    /// away and about every transition after that.
+    pub fn with_observer(mut self, observer: Arc<dyn PrompterObserver>) -> Self {
+        observer.prompt_entered(self.current());
+        if let Some(ending) = &self.ending {
+            observer.finished(ending);
+        }
+        self.observers.push(observer);
+        self
+    }
 
-        Ok(Self {
-            prompts,
-            next: start,
-            next_idx: start_idx,
-        })
+    /// Presets a variable, e.g. with something the host application knows.
+    /// Until the conversation moves on, where it starts is worked out again
+    /// so that guards on the first prompts see the variable.
+    pub fn with_variable(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.variables.set(name, value);
+        if !self.moved {
+            self.restart();
+        }
+        self
+    }
+
+    fn restart(&mut self) {
+        let (next_idx, ending) = self.starting_point();
+        if next_idx != self.next_idx {
+            self.next_idx = next_idx;
+            self.arrange();
+            if self.transcript.is_some() {
+                let mut transcript = Transcript::new();
+                transcript.push(Self::prompt_entry(self.current()));
+                self.transcript = Some(transcript);
+            }
+            for observer in &self.observers {
+                observer.prompt_entered(self.current());
+            }
+        }
+        if ending != self.ending {
+            if let Some(ending) = &ending {
+                for observer in &self.observers {
+                    observer.finished(ending);
+                }
+            }
+            self.ending = ending;
+        }
     }
 
+    pub fn variables(&self) -> &Variables {
+        &self.variables
+    }
+
+    pub fn transcript(&self) -> Option<&Transcript> {
+        self.transcript.as_ref()
+    }
+
+    /// The current prompt with only the responses that are available right
+    /// now.
     pub fn next(&self) -> Prompt<'a> {
-        self.next.clone()
+        let mut prompt = self.current().clone();
+        prompt.responses = self
+            .offered()
+            .map(|idx| self.current().responses[idx].clone())
+            .collect();
+        prompt
+    }
+
+    // Indices of the current prompt's available responses, in the order
+    // they're offered
+    fn offered(&self) -> impl Iterator<Item = usize> + '_ {
+        let responses = &self.current().responses;
+        let order: Box<dyn Iterator<Item = usize>> = match &self.order {
+            Some(order) => Box::new(order.iter().copied()),
+            None => Box::new(0..responses.len()),
+        };
+        order.filter(|idx| self.is_available(&responses[*idx]))
+    }
+
+    pub fn is_available(&self, response: &Response) -> bool {
+        self.holds(&response.guard)
+    }
+
+    fn holds(&self, guard: &Option<Expr>) -> bool {
+        guard
+            .as_ref()
+            .is_none_or(|guard| eval::holds(guard, &self.variables))
+    }
+
+    // First prompt from `idx` on whose guard holds
+    fn skip_guarded(&self, idx: usize) -> Option<usize> {
+        (idx..self.script.prompts().len())
+            .find(|idx| self.holds(&self.script.prompts()[*idx].guard))
+    }
+
+    /// Borrowing counterpart of `next`. Once the conversation has ended this
+    /// is the last prompt that was shown.
+    pub fn current(&self) -> &Prompt<'a> {
+        &self.script.prompts()[self.next_idx]
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.ending.is_some()
+    }
+
+    pub fn ending(&self) -> Option<&Ending<'a>> {
+        self.ending.as_ref()
+    }
+
+    /// Whether the current prompt is a statement, shown without responses
+    /// until `advance` moves on from it.
+    pub fn is_statement(&self) -> bool {
+        !self.is_finished() && self.current().advance.is_some()
+    }
+
+    /// Moves on from a statement, to its `-> LABEL` or otherwise the next
+    /// prompt. Coming back round to a statement already advanced through
+    /// since the last answer ends the conversation as `Looped`.
+    pub fn advance(mut self) -> Result<Prompter<'a>, PrompterErr> {
+        if self.is_finished() {
+            return Err(PrompterErr::NoMoreQ);
+        }
+        let Some(advance) = self.current().advance else {
+            return Err(PrompterErr::NotAStatement);
+        };
+
+        self.statements_seen.push(self.next_idx);
+        let label = match advance {
+            Advance::Next => None,
+            Advance::Goto(label) => Some(label),
+        };
+        let next = self.follow_label(label);
+        self.move_on(next);
+
+        if self.is_statement() && self.statements_seen.contains(&self.next_idx) {
+            self.finish(Ending::Looped);
+        }
+        Ok(self)
+    }
+
+    fn prompt_entry(prompt: &Prompt) -> TranscriptEntry {
+        TranscriptEntry::Prompt {
+            label: prompt.label.map(str::to_string),
+            text: prompt.text.to_string(),
+        }
+    }
+
+    // A prompt without responses ends the conversation, with an outcome when
+    // it's labelled like `(END:happy)`. One whose responses are all guarded
+    // away is stuck.
+    fn ending_at(&self, idx: usize) -> Option<Ending<'a>> {
+        let current = &self.script.prompts()[idx];
+        if current.advance.is_some() {
+            return None;
+        }
+        if current.responses.is_empty() {
+            return current
+                .label
+                .and_then(Ending::from_label)
+                .or(Some(Ending::Completed { outcome: None }));
+        }
+
+        match current.responses.iter().any(|r| self.is_available(r)) {
+            true => None,
+            false => Some(Ending::DeadEnd),
+        }
     }
 
     pub fn answer(self, response: &Response) -> Result<Prompter<'a>, PrompterErr> {
+        if self.is_finished() {
+            return Err(PrompterErr::NoMoreQ);
+        }
+        if response.input.is_some() {
+            return Err(PrompterErr::InputRequired);
+        }
+        if self.current().multi.is_some() {
+            return Err(PrompterErr::SelectionRequired);
+        }
+
+        let index = self
+            .current()
+            .responses
+            .iter()
+            .position(|r| r == response)
+            .filter(|_| self.is_available(response))
+            .ok_or(PrompterErr::BadResponse)?;
+
+        Ok(self.take(index, response.text, None))
+    }
+
+    // Moves on with the response at `index` of the current prompt, `text`
+    // being what the user picked or typed and `stored` what input keeps of it
+    fn take(mut self, index: usize, text: &str, stored: Option<(&str, Value)>) -> Prompter<'a> {
+        // Follow the script's own copy of the response, which lives as long
+        // as the labels an ending can borrow
+        let script = Arc::clone(&self.script);
+        let response = &script.prompts()[self.next_idx].responses[index];
+
+        self.record(TranscriptEntry::Answer {
+            index,
+            text: text.to_string(),
+        });
+        self.choose(index, response);
+        if let Some((variable, value)) = stored {
+            self.variables.set(variable, value);
+        }
+
+        let next = self.follow(response);
+        self.move_on(next);
+        self
+    }
+
+    // Answers are recorded, and start a fresh run of statements
+    fn record(&mut self, entry: TranscriptEntry) {
+        self.statements_seen.clear();
+        if let Some(transcript) = self.transcript.as_mut() {
+            transcript.push(entry);
+        }
+    }
+
+    // Everything choosing a response does short of moving on
+    fn choose(&mut self, index: usize, response: &Response<'a>) {
+        for observer in &self.observers {
+            observer.answer_chosen(self.current(), index, response);
+        }
+
+        for assignment in &response.assignments {
+            self.variables
+                .set(assignment.name, Value::from(&assignment.value));
+        }
+        for delta in &response.scores {
+            let score = self.score(delta.name).saturating_add(delta.delta);
+            self.variables.set(delta.name, score);
+        }
+    }
+
+    fn move_on(&mut self, next: Result<usize, Ending<'a>>) {
+        self.moved = true;
+        match next {
+            Ok(next_idx) => self.enter(next_idx),
+            Err(ending) => self.finish(ending),
+        }
+    }
+
+    fn follow(&self, response: &Response<'a>) -> Result<usize, Ending<'a>> {
         // When there is a label try to find the question with the given label
         // otherwise, move on to the next question
+        let label = match (&response.target, response.label) {
+            (Some(target), _) => Some(self.resolve_target(target).ok_or(Ending::DeadEnd)?),
+            (None, label) => label,
+        };
+        self.follow_label(label)
+    }
+
+    // Without a label the current prompt's own target is followed, if it has
+    // one
+    fn follow_label(&self, label: Option<&'a str>) -> Result<usize, Ending<'a>> {
+        let label = match (label, &self.current().target) {
+            (None, Some(target)) => Some(self.resolve_target(target).ok_or(Ending::DeadEnd)?),
+            (label, _) => label,
+        };
+        match label {
+            Some(label) => {
+                let next_idx = self.script.find(label).ok_or_else(|| {
+                    Ending::from_label(label).unwrap_or(Ending::UnresolvedLabel(label))
+                })?;
+                for observer in &self.observers {
+                    observer.label_resolved(label, &self.script.prompts()[next_idx]);
+                }
+                Ok(next_idx)
+            }
+            None => {
+                let next_idx = self.next_idx + 1;
+                let next = self.script.get(next_idx).ok_or(Ending::DeadEnd)?;
+                for observer in &self.observers {
+                    observer.fell_through(self.current(), next);
+                }
+                Ok(next_idx)
+            }
+        }
+    }
+
+    // The label a worked out target goes to, `None` when there's none to pick
+    fn resolve_target(&self, target: &Target<'a>) -> Option<&'a str> {
+        match target {
+            Target::Bands { score, bands } => self.band_label(*score, bands),
+            Target::Random(labels) => self.pick(labels),
+            Target::Label(label) => Some(label),
+        }
+    }
 
-        if let Some(label) = response.label {
-            let (next_idx, next_prompt) = self
-                .prompts
-                .clone()
-                .into_iter()
-                .enumerate()
-                .find(|(_, item)| item.label == Some(label))
-                .ok_or(PrompterErr::NoMoreQ)?;
-
-            let result = Self {
-                next: next_prompt,
-                next_idx,
-                ..self
-            };
-
-            return Ok(result);
-        }
-
-        let next_prompt = self
-            .prompts
-            .get(self.next_idx + 1)
-            .ok_or(PrompterErr::NoMoreQ)?
-            .clone();
-
-        let result = Self {
-            next: next_prompt,
-            next_idx: self.next_idx + 1,
-            ..self
+    fn enter(&mut self, next_idx: usize) {
+        let Some(next_idx) = self.skip_guarded(next_idx) else {
+            self.finish(Ending::DeadEnd);
+            return;
         };
 
-        return Ok(result);
+        self.next_idx = next_idx;
+        self.entered += 1;
+        self.arrange();
+        let script = Arc::clone(&self.script);
+        let next = &script.prompts()[next_idx];
+
+        if let Some(transcript) = self.transcript.as_mut() {
+            transcript.push(Self::prompt_entry(next));
+        }
+        for observer in &self.observers {
+            observer.prompt_entered(next);
+        }
+
+        if let Some(ending) = self.ending_at(next_idx) {
+            self.finish(ending);
+        }
+    }
+
+    fn finish(&mut self, ending: Ending<'a>) {
+        for observer in &self.observers {
+            observer.finished(&ending);
+        }
+        self.ending = Some(ending);
     }
 }
 
@@ -81,9 +533,11 @@ impl<'a> Prompter<'a> {
 mod tests {
     use std::fs::read_to_string;
 
+    use std::{sync::Arc, thread};
+
     use lexer::parse;
 
-    use crate::Prompter;
+    use crate::{script::Script, Ending, Prompter, PrompterErr};
 
     #[test]
     fn it_works_with_looping() {
@@ -91,19 +545,19 @@ mod tests {
         let prompts = parse(&data).unwrap();
         let seed_prompt = Prompter::new(prompts).unwrap();
 
-        assert_eq!(seed_prompt.next.label, Some("START"));
+        assert_eq!(seed_prompt.current().label, Some("START"));
 
         let next_prompt = seed_prompt
             .clone()
-            .answer(&seed_prompt.next.responses[1])
+            .answer(&seed_prompt.current().responses[1])
             .unwrap();
-        assert_eq!(next_prompt.next.label, Some("ANS_NO"));
+        assert_eq!(next_prompt.current().label, Some("ANS_NO"));
 
         let next_prompt = next_prompt
             .clone()
-            .answer(&next_prompt.next.responses[0])
+            .answer(&next_prompt.current().responses[0])
             .unwrap();
-        assert_eq!(next_prompt.next.label, Some("START"));
+        assert_eq!(next_prompt.current().label, Some("START"));
     }
 
     #[test]
@@ -114,12 +568,17 @@ mod tests {
 
         let next_prompt = seed_prompt
             .clone()
-            .answer(&seed_prompt.next.responses[0].clone())
+            .answer(&seed_prompt.current().responses[0].clone())
             .unwrap();
 
-        assert_eq!(next_prompt.next.label, Some("YES"));
-        assert_eq!(next_prompt.next.text, "Nice! Glad to meet you human!");
-        assert_eq!(next_prompt.next.responses.len(), 0);
+        assert_eq!(next_prompt.current().label, Some("YES"));
+        assert_eq!(next_prompt.current().text, "Nice! Glad to meet you human!");
+        assert_eq!(next_prompt.current().responses.len(), 0);
+        assert!(next_prompt.is_finished());
+        assert_eq!(
+            next_prompt.ending(),
+            Some(&Ending::Completed { outcome: None })
+        );
     }
 
     #[test]
@@ -127,15 +586,170 @@ mod tests {
         let data = read_to_string("./labelless_prompt.txt").unwrap();
         let prompts = parse(&data).unwrap();
         let seed_prompt = Prompter::new(prompts).unwrap();
-        assert_eq!(seed_prompt.next.text, "Are you a human?");
+        assert_eq!(seed_prompt.current().text, "Are you a human?");
 
         let next_prompt = seed_prompt
             .clone()
-            .answer(&seed_prompt.next.responses[0])
+            .answer(&seed_prompt.current().responses[0])
             .unwrap();
-        assert_eq!(next_prompt.next.label, None);
-        assert_eq!(next_prompt.next.text, "Nice! Glad to meet you human!");
+        assert_eq!(next_prompt.current().label, None);
+        assert_eq!(next_prompt.current().text, "Nice! Glad to meet you human!");
     }
 
-    // Write test for the error cases
+    #[test]
+    fn shares_one_script_between_sessions() {
+        let data = read_to_string("./simple_prompt.txt").unwrap();
+        let script = Arc::new(Script::new(parse(&data).unwrap()).unwrap());
+
+        thread::scope(|scope| {
+            for choice in 0..2 {
+                let prompter = Prompter::from_script(Arc::clone(&script));
+                scope.spawn(move || {
+                    let response = prompter.current().responses[choice].clone();
+                    let next_prompt = prompter.answer(&response).unwrap();
+                    assert_eq!(next_prompt.current().label, response.label);
+                });
+            }
+        });
+
+        assert_eq!(Arc::strong_count(&script), 1);
+    }
+
+    #[test]
+    fn ends_with_named_outcome() {
+        let data = read_to_string("./outcome_prompt.txt").unwrap();
+        let seed_prompt = Prompter::new(parse(&data).unwrap()).unwrap();
+        assert!(!seed_prompt.is_finished());
+
+        let happy = seed_prompt
+            .clone()
+            .answer(&seed_prompt.current().responses[0])
+            .unwrap();
+        assert_eq!(happy.ending().and_then(Ending::outcome), Some("happy"));
+        assert_eq!(happy.current().label, Some("START"));
+
+        let sad = seed_prompt
+            .clone()
+            .answer(&seed_prompt.current().responses[1])
+            .unwrap();
+        let hugged = sad.clone().answer(&sad.current().responses[0]).unwrap();
+        assert_eq!(hugged.current().text, "Here's a big hug!");
+        assert_eq!(hugged.ending().and_then(Ending::outcome), Some("hugged"));
+    }
+
+    #[test]
+    fn ends_on_dead_end_and_unresolved_label() {
+        let data = read_to_string("./outcome_prompt.txt").unwrap();
+        let seed_prompt = Prompter::new(parse(&data).unwrap()).unwrap();
+
+        let unresolved = seed_prompt
+            .clone()
+            .answer(&seed_prompt.current().responses[2])
+            .unwrap();
+        assert_eq!(
+            unresolved.ending(),
+            Some(&Ending::UnresolvedLabel("MISSING"))
+        );
+
+        let sad = seed_prompt
+            .clone()
+            .answer(&seed_prompt.current().responses[1])
+            .unwrap();
+        let dead_end = sad.clone().answer(&sad.current().responses[1]).unwrap();
+        assert_eq!(dead_end.ending(), Some(&Ending::DeadEnd));
+    }
+
+    #[test]
+    fn fails_to_answer_after_the_end_or_with_a_stranger() {
+        let data = read_to_string("./outcome_prompt.txt").unwrap();
+        let seed_prompt = Prompter::new(parse(&data).unwrap()).unwrap();
+        let response = seed_prompt.current().responses[0].clone();
+
+        let stranger = seed_prompt.script().prompts()[2].responses[1].clone();
+        assert!(matches!(
+            seed_prompt.clone().answer(&stranger),
+            Err(PrompterErr::BadResponse)
+        ));
+
+        let finished = seed_prompt.answer(&response).unwrap();
+        assert!(matches!(
+            finished.answer(&response),
+            Err(PrompterErr::NoMoreQ)
+        ));
+    }
+
+    #[test]
+    fn stores_free_text_input() {
+        let data = read_to_string("./input_prompt.txt").unwrap();
+        let seed_prompt = Prompter::new(parse(&data).unwrap()).unwrap();
+
+        let input = seed_prompt.current().responses[0].clone();
+        assert!(matches!(
+            seed_prompt.clone().answer(&input),
+            Err(PrompterErr::InputRequired)
+        ));
+
+        let greeted = seed_prompt.answer_input("Ada").unwrap();
+        assert_eq!(greeted.current().label, Some("GREET"));
+        assert_eq!(greeted.variables().get_str("name"), Some("Ada"));
+
+        let cake = greeted.answer_input("36").unwrap();
+        assert!(matches!(
+            cake.answer_input("Grace"),
+            Err(PrompterErr::BadResponse)
+        ));
+    }
+
+    #[test]
+    fn advances_through_statements() {
+        let data = read_to_string("./story_prompt.txt").unwrap();
+        let seed_prompt = Prompter::new(parse(&data).unwrap())
+            .unwrap()
+            .with_transcript();
+        assert!(seed_prompt.is_statement());
+        assert!(seed_prompt.next().responses.is_empty());
+
+        let next_prompt = seed_prompt.advance().unwrap().advance().unwrap();
+        assert_eq!(next_prompt.current().label, Some("PARTY"));
+        assert!(!next_prompt.is_statement());
+        assert!(matches!(
+            next_prompt.clone().advance(),
+            Err(PrompterErr::NotAStatement)
+        ));
+
+        let cake = next_prompt
+            .clone()
+            .answer(&next_prompt.current().responses[0])
+            .unwrap();
+        assert_eq!(cake.ending(), Some(&Ending::Completed { outcome: None }));
+        assert_eq!(cake.transcript().unwrap().entries().len(), 5);
+
+        // Replaying goes through the statements by itself
+        let replayed = Prompter::new(parse(&data).unwrap())
+            .unwrap()
+            .replay(cake.transcript().unwrap())
+            .ok()
+            .unwrap();
+        assert_eq!(replayed.current().label, Some("CAKE"));
+    }
+
+    #[test]
+    fn ends_statement_loops() {
+        let data = read_to_string("./story_prompt.txt").unwrap();
+        let seed_prompt = Prompter::new(parse(&data).unwrap()).unwrap();
+        let party = seed_prompt.advance().unwrap().advance().unwrap();
+
+        let looping = party
+            .clone()
+            .answer(&party.current().responses[1])
+            .unwrap()
+            .advance()
+            .unwrap();
+        assert_eq!(looping.current().label, Some("AGAIN"));
+
+        let looped = looping.advance().unwrap();
+        assert_eq!(looped.ending(), Some(&Ending::Looped));
+        assert_eq!(looped.current().label, Some("LOOP"));
+        assert!(!looped.is_statement());
+    }
 }
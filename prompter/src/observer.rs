@@ -0,0 +1,132 @@
+use lexer::parser::{Prompt, Response};
+
+use crate::Ending;
+
+/// Hooks into a `Prompter`'s transitions. Every method defaults to a no-op so
+/// implementors only pick the events they care about.
+///
+/// Observers are shared between a prompter and all of its clones, so they
+/// take `&self` and need their own interior mutability for any state.
+pub trait PrompterObserver: Send + Sync {
+    /// A prompt became the current one, including the prompt a conversation
+    /// is already at when the observer is attached.
+    fn prompt_entered(&self, _prompt: &Prompt) {}
+
+    fn answer_chosen(&self, _prompt: &Prompt, _index: usize, _response: &Response) {}
+
+    /// A response label was found in the script.
+    fn label_resolved(&self, _label: &str, _prompt: &Prompt) {}
+
+    /// A label-less response moved on to the following prompt.
+    fn fell_through(&self, _from: &Prompt, _to: &Prompt) {}
+
+    /// The conversation can't go any further.
+    fn finished(&self, _ending: &Ending) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::read_to_string,
+        sync::{Arc, Mutex},
+    };
+
+    use lexer::{
+        parse,
+        parser::{Prompt, Response},
+    };
+
+    use super::PrompterObserver;
+    use crate::{Ending, Prompter};
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl PrompterObserver for Recorder {
+        fn prompt_entered(&self, prompt: &Prompt) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("enter {}", prompt.text));
+        }
+
+        fn answer_chosen(&self, _prompt: &Prompt, index: usize, _response: &Response) {
+            self.events.lock().unwrap().push(format!("answer {index}"));
+        }
+
+        fn label_resolved(&self, label: &str, _prompt: &Prompt) {
+            self.events.lock().unwrap().push(format!("label {label}"));
+        }
+
+        fn fell_through(&self, _from: &Prompt, _to: &Prompt) {
+            self.events.lock().unwrap().push("fall through".to_string());
+        }
+
+        fn finished(&self, ending: &Ending) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("finished {ending:?}"));
+        }
+    }
+
+    struct Counter(Mutex<usize>);
+
+    impl PrompterObserver for Counter {
+        fn prompt_entered(&self, _prompt: &Prompt) {
+            *self.0.lock().unwrap() += 1;
+        }
+    }
+
+    #[test]
+    fn notifies_every_transition() {
+        let data = read_to_string("./simple_prompt.txt").unwrap();
+        let recorder = Arc::new(Recorder::default());
+        let prompter = Prompter::new(parse(&data).unwrap())
+            .unwrap()
+            .with_observer(recorder.clone());
+
+        let response = prompter.current().responses[0].clone();
+        prompter.answer(&response).unwrap();
+
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            vec![
+                "enter Are you a human?",
+                "answer 0",
+                "label YES",
+                "enter Nice! Glad to meet you human!",
+                "finished Completed { outcome: None }",
+            ]
+        );
+    }
+
+    #[test]
+    fn notifies_fall_through_and_every_observer() {
+        let data = read_to_string("./labelless_prompt.txt").unwrap();
+        let recorder = Arc::new(Recorder::default());
+        let counter = Arc::new(Counter(Mutex::new(0)));
+        let prompter = Prompter::new(parse(&data).unwrap())
+            .unwrap()
+            .with_observer(recorder.clone())
+            .with_observer(counter.clone());
+
+        let response = prompter.current().responses[0].clone();
+        let prompter = prompter.answer(&response).unwrap();
+        let response = prompter.current().responses[0].clone();
+        assert!(prompter.answer(&response).unwrap().is_finished());
+
+        assert_eq!(*counter.0.lock().unwrap(), 2);
+        assert_eq!(
+            recorder.events.lock().unwrap()[2..],
+            [
+                "fall through",
+                "enter Nice! Glad to meet you human!",
+                "answer 0",
+                "finished DeadEnd"
+            ]
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! Scripts that own the text they were parsed from, for hosts that load and
+//! unload scripts as they go rather than keeping the text around themselves.
+//!
+//! `Script` and `Prompter` borrow their labels and texts from the script's
+//! text, so they can't be stored next to it. `OwnedScript` and
+//! `OwnedPrompter` keep the two together and only lend the script or
+//! prompter out to closures that work for any lifetime, so nothing borrowed
+//! from the text can outlive it. The text is freed once the script and every
+//! prompter started from it are dropped.
+
+use std::{mem, sync::Arc};
+
+use crate::{script::Script, Prompter};
+
+/// A script along with its text.
+#[derive(Debug, Clone)]
+pub struct OwnedScript {
+    // Borrows from `text`, which is declared after it so it's dropped first
+    script: Arc<Script<'static>>,
+    text: Arc<str>,
+}
+
+/// A prompter along with the text of its script.
+#[derive(Clone)]
+pub struct OwnedPrompter {
+    // Borrows from `text`, which is declared after it so it's dropped first
+    prompter: Prompter<'static>,
+    text: Arc<str>,
+}
+
+impl OwnedScript {
+    /// Builds the script from `text` with `build`, typically parsing it and
+    /// calling `Script::new`.
+    pub fn new<E>(
+        text: impl Into<Arc<str>>,
+        build: impl for<'a> FnOnce(&'a str) -> Result<Script<'a>, E>,
+    ) -> Result<OwnedScript, E> {
+        let text = text.into();
+        let script = build(&text)?;
+        // SAFETY: `build` works for any lifetime, so the script borrows from
+        // nothing shorter lived than `text`. The text is on the heap behind
+        // an `Arc` that's kept for as long as the script, and every prompter
+        // started from it, so it never moves or goes away under them.
+        // Nothing is handed out with the made up `'static` lifetime, see
+        // `with_script`, `start_with` and `OwnedPrompter`.
+        let script = unsafe { mem::transmute::<Script<'_>, Script<'static>>(script) };
+        Ok(OwnedScript {
+            script: Arc::new(script),
+            text,
+        })
+    }
+
+    /// The text the script was parsed from.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Lends the script to `f`.
+    pub fn with_script<R>(&self, f: impl for<'a> FnOnce(&Arc<Script<'a>>) -> R) -> R {
+        f(&self.script)
+    }
+
+    /// Starts a conversation with `f`, typically through
+    /// `Prompter::from_script`.
+    pub fn start_with<E>(
+        &self,
+        f: impl for<'a> FnOnce(Arc<Script<'a>>) -> Result<Prompter<'a>, E>,
+    ) -> Result<OwnedPrompter, E> {
+        Ok(OwnedPrompter {
+            prompter: f(Arc::clone(&self.script))?,
+            text: Arc::clone(&self.text),
+        })
+    }
+}
+
+impl OwnedPrompter {
+    /// Lends the prompter to `f`, which can't hand back anything borrowed
+    /// from it:
+    ///
+    /// ```compile_fail
+    /// # use prompter::{owned::OwnedScript, script::Script, Prompter, PrompterErr};
+    /// let script = OwnedScript::new("> \"Hi\"", |text| {
+    ///     Script::new(lexer::parse(text).unwrap())
+    /// })
+    /// .unwrap();
+    /// let prompter = script
+    ///     .start_with(|script| Ok::<_, PrompterErr>(Prompter::from_script(script)))
+    ///     .unwrap();
+    /// let text: &str = prompter.with(|prompter| prompter.current().text);
+    /// ```
+    pub fn with<R>(&self, f: impl for<'a> FnOnce(&Prompter<'a>) -> R) -> R {
+        f(&self.prompter)
+    }
+
+    /// Moves the conversation on with `f`, like answering. It's left where
+    /// it was when `f` fails.
+    pub fn update<E>(
+        &mut self,
+        f: impl for<'a> FnOnce(Prompter<'a>) -> Result<Prompter<'a>, E>,
+    ) -> Result<(), E> {
+        self.prompter = f(self.prompter.clone())?;
+        Ok(())
+    }
+
+    /// The text of the conversation's script.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use lexer::parse;
+
+    use super::OwnedScript;
+    use crate::{script::Script, Prompter, PrompterErr};
+
+    fn owned(text: &str) -> OwnedScript {
+        OwnedScript::new(text.to_string(), |text| Script::new(parse(text).unwrap())).unwrap()
+    }
+
+    #[test]
+    fn keeps_the_text_for_its_prompters() {
+        let script = owned("> (START) \"Hi\"\n< (END:happy) \"Hello\"\n");
+        let mut prompter = script
+            .start_with(|script| Ok::<_, PrompterErr>(Prompter::from_script(script)))
+            .unwrap();
+        let text = Arc::clone(&script.text);
+        drop(script);
+
+        assert_eq!(prompter.with(|prompter| prompter.current().text.len()), 2);
+        prompter
+            .update(|prompter| {
+                let hello = prompter.current().responses[0].clone();
+                prompter.answer(&hello)
+            })
+            .unwrap();
+        let outcome =
+            prompter.with(|prompter| prompter.ending().unwrap().outcome().map(str::to_string));
+        assert_eq!(outcome.as_deref(), Some("happy"));
+
+        // Freed along with the last prompter
+        assert_eq!(Arc::strong_count(&text), 2);
+        drop(prompter);
+        assert_eq!(Arc::strong_count(&text), 1);
+    }
+
+    #[test]
+    fn leaves_failed_updates_where_they_were() {
+        let script = owned("> (START) \"Hi\"\n< input name [int]\n");
+        let mut prompter = script
+            .start_with(|script| Ok::<_, PrompterErr>(Prompter::from_script(script)))
+            .unwrap();
+
+        let err = prompter.update(|prompter| prompter.answer_input("Ada"));
+        assert_eq!(err.unwrap_err().code(), "invalid_input");
+        assert!(prompter.with(|prompter| prompter.current().label == Some("START")));
+    }
+}
@@ -0,0 +1,522 @@
+use std::fmt::{self, Display};
+
+use crate::json::Json;
+
+/// A small regular expression for `[regex "..."]` input checks. Supports
+/// literals, `.`, classes like `[a-z]` and `[^0-9]`, the escapes `\d`, `\w`
+/// and `\s`, groups, `|`, and the `*`, `+`, `?` and `{n,m}` repeats. The
+/// whole text has to match, so `^` and `$` are optional.
+///
+/// Patterns are compiled to a small program that's run over the text one
+/// character at a time, trying every way to match at once, so matching takes
+/// time in proportion to the text and never backtracks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    program: Vec<Inst>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternError {
+    UnexpectedEnd,
+    UnmatchedParenthesis,
+    NothingToRepeat,
+    InvalidRepeat,
+    InvalidClass,
+    /// Groups nested too deeply, or repeats adding up to too much to compile.
+    TooComplex,
+}
+
+impl Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::UnexpectedEnd => write!(f, "pattern ends unexpectedly"),
+            PatternError::UnmatchedParenthesis => write!(f, "unmatched parenthesis in pattern"),
+            PatternError::NothingToRepeat => write!(f, "nothing to repeat in pattern"),
+            PatternError::InvalidRepeat => write!(f, "invalid `{{n,m}}` repeat in pattern"),
+            PatternError::InvalidClass => write!(f, "invalid `[...]` class in pattern"),
+            PatternError::TooComplex => write!(f, "pattern is too complex"),
+        }
+    }
+}
+
+// Limits keeping compiling and matching untrusted patterns cheap
+const MAX_DEPTH: usize = 32;
+const MAX_PROGRAM: usize = 10_000;
+
+type Sequence = Vec<Piece>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    Group(Vec<Sequence>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Piece {
+    node: Node,
+    min: usize,
+    max: Option<usize>,
+}
+
+fn escape_ranges(escape: char) -> Option<Vec<(char, char)>> {
+    match escape {
+        'd' => Some(vec![('0', '9')]),
+        'w' => Some(vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')]),
+        's' => Some(vec![(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')]),
+        _ => None,
+    }
+}
+
+struct PatternParser {
+    chars: Vec<char>,
+    position: usize,
+    depth: usize,
+}
+
+impl PatternParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<char, PatternError> {
+        let c = self.peek().ok_or(PatternError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn alternatives(&mut self) -> Result<Vec<Sequence>, PatternError> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.eat('|') {
+            alternatives.push(self.sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> Result<Sequence, PatternError> {
+        let mut sequence = vec![];
+
+        while let Some(c) = self.peek() {
+            let node = match c {
+                '|' | ')' => break,
+                // Anchors are implied at both ends
+                '^' if self.position == 0 => {
+                    self.position += 1;
+                    continue;
+                }
+                '$' if self.position + 1 == self.chars.len() => {
+                    self.position += 1;
+                    continue;
+                }
+                '*' | '+' | '?' | '{' => return Err(PatternError::NothingToRepeat),
+                '(' => {
+                    self.position += 1;
+                    self.depth += 1;
+                    if self.depth > MAX_DEPTH {
+                        return Err(PatternError::TooComplex);
+                    }
+                    let group = self.alternatives()?;
+                    if !self.eat(')') {
+                        return Err(PatternError::UnmatchedParenthesis);
+                    }
+                    self.depth -= 1;
+                    Node::Group(group)
+                }
+                '[' => {
+                    self.position += 1;
+                    self.class()?
+                }
+                '.' => {
+                    self.position += 1;
+                    Node::Any
+                }
+                '\\' => {
+                    self.position += 1;
+                    let escape = self.next()?;
+                    match escape_ranges(escape) {
+                        Some(ranges) => Node::Class {
+                            ranges,
+                            negated: false,
+                        },
+                        None => Node::Char(escape),
+                    }
+                }
+                c => {
+                    self.position += 1;
+                    Node::Char(c)
+                }
+            };
+
+            let (min, max) = self.repeat()?;
+            sequence.push(Piece { node, min, max });
+        }
+
+        Ok(sequence)
+    }
+
+    fn class(&mut self) -> Result<Node, PatternError> {
+        let negated = self.eat('^');
+        let mut ranges = vec![];
+
+        loop {
+            let c = self.next().map_err(|_| PatternError::InvalidClass)?;
+            let start = match c {
+                ']' if !ranges.is_empty() => break,
+                '\\' => {
+                    let escape = self.next()?;
+                    if let Some(escaped) = escape_ranges(escape) {
+                        ranges.extend(escaped);
+                        continue;
+                    }
+                    escape
+                }
+                c => c,
+            };
+
+            let end = match (self.peek(), self.chars.get(self.position + 1)) {
+                (Some('-'), Some(end)) if *end != ']' => {
+                    self.position += 2;
+                    *end
+                }
+                _ => start,
+            };
+            if end < start {
+                return Err(PatternError::InvalidClass);
+            }
+            ranges.push((start, end));
+        }
+
+        Ok(Node::Class { ranges, negated })
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        self.chars[start..self.position]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    }
+
+    fn repeat(&mut self) -> Result<(usize, Option<usize>), PatternError> {
+        let repeat = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.position += 1;
+                let min = self.number().ok_or(PatternError::InvalidRepeat)?;
+                let max = match self.eat(',') {
+                    true => self.number(),
+                    false => Some(min),
+                };
+                if !self.eat('}') || max.is_some_and(|max| max < min) {
+                    return Err(PatternError::InvalidRepeat);
+                }
+                return Ok((min, max));
+            }
+            _ => return Ok((1, Some(1))),
+        };
+
+        self.position += 1;
+        Ok(repeat)
+    }
+}
+
+impl Node {
+    fn matches_char(&self, c: char) -> bool {
+        match self {
+            Node::Char(expected) => *expected == c,
+            Node::Any => true,
+            Node::Class { ranges, negated } => {
+                ranges
+                    .iter()
+                    .any(|(start, end)| (*start..=*end).contains(&c))
+                    != *negated
+            }
+            Node::Group(_) => unreachable!("groups are matched as sequences"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Inst {
+    /// Takes a character the node matches, never a group.
+    Take(Node),
+    /// Carries on at both, preferring neither.
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+struct Compiler {
+    program: Vec<Inst>,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> Result<usize, PatternError> {
+        if self.program.len() >= MAX_PROGRAM {
+            return Err(PatternError::TooComplex);
+        }
+        self.program.push(inst);
+        Ok(self.program.len() - 1)
+    }
+
+    // Jumps and splits pushed before where they go are pointed there after
+    fn patch(&mut self, at: usize, to: usize) {
+        match &mut self.program[at] {
+            Inst::Jump(target) | Inst::Split(_, target) => *target = to,
+            _ => unreachable!("only jumps and splits are patched"),
+        }
+    }
+
+    fn alternatives(&mut self, alternatives: &[Sequence]) -> Result<(), PatternError> {
+        let Some((last, rest)) = alternatives.split_last() else {
+            return Ok(());
+        };
+
+        let mut jumps = vec![];
+        for sequence in rest {
+            let split = self.push(Inst::Split(self.program.len() + 1, 0))?;
+            self.sequence(sequence)?;
+            jumps.push(self.push(Inst::Jump(0))?);
+            self.patch(split, self.program.len());
+        }
+        self.sequence(last)?;
+        for jump in jumps {
+            self.patch(jump, self.program.len());
+        }
+        Ok(())
+    }
+
+    fn sequence(&mut self, sequence: &[Piece]) -> Result<(), PatternError> {
+        sequence.iter().try_for_each(|piece| self.piece(piece))
+    }
+
+    fn node(&mut self, node: &Node) -> Result<(), PatternError> {
+        match node {
+            Node::Group(alternatives) => self.alternatives(alternatives),
+            node => self.push(Inst::Take(node.clone())).map(|_| ()),
+        }
+    }
+
+    // Repeats are written out, the required ones first and then either a
+    // loop or as many optional ones as allowed
+    fn piece(&mut self, piece: &Piece) -> Result<(), PatternError> {
+        for _ in 0..piece.min {
+            self.node(&piece.node)?;
+        }
+
+        match piece.max {
+            None => {
+                let split = self.push(Inst::Split(self.program.len() + 1, 0))?;
+                self.node(&piece.node)?;
+                self.push(Inst::Jump(split))?;
+                self.patch(split, self.program.len());
+            }
+            Some(max) => {
+                let mut splits = vec![];
+                for _ in piece.min..max {
+                    splits.push(self.push(Inst::Split(self.program.len() + 1, 0))?);
+                    self.node(&piece.node)?;
+                }
+                for split in splits {
+                    self.patch(split, self.program.len());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// The instructions matching can be at, each once, with when each was added
+struct Threads {
+    list: Vec<usize>,
+    added: Vec<usize>,
+}
+
+impl Pattern {
+    // Adds `pc` and everything reachable from it without taking a character
+    fn add(&self, threads: &mut Threads, pc: usize, step: usize) {
+        let mut stack = vec![pc];
+        while let Some(pc) = stack.pop() {
+            if threads.added[pc] == step {
+                continue;
+            }
+            threads.added[pc] = step;
+            match self.program[pc] {
+                Inst::Jump(to) => stack.push(to),
+                Inst::Split(first, second) => stack.extend([second, first]),
+                _ => threads.list.push(pc),
+            }
+        }
+    }
+}
+
+impl Pattern {
+    pub fn new(source: &str) -> Result<Pattern, PatternError> {
+        let mut parser = PatternParser {
+            chars: source.chars().collect(),
+            position: 0,
+            depth: 0,
+        };
+        let alternatives = parser.alternatives()?;
+        if parser.peek().is_some() {
+            return Err(PatternError::UnmatchedParenthesis);
+        }
+
+        let mut compiler = Compiler { program: vec![] };
+        compiler.alternatives(&alternatives)?;
+        compiler.push(Inst::Match)?;
+        Ok(Pattern {
+            program: compiler.program,
+        })
+    }
+
+    /// The compiled program, for players outside Rust to match with exactly
+    /// the same rules. Each instruction is `{"take": {"ranges", "negated"}}`
+    /// with ranges of code points, `{"split": [a, b]}`, `{"jump": to}` or
+    /// `{"match": true}`.
+    pub fn to_json(&self) -> Json {
+        let code = |c: char| Json::from(c as i64);
+        let take = |ranges: &[(char, char)], negated: bool| {
+            let ranges = ranges
+                .iter()
+                .map(|(start, end)| Json::Array(vec![code(*start), code(*end)]));
+            Json::object([(
+                "take",
+                Json::object([
+                    ("ranges", Json::Array(ranges.collect())),
+                    ("negated", Json::from(negated)),
+                ]),
+            )])
+        };
+
+        let program = self.program.iter().map(|inst| match inst {
+            Inst::Take(Node::Char(c)) => take(&[(*c, *c)], false),
+            // Nothing excluded
+            Inst::Take(Node::Any) => take(&[], true),
+            Inst::Take(Node::Class { ranges, negated }) => take(ranges, *negated),
+            Inst::Take(Node::Group(_)) => unreachable!("groups are compiled to sequences"),
+            Inst::Split(first, second) => Json::object([(
+                "split",
+                Json::Array(vec![Json::from(*first), Json::from(*second)]),
+            )]),
+            Inst::Jump(to) => Json::object([("jump", Json::from(*to))]),
+            Inst::Match => Json::object([("match", Json::Bool(true))]),
+        });
+        Json::Array(program.collect())
+    }
+
+    /// Whether the whole of `text` matches.
+    pub fn is_match(&self, text: &str) -> bool {
+        let threads = || Threads {
+            list: vec![],
+            added: vec![0; self.program.len()],
+        };
+        let (mut current, mut next) = (threads(), threads());
+        self.add(&mut current, 0, 1);
+
+        for (idx, c) in text.chars().enumerate() {
+            if current.list.is_empty() {
+                return false;
+            }
+            for &pc in &current.list {
+                if let Inst::Take(node) = &self.program[pc] {
+                    if node.matches_char(c) {
+                        self.add(&mut next, pc + 1, idx + 2);
+                    }
+                }
+            }
+            current.list.clear();
+            std::mem::swap(&mut current, &mut next);
+        }
+
+        current
+            .list
+            .iter()
+            .any(|pc| self.program[*pc] == Inst::Match)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pattern, PatternError};
+
+    #[test]
+    fn matches_whole_text() {
+        let pattern = Pattern::new("[A-Z]{3}-\\d+").unwrap();
+        assert!(pattern.is_match("ABC-42"));
+        assert!(!pattern.is_match("ABC-"));
+        assert!(!pattern.is_match("xABC-42"));
+        assert!(!pattern.is_match("ABC-42x"));
+
+        let pattern = Pattern::new("^(cat|dog)s?$").unwrap();
+        assert!(pattern.is_match("dogs"));
+        assert!(pattern.is_match("cat"));
+        assert!(!pattern.is_match("cow"));
+
+        let pattern = Pattern::new("a*a*b|[^b]{2,}").unwrap();
+        assert!(pattern.is_match("aaab"));
+        assert!(pattern.is_match("xyz"));
+        assert!(!pattern.is_match("x"));
+
+        let pattern = Pattern::new("(a?)*b\\.").unwrap();
+        assert!(pattern.is_match("aab."));
+        assert!(!pattern.is_match("aabx"));
+    }
+
+    #[test]
+    fn fails_on_bad_patterns() {
+        assert_eq!(Pattern::new("(ab"), Err(PatternError::UnmatchedParenthesis));
+        assert_eq!(Pattern::new("ab)"), Err(PatternError::UnmatchedParenthesis));
+        assert_eq!(Pattern::new("*a"), Err(PatternError::NothingToRepeat));
+        assert_eq!(Pattern::new("a{3,1}"), Err(PatternError::InvalidRepeat));
+        assert_eq!(Pattern::new("[z-a]"), Err(PatternError::InvalidClass));
+        assert_eq!(Pattern::new("[ab"), Err(PatternError::InvalidClass));
+        assert_eq!(Pattern::new("ab\\"), Err(PatternError::UnexpectedEnd));
+        assert_eq!(
+            Pattern::new(&"(".repeat(100_000)),
+            Err(PatternError::TooComplex)
+        );
+        assert_eq!(
+            Pattern::new("(a{1000}){1000}"),
+            Err(PatternError::TooComplex)
+        );
+    }
+
+    #[test]
+    fn writes_out_the_program() {
+        let pattern = Pattern::new("a|.").unwrap();
+        assert_eq!(
+            pattern.to_json().to_string(),
+            r#"[{"split":[1,3]},{"take":{"ranges":[[97,97]],"negated":false}},{"jump":4},{"take":{"ranges":[],"negated":true}},{"match":true}]"#
+        );
+    }
+
+    #[test]
+    fn matches_long_and_ambiguous_text() {
+        let text = "a".repeat(100_000);
+        assert!(Pattern::new("\\w+").unwrap().is_match(&text));
+        assert!(Pattern::new("(a|aa)*").unwrap().is_match(&text));
+
+        let pattern = Pattern::new("(a+)+b").unwrap();
+        assert!(!pattern.is_match(&"a".repeat(28)));
+        assert!(pattern.is_match("aaab"));
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::Mutex,
+};
+
+use lexer::parser::WeightedLabel;
+
+use crate::Prompter;
+
+/// Where a prompter's random picks and shuffles come from. Inject a seeded
+/// one with `Prompter::with_random` to make runs reproducible. Sources are
+/// cloned along with the prompter, so any `Clone` one will do.
+pub trait RandomSource: Send + CloneSource {
+    fn next_u64(&mut self) -> u64;
+}
+
+/// Boxed copies of a `RandomSource`, there for every `Clone` one.
+pub trait CloneSource {
+    fn clone_source(&self) -> Box<dyn RandomSource>;
+}
+
+impl<T: RandomSource + Clone + 'static> CloneSource for T {
+    fn clone_source(&self) -> Box<dyn RandomSource> {
+        Box::new(self.clone())
+    }
+}
+
+/// A prompter's own source. A cloned prompter carries on from the state it
+/// was cloned at, without moving the original's on.
+pub(crate) struct Random(Mutex<Box<dyn RandomSource>>);
+
+impl Random {
+    pub(crate) fn new(source: impl RandomSource + 'static) -> Self {
+        Self(Mutex::new(Box::new(source)))
+    }
+
+    fn with<T>(&self, f: impl FnOnce(&mut dyn RandomSource) -> T) -> T {
+        // A source that panicked mid pick is still a source of numbers
+        let mut source = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        f(&mut **source)
+    }
+}
+
+impl Clone for Random {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.with(|source| source.clone_source())))
+    }
+}
+
+/// A small SplitMix64 generator, plenty for picking branches.
+#[derive(Debug, Clone)]
+pub struct SeededRandom {
+    state: u64,
+}
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Seeded differently every time, which is what prompters use by default.
+    pub fn from_entropy() -> Self {
+        Self::new(RandomState::new().build_hasher().finish())
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+// Evenly below `n`, which has to be more than 0
+fn below(source: &mut dyn RandomSource, n: u64) -> u64 {
+    ((u128::from(source.next_u64()) * u128::from(n)) >> 64) as u64
+}
+
+impl<'a> Prompter<'a> {
+    /// Uses the given source for random picks and shuffles, starting with the
+    /// current prompt's responses. Clones of this prompter get their own copy.
+    pub fn with_random(mut self, source: impl RandomSource + 'static) -> Self {
+        self.random = Random::new(source);
+        self.arrange();
+        self
+    }
+
+    fn with_source<T>(&self, f: impl FnOnce(&mut dyn RandomSource) -> T) -> T {
+        self.random.with(f)
+    }
+
+    pub(crate) fn pick(&self, labels: &[WeightedLabel<'a>]) -> Option<&'a str> {
+        let total: u64 = labels.iter().map(|l| u64::from(l.weight)).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = self.with_source(|source| below(source, total));
+        labels
+            .iter()
+            .find(|label| match roll.checked_sub(u64::from(label.weight)) {
+                Some(rest) => {
+                    roll = rest;
+                    false
+                }
+                None => true,
+            })
+            .map(|label| label.label)
+    }
+
+    // Works out the order the current prompt's responses are offered in
+    pub(crate) fn arrange(&mut self) {
+        let len = self.current().responses.len();
+        self.order = match self.current().shuffle {
+            true => Some(self.with_source(|source| {
+                // Fisher-Yates
+                let mut order: Vec<usize> = (0..len).collect();
+                for idx in (1..len).rev() {
+                    order.swap(idx, below(source, idx as u64 + 1) as usize);
+                }
+                order
+            })),
+            false => None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use lexer::parse;
+
+    use super::{RandomSource, SeededRandom};
+    use crate::Prompter;
+
+    // Always rolls the same number
+    #[derive(Clone)]
+    struct Fixed(u64);
+
+    impl RandomSource for Fixed {
+        fn next_u64(&mut self) -> u64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn seeded_runs_repeat() {
+        let mut a = SeededRandom::new(7);
+        let mut b = SeededRandom::new(7);
+        let rolls: Vec<u64> = (0..4).map(|_| a.next_u64()).collect();
+        assert_eq!(rolls, (0..4).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(rolls[0], rolls[1]);
+    }
+
+    #[test]
+    fn picks_weighted_labels() {
+        let data = read_to_string("./random_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+
+        // WIN weighs 3 of 4, so the lowest three quarters of rolls win
+        let pick = |roll: u64| {
+            let prompter = prompter.clone().with_random(Fixed(roll));
+            let response = prompter.current().responses[0].clone();
+            let next = prompter.answer(&response).unwrap();
+            next.current().label
+        };
+        assert_eq!(pick(0), Some("WIN"));
+        assert_eq!(pick((3 << 62) - 1), Some("WIN"));
+        assert_eq!(pick(3 << 62), Some("LOSE"));
+
+        // Labelled responses go where they say
+        let response = prompter.current().responses[2].clone();
+        let next = prompter.answer(&response).unwrap();
+        assert_eq!(next.current().label, Some("LOSE"));
+    }
+
+    #[test]
+    fn shuffles_responses_reproducibly() {
+        let data = read_to_string("./random_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+
+        let texts = |seed| {
+            let prompter = prompter.clone().with_random(SeededRandom::new(seed));
+            let texts: Vec<&str> = prompter.next().responses.iter().map(|r| r.text).collect();
+            // The order holds for as long as the prompt is shown
+            assert_eq!(
+                prompter
+                    .next()
+                    .responses
+                    .iter()
+                    .map(|r| r.text)
+                    .collect::<Vec<_>>(),
+                texts
+            );
+            texts
+        };
+
+        assert_eq!(texts(1), texts(1));
+        let mut sorted = texts(2);
+        sorted.sort();
+        assert_eq!(sorted, ["Heads", "Lose on purpose", "Tails"]);
+        assert!((0..16).any(|seed| texts(seed) != texts(0)));
+    }
+
+    // Rolls a quarter further round each time
+    #[derive(Clone)]
+    struct Counter(u64);
+
+    impl RandomSource for Counter {
+        fn next_u64(&mut self) -> u64 {
+            self.0 = self.0.wrapping_add(1 << 62);
+            self.0 - (1 << 62)
+        }
+    }
+
+    fn landing(prompter: Prompter<'_>) -> Option<&str> {
+        let response = prompter.current().responses[0].clone();
+        prompter.answer(&response).unwrap().current().label
+    }
+
+    #[test]
+    fn clones_carry_on_from_the_same_state() {
+        let data = read_to_string("./random_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap())
+            .unwrap()
+            .with_random(Counter(0));
+
+        // However many clones answer first, the original rolls the same
+        for _ in 0..4 {
+            assert_eq!(landing(prompter.clone()), Some("WIN"));
+        }
+        assert_eq!(landing(prompter), Some("WIN"));
+    }
+}
@@ -0,0 +1,135 @@
+use lexer::{
+    parser::{Response, Selection},
+    template::{parse_template, Filter, Segment},
+};
+
+use crate::{select::ResponseId, variables::Variables, Prompter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedResponse<'a> {
+    pub id: ResponseId,
+    pub text: String,
+    /// The response to answer with when this one is picked.
+    pub response: Response<'a>,
+}
+
+/// A prompt ready to show, with placeholders filled in and only the
+/// available responses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedPrompt<'a> {
+    pub label: Option<&'a str>,
+    pub text: String,
+    pub responses: Vec<RenderedResponse<'a>>,
+    /// How many responses to pick, on multi-select prompts.
+    pub multi: Option<Selection>,
+}
+
+/// Fills in `{var}` placeholders from the first scope that has the variable.
+/// Unset variables without a default render as nothing, and text that isn't
+/// a valid template is returned as is; `validate` reports both.
+pub fn render(text: &str, scopes: &[&Variables]) -> String {
+    let Ok(segments) = parse_template(text) else {
+        return text.to_string();
+    };
+
+    segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => text.to_string(),
+            Segment::Placeholder(placeholder) => {
+                let value = scopes
+                    .iter()
+                    .find_map(|scope| scope.get(placeholder.name))
+                    .map(ToString::to_string);
+
+                placeholder
+                    .filters
+                    .iter()
+                    .fold(value, |value, filter| match filter {
+                        Filter::Upper => value.map(|v| v.to_uppercase()),
+                        Filter::Lower => value.map(|v| v.to_lowercase()),
+                        Filter::Default(default) => value
+                            .filter(|v| !v.is_empty())
+                            .or_else(|| Some(default.to_string())),
+                    })
+                    .unwrap_or_default()
+            }
+        })
+        .collect()
+}
+
+impl<'a> Prompter<'a> {
+    pub fn render_next(&self) -> RenderedPrompt<'a> {
+        self.render_next_with(&Variables::new())
+    }
+
+    /// Like `render_next`, falling back to values the host application
+    /// provides for anything the conversation hasn't set.
+    pub fn render_next_with(&self, host: &Variables) -> RenderedPrompt<'a> {
+        let scopes = [self.variables(), host];
+        let next = self.next();
+
+        RenderedPrompt {
+            label: next.label,
+            text: render(next.text, &scopes),
+            responses: self
+                .response_ids()
+                .into_iter()
+                .zip(next.responses)
+                .map(|(id, response)| RenderedResponse {
+                    id,
+                    text: render(response.text, &scopes),
+                    response,
+                })
+                .collect(),
+            multi: next.multi,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use lexer::parse;
+
+    use super::render;
+    use crate::{variables::Variables, Prompter};
+
+    #[test]
+    fn renders_placeholders_and_filters() {
+        let mut variables = Variables::new();
+        variables.set("name", "Ada");
+        variables.set("age", 36);
+        variables.set("nickname", "");
+
+        assert_eq!(
+            render("Happy {age}th, {name|upper}! {{ok}}", &[&variables]),
+            "Happy 36th, ADA! {ok}"
+        );
+        assert_eq!(
+            render("Hi {nickname|default:Birthday Star|lower}", &[&variables]),
+            "Hi birthday star"
+        );
+        assert_eq!(render("Hi {missing}!", &[&variables]), "Hi !");
+        assert_eq!(render("Hi {broken", &[&variables]), "Hi {broken");
+    }
+
+    #[test]
+    fn renders_the_next_prompt() {
+        let data = read_to_string("./template_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+        let mut host = Variables::new();
+        host.set("name", "Grace");
+        host.set("sender", "Ada");
+
+        let rendered = prompter.render_next_with(&host);
+        assert_eq!(rendered.text, "Happy birthday, Grace!");
+        assert_eq!(rendered.responses[0].text, "Thanks Ada!");
+        assert_eq!(rendered.responses[0].id.index(), 0);
+
+        let prompter = prompter.answer(&rendered.responses[0].response).unwrap();
+        let rendered = prompter.render_next_with(&host);
+        assert_eq!(rendered.text, "You're welcome, GRACE. {Love, Ada}");
+    }
+}
@@ -0,0 +1,572 @@
+//! Scenarios, conversations written down ahead of time to check a script
+//! still plays the way its author meant, read from `.pgtest` files like
+//!
+//! ```text
+//! # Settings before the first scenario apply to all of them
+//! script quiz.pg
+//! seed 7
+//! set name = "Ada"
+//!
+//! == Gold for getting everything right
+//! > (START) "What's the capital of France?"
+//! < "Paris"
+//! > "Who painted the Mona Lisa?"
+//! < "Leonardo"
+//! > "That's all! Ready for your results?"
+//! < "Yes"
+//! > (GOLD) "Top marks!"
+//! end completed
+//! ```
+//!
+//! `> "text"` expects the current prompt, rendered, optionally with its
+//! `(LABEL)`, and moves on from statements. `< "text"` answers with the
+//! response showing that text, `< "A" "B"` picks several, `< input "42"`
+//! types text in and `< timeout` takes the default. `! code` after an answer
+//! expects it turned down with that `PrompterErr::code`, and `end` expects
+//! the conversation over, optionally with a kind and outcome like
+//! `end completed happy`. `seed`, `set` and `start LABEL` can also be given
+//! per scenario, after its `==` line.
+
+use std::{
+    fmt::{self, Display},
+    sync::Arc,
+};
+
+use crate::{
+    frontend::{apply, Reply},
+    random::SeededRandom,
+    script::Script,
+    variables::Value,
+    Prompter, PrompterErr,
+};
+
+/// A line of a `.pgtest` file that doesn't read as a step or setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScenarioErr {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Where a scenario stopped going as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    Choose(Vec<String>),
+    Type(String),
+    TimedOut,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Expect {
+        label: Option<String>,
+        text: String,
+    },
+    Answer(Answer),
+    /// The answer before is turned down with this `PrompterErr::code`.
+    Refused(String),
+    /// The conversation is over, with this `Ending::kind` and outcome when
+    /// given.
+    End {
+        kind: Option<String>,
+        outcome: Option<String>,
+    },
+}
+
+/// How a scenario starts. Seeded with 0 unless told otherwise so random
+/// picks are the same every run.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Setup {
+    pub seed: u64,
+    pub start: Option<String>,
+    pub variables: Vec<(String, Value)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scenario {
+    pub name: String,
+    /// The `==` line, for reporting.
+    pub line: usize,
+    pub setup: Setup,
+    /// Each step with its line.
+    pub steps: Vec<(usize, Step)>,
+}
+
+/// The scenarios of a `.pgtest` file, and the script they're for when it
+/// says.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TestFile {
+    pub script: Option<String>,
+    pub scenarios: Vec<Scenario>,
+}
+
+impl Display for ScenarioErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+// A `"quoted"` string with `\"` and `\\` escapes, and what follows it
+fn quoted(line: &str) -> Option<(String, &str)> {
+    let mut chars = line.strip_prefix('"')?.char_indices();
+    let mut text = String::new();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' => return Some((text, line[idx + 2..].trim_start())),
+            '\\' => text.push(chars.next()?.1),
+            c => text.push(c),
+        }
+    }
+    None
+}
+
+fn texts(mut rest: &str) -> Option<Vec<String>> {
+    let mut texts = vec![];
+    while !rest.is_empty() {
+        let (text, after) = quoted(rest)?;
+        texts.push(text);
+        rest = after;
+    }
+    (!texts.is_empty()).then_some(texts)
+}
+
+fn value(text: &str) -> Option<Value> {
+    match text {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        _ => match quoted(text) {
+            Some((text, "")) => Some(Value::Str(text)),
+            Some(_) => None,
+            None => text.parse().ok().map(Value::Int),
+        },
+    }
+}
+
+// Applies a `seed`, `set` or `start` setting, `false` when it's none of them
+fn setting(setup: &mut Setup, keyword: &str, rest: &str) -> Result<bool, String> {
+    match keyword {
+        "seed" => {
+            setup.seed = rest
+                .parse()
+                .map_err(|_| format!("`seed` takes a number, not {rest}"))?;
+        }
+        "start" if !rest.is_empty() => setup.start = Some(rest.to_string()),
+        "start" => return Err("`start` takes a label".to_string()),
+        "set" => {
+            let (name, text) = rest.split_once('=').ok_or("expected `set name = value`")?;
+            let value = value(text.trim()).ok_or_else(|| {
+                format!("{} isn't a \"string\", number, true or false", text.trim())
+            })?;
+            setup.variables.push((name.trim().to_string(), value));
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+fn step(keyword: &str, rest: &str) -> Result<Option<Step>, String> {
+    let step = match keyword {
+        ">" => {
+            let (label, rest) = match rest.strip_prefix('(') {
+                Some(rest) => {
+                    let (label, rest) = rest.split_once(')').ok_or("label is missing its `)`")?;
+                    (Some(label.to_string()), rest.trim_start())
+                }
+                None => (None, rest),
+            };
+            match quoted(rest) {
+                Some((text, "")) => Step::Expect { label, text },
+                _ => return Err("expected `> \"prompt text\"`".to_string()),
+            }
+        }
+        "<" => {
+            let answer = match rest.split_once(' ').unwrap_or((rest, "")) {
+                ("timeout", "") => Answer::TimedOut,
+                ("input", text) => match quoted(text.trim_start()) {
+                    Some((text, "")) => Answer::Type(text),
+                    _ => return Err("expected `< input \"typed text\"`".to_string()),
+                },
+                _ => Answer::Choose(texts(rest).ok_or("expected `< \"answer text\"`")?),
+            };
+            Step::Answer(answer)
+        }
+        "!" if !rest.is_empty() => Step::Refused(rest.to_string()),
+        "!" => return Err("`!` takes an error code, like `! invalid_input`".to_string()),
+        "end" => {
+            let mut words = rest.split_whitespace().map(str::to_string);
+            Step::End {
+                kind: words.next(),
+                outcome: words.next(),
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(step))
+}
+
+impl TestFile {
+    pub fn parse(data: &str) -> Result<TestFile, ScenarioErr> {
+        let mut file = TestFile::default();
+        let mut defaults = Setup::default();
+
+        for (idx, line) in data.lines().enumerate() {
+            let line_no = idx + 1;
+            let err = |message: String| ScenarioErr {
+                line: line_no,
+                message,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix("==") {
+                file.scenarios.push(Scenario {
+                    name: name.trim().to_string(),
+                    line: line_no,
+                    setup: defaults.clone(),
+                    steps: vec![],
+                });
+                continue;
+            }
+
+            // `>`, `<` and `!` don't need a space after them
+            let (keyword, rest) = match line.strip_prefix(['>', '<', '!']) {
+                Some(rest) => (&line[..1], rest),
+                None => line.split_once(' ').unwrap_or((line, "")),
+            };
+            let rest = rest.trim();
+
+            let Some(scenario) = file.scenarios.last_mut() else {
+                if keyword == "script" {
+                    file.script = Some(rest.to_string());
+                    continue;
+                }
+                match setting(&mut defaults, keyword, rest).map_err(err)? {
+                    true => continue,
+                    false => return Err(err(format!("expected a setting or `==` before {line}"))),
+                }
+            };
+            if setting(&mut scenario.setup, keyword, rest).map_err(err)? {
+                continue;
+            }
+
+            let step = step(keyword, rest)
+                .map_err(err)?
+                .ok_or_else(|| err(format!("expected a step, not {line}")))?;
+            let after_answer = matches!(scenario.steps.last(), Some((_, Step::Answer(_))));
+            if matches!(step, Step::Refused(_)) && !after_answer {
+                return Err(err(
+                    "`!` goes right after the answer turned down".to_string()
+                ));
+            }
+            scenario.steps.push((line_no, step));
+        }
+
+        // Most likely a header that isn't quite `==`, which would pass by
+        // testing nothing
+        if file.scenarios.is_empty() {
+            return Err(ScenarioErr {
+                line: 1,
+                message: "no scenarios, each starts with `== name`".to_string(),
+            });
+        }
+        Ok(file)
+    }
+}
+
+// What the current prompt looks like in a test file
+fn describe(prompter: &Prompter) -> String {
+    let prompt = prompter.render_next();
+    match prompt.label {
+        Some(label) => format!("> ({label}) {:?}", prompt.text),
+        None => format!("> {:?}", prompt.text),
+    }
+}
+
+impl Scenario {
+    fn start<'a>(&self, script: Arc<Script<'a>>) -> Result<Prompter<'a>, Failure> {
+        let script = match &self.setup.start {
+            None => script,
+            Some(label) => {
+                let started = Script::new(script.prompts().to_vec())
+                    .expect("the script has prompts")
+                    .with_start(label)
+                    .ok_or_else(|| Failure {
+                        line: self.line,
+                        message: format!("no prompt is labelled {label}"),
+                    })?;
+                Arc::new(started)
+            }
+        };
+
+        let mut prompter =
+            Prompter::from_script(script).with_random(SeededRandom::new(self.setup.seed));
+        for (name, value) in &self.setup.variables {
+            prompter = prompter.with_variable(name.as_str(), value.clone());
+        }
+        Ok(prompter)
+    }
+
+    /// Plays the scenario against a script, stopping at the first step that
+    /// doesn't go as written.
+    pub fn run(&self, script: Arc<Script>) -> Result<(), Failure> {
+        let mut prompter = self.start(script)?;
+        let mut steps = self.steps.iter().peekable();
+
+        while let Some((line, step)) = steps.next() {
+            let fail = |message: String| Failure {
+                line: *line,
+                message,
+            };
+
+            match step {
+                Step::Expect { label, text } => {
+                    let prompt = prompter.render_next();
+                    let label_matches = label.is_none() || prompt.label == label.as_deref();
+                    if !label_matches || prompt.text != *text {
+                        return Err(fail(format!("the script is at {}", describe(&prompter))));
+                    }
+                    // Statements are moved on from as soon as they're shown
+                    if prompter.is_statement() && !prompter.is_finished() {
+                        prompter = prompter.advance().map_err(|err| fail(err.to_string()))?;
+                    }
+                }
+                Step::Answer(answer) => {
+                    let answered = self.answer(&prompter, answer);
+                    let refused = match steps.next_if(|(_, step)| matches!(step, Step::Refused(_)))
+                    {
+                        Some((line, Step::Refused(code))) => Some((*line, code)),
+                        _ => None,
+                    };
+
+                    match (answered, refused) {
+                        (Ok(answered), None) => prompter = answered,
+                        (Ok(_), Some((line, code))) => {
+                            return Err(Failure {
+                                line,
+                                message: format!(
+                                    "expected the answer turned down with {code}, it was taken"
+                                ),
+                            })
+                        }
+                        (Err(err), Some((_, code))) if err.code() == code => {}
+                        (Err(err), Some((line, code))) => {
+                            return Err(Failure {
+                                line,
+                                message: format!(
+                                    "expected {code}, the answer was turned down with {}: {err}",
+                                    err.code()
+                                ),
+                            })
+                        }
+                        (Err(err), None) => {
+                            return Err(fail(format!("the answer was turned down: {err}")))
+                        }
+                    }
+                }
+                Step::Refused(_) => unreachable!("parsing only allows `!` after answers"),
+                Step::End { kind, outcome } => {
+                    let Some(ending) = prompter.ending() else {
+                        let message =
+                            format!("expected the end, the script is at {}", describe(&prompter));
+                        return Err(fail(message));
+                    };
+                    let kind_matches = kind.as_ref().is_none_or(|kind| kind == ending.kind());
+                    let outcome_matches =
+                        outcome.is_none() || ending.outcome() == outcome.as_deref();
+                    if !kind_matches || !outcome_matches {
+                        let ended = match ending.outcome() {
+                            Some(outcome) => format!("{} {outcome}", ending.kind()),
+                            None => ending.kind().to_string(),
+                        };
+                        return Err(fail(format!("the conversation ended {ended}")));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn answer<'a>(
+        &self,
+        prompter: &Prompter<'a>,
+        answer: &Answer,
+    ) -> Result<Prompter<'a>, PrompterErr> {
+        let reply = match answer {
+            Answer::Choose(texts) => {
+                let prompt = prompter.render_next();
+                let ids = texts
+                    .iter()
+                    .map(|text| {
+                        let response = prompt
+                            .responses
+                            .iter()
+                            .find(|response| response.text == *text);
+                        response
+                            .map(|response| response.id)
+                            .ok_or(PrompterErr::BadResponse)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                match (prompt.multi, ids.as_slice()) {
+                    (None, [id]) => Reply::Choose(*id),
+                    _ => Reply::ChooseMany(ids),
+                }
+            }
+            Answer::Type(text) => Reply::Type(text.clone()),
+            Answer::TimedOut => Reply::TimedOut,
+        };
+        apply(prompter.clone(), reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::read_to_string, sync::Arc};
+
+    use lexer::parse;
+
+    use super::{Answer, Failure, ScenarioErr, Step, TestFile};
+    use crate::{script::Script, variables::Value};
+
+    fn script(path: &str) -> Arc<Script<'static>> {
+        let data: &'static str = read_to_string(path).unwrap().leak();
+        Arc::new(Script::new(parse(data).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn reads_test_files() {
+        let file = TestFile::parse(
+            "# birthday\nscript card.pg\nseed 3\n\n== Happy\nset name = \"Ada\"\n\
+             > (START) \"Hi \\\"you\\\"\"\n< \"Pizza\" \"Cake\"\n< input \"42\"\n! invalid_input\n\
+             < timeout\nend completed happy\n",
+        )
+        .unwrap();
+
+        assert_eq!(file.script.as_deref(), Some("card.pg"));
+        let scenario = &file.scenarios[0];
+        assert_eq!((scenario.name.as_str(), scenario.line), ("Happy", 5));
+        assert_eq!(scenario.setup.seed, 3);
+        assert_eq!(
+            scenario.setup.variables,
+            [("name".to_string(), Value::Str("Ada".to_string()))]
+        );
+
+        let steps: Vec<_> = scenario
+            .steps
+            .iter()
+            .map(|(_, step)| step.clone())
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                Step::Expect {
+                    label: Some("START".to_string()),
+                    text: "Hi \"you\"".to_string()
+                },
+                Step::Answer(Answer::Choose(vec![
+                    "Pizza".to_string(),
+                    "Cake".to_string()
+                ])),
+                Step::Answer(Answer::Type("42".to_string())),
+                Step::Refused("invalid_input".to_string()),
+                Step::Answer(Answer::TimedOut),
+                Step::End {
+                    kind: Some("completed".to_string()),
+                    outcome: Some("happy".to_string())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn points_at_lines_that_dont_read() {
+        let err = |data| TestFile::parse(data).unwrap_err();
+        assert_eq!(
+            err("== A\n> \"Hi\n"),
+            ScenarioErr {
+                line: 2,
+                message: "expected `> \"prompt text\"`".to_string()
+            }
+        );
+        assert_eq!(err("> \"Hi\"").line, 1);
+        assert_eq!(err("== A\n! bad_response").line, 2);
+        assert_eq!(err("seed many").message, "`seed` takes a number, not many");
+        assert_eq!(err("== A\ndance").message, "expected a step, not dance");
+        assert_eq!(err("== A\né").message, "expected a step, not é");
+        assert_eq!(
+            err("# Nothing yet\nseed 7\n").message,
+            "no scenarios, each starts with `== name`"
+        );
+        assert_eq!(err("").line, 1);
+    }
+
+    #[test]
+    fn plays_scenarios() {
+        let script = script("./input_prompt.txt");
+        let file = TestFile::parse(
+            "== Stranger\n\
+             > (START) \"What's your name?\"\n\
+             < \"I'd rather not say\"\n\
+             > \"Happy birthday, stranger! How old are you now?\"\n\
+             < \"Nope\"\n! bad_response\n\
+             < input \"500\"\n! invalid_input\n\
+             < input \"7\"\n\
+             > (CAKE) \"7 candles coming up!\"\n\
+             < \"Thanks!\"\n\
+             end completed\n\
+             == Wrong\n\
+             < input \"Ada\"\n\
+             > \"Happy birthday, Bob! How old are you now?\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(file.scenarios[0].run(Arc::clone(&script)), Ok(()));
+        assert_eq!(
+            file.scenarios[1].run(script),
+            Err(Failure {
+                line: 15,
+                message: "the script is at > (GREET) \"Happy birthday, Ada! How old are you now?\""
+                    .to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn follows_statements_seeds_and_endings() {
+        let story = script("./story_prompt.txt");
+        let file = TestFile::parse(
+            "== Party\n\
+             > \"Once upon a time there was a birthday.\"\n\
+             > \"It was a very big birthday.\"\n\
+             < \"Yes\"\n\
+             > \"There was cake!\"\nend\n\
+             == Not over\n\
+             > \"Once upon a time there was a birthday.\"\nend\n",
+        )
+        .unwrap();
+        assert_eq!(file.scenarios[0].run(Arc::clone(&story)), Ok(()));
+        let message = file.scenarios[1].run(story).unwrap_err().message;
+        assert_eq!(
+            message,
+            "expected the end, the script is at > \"It was a very big birthday.\""
+        );
+
+        // The same seed picks the same way every run
+        let random = script("./random_prompt.txt");
+        let file = TestFile::parse("seed 1\n== Pick\n< \"Heads\"\nend completed\n").unwrap();
+        let first = file.scenarios[0].run(Arc::clone(&random));
+        assert_eq!(first, file.scenarios[0].run(random));
+    }
+}
@@ -0,0 +1,82 @@
+use lexer::parser::Band;
+
+use crate::Prompter;
+
+impl<'a> Prompter<'a> {
+    /// Points scored so far, also readable as an integer variable of the same
+    /// name. Unset or non-integer scores count as nothing.
+    pub fn score(&self, name: &str) -> i64 {
+        self.variables().get_int(name).unwrap_or_default()
+    }
+
+    /// All the script's scores added up.
+    pub fn total_score(&self) -> i64 {
+        self.script()
+            .scores()
+            .fold(0, |total: i64, name| total.saturating_add(self.score(name)))
+    }
+
+    // The label of the first band the score falls in
+    pub(crate) fn band_label(&self, score: Option<&str>, bands: &[Band<'a>]) -> Option<&'a str> {
+        let score = match score {
+            Some(name) => self.score(name),
+            None => self.total_score(),
+        };
+        bands
+            .iter()
+            .find(|band| band.contains(score))
+            .map(|band| band.label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use lexer::parse;
+
+    use crate::{Ending, Prompter};
+
+    fn play<'a>(prompter: Prompter<'a>, picks: &[usize]) -> Prompter<'a> {
+        picks.iter().fold(prompter, |prompter, pick| {
+            let response = prompter.next().responses[*pick].clone();
+            prompter.answer(&response).unwrap()
+        })
+    }
+
+    #[test]
+    fn accumulates_scores() {
+        let data = read_to_string("./quiz_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+        assert_eq!(
+            prompter.script().scores().collect::<Vec<_>>(),
+            ["geography", "history", "score"]
+        );
+
+        let prompter = play(prompter, &[0, 1]);
+        assert_eq!(prompter.score("geography"), 2);
+        assert_eq!(prompter.score("history"), -1);
+        assert_eq!(prompter.score("score"), 1);
+        assert_eq!(prompter.total_score(), 2);
+        assert_eq!(prompter.variables().get_int("geography"), Some(2));
+    }
+
+    #[test]
+    fn routes_to_result_bands() {
+        let data = read_to_string("./quiz_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+
+        let gold = play(prompter.clone(), &[0, 0, 0]);
+        assert_eq!(gold.current().label, Some("GOLD"));
+        assert_eq!(gold.ending(), Some(&Ending::Completed { outcome: None }));
+
+        let silver = play(prompter.clone(), &[0, 1, 0]);
+        assert_eq!(silver.ending().and_then(Ending::outcome), Some("silver"));
+
+        let bronze = play(prompter.clone(), &[1, 1, 0]);
+        assert_eq!(bronze.current().label, Some("BRONZE"));
+
+        let geography = play(prompter, &[0, 1, 1]);
+        assert_eq!(geography.current().label, Some("GEOGRAPHER"));
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::{BTreeSet, HashMap};
+
+use lexer::parser::{InputCheck, Prompt};
+
+use crate::{pattern::Pattern, PromptStartErr};
+
+const STARTING_LABEL: &str = "START";
+
+/// A parsed script compiled for prompting: the prompts, a label index and the
+/// resolved starting point. Build it once and share it behind an `Arc` between
+/// as many `Prompter`s as needed.
+#[derive(Debug)]
+pub struct Script<'a> {
+    prompts: Vec<Prompt<'a>>,
+    labels: HashMap<&'a str, usize>,
+    start: usize,
+    scores: BTreeSet<&'a str>,
+    /// `[regex ...]` patterns by their source, `None` when invalid.
+    patterns: HashMap<&'a str, Option<Pattern>>,
+}
+
+impl<'a> Script<'a> {
+    pub fn new(prompts: Vec<Prompt<'a>>) -> Result<Script<'a>, PromptStartErr> {
+        if prompts.is_empty() {
+            return Err(PromptStartErr);
+        }
+
+        // Duplicate labels resolve to the first prompt carrying them
+        let mut labels = HashMap::new();
+        for (idx, prompt) in prompts.iter().enumerate() {
+            if let Some(label) = prompt.label {
+                labels.entry(label).or_insert(idx);
+            }
+        }
+
+        let start = labels.get(STARTING_LABEL).copied().unwrap_or(0);
+
+        let scores = prompts
+            .iter()
+            .flat_map(|prompt| &prompt.responses)
+            .flat_map(|response| &response.scores)
+            .map(|delta| delta.name)
+            .collect();
+
+        let patterns = prompts
+            .iter()
+            .flat_map(|prompt| &prompt.responses)
+            .filter_map(|response| match response.input.as_ref()?.check {
+                Some(InputCheck::Regex(source)) => Some(source),
+                _ => None,
+            })
+            .map(|source| (source, Pattern::new(source).ok()))
+            .collect();
+
+        Ok(Self {
+            prompts,
+            labels,
+            start,
+            scores,
+            patterns,
+        })
+    }
+
+    /// Starts at the prompt with the given label instead, if there is one.
+    pub fn with_start(mut self, label: &str) -> Option<Script<'a>> {
+        self.start = self.find(label)?;
+        Some(self)
+    }
+
+    pub fn prompts(&self) -> &[Prompt<'a>] {
+        &self.prompts
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&Prompt<'a>> {
+        self.prompts.get(idx)
+    }
+
+    /// Index of the prompt with the given label.
+    pub fn find(&self, label: &str) -> Option<usize> {
+        self.labels.get(label).copied()
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Names of every score a response adds points to, in order.
+    pub fn scores(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.scores.iter().copied()
+    }
+
+    /// The compiled form of a `[regex ...]` pattern in the script, `None`
+    /// when it's invalid or not in the script.
+    pub fn pattern(&self, source: &str) -> Option<&Pattern> {
+        self.patterns.get(source)?.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use lexer::parse;
+
+    use super::Script;
+
+    #[test]
+    fn indexes_labels_and_start() {
+        let data = read_to_string("./simple_prompt.txt").unwrap();
+        let script = Script::new(parse(&data).unwrap()).unwrap();
+
+        assert_eq!(script.start(), 0);
+        assert_eq!(script.find("ANS_NO"), Some(1));
+        assert_eq!(script.find("YES"), Some(2));
+        assert_eq!(script.find("MISSING"), None);
+    }
+
+    #[test]
+    fn starts_at_a_chosen_label() {
+        let data = read_to_string("./simple_prompt.txt").unwrap();
+        let script = Script::new(parse(&data).unwrap()).unwrap();
+        let script = script.with_start("YES").unwrap();
+
+        assert_eq!(script.start(), 2);
+        assert!(script.with_start("MISSING").is_none());
+    }
+
+    #[test]
+    fn starts_at_first_prompt_without_start_label() {
+        let data = read_to_string("./labelless_prompt.txt").unwrap();
+        let script = Script::new(parse(&data).unwrap()).unwrap();
+
+        assert_eq!(script.start(), 0);
+        assert_eq!(script.prompts().len(), 2);
+    }
+
+    #[test]
+    fn compiles_patterns_once() {
+        let data = "> (START) \"Code?\"\n\
+                    < input code [regex \"[A-Z]{3}\"]\n\
+                    < input other [regex \"(oops\"]\n";
+        let script = Script::new(parse(data).unwrap()).unwrap();
+
+        assert!(script.pattern("[A-Z]{3}").unwrap().is_match("ABC"));
+        assert!(script.pattern("(oops").is_none());
+        assert!(script.pattern("[a-z]").is_none());
+    }
+
+    #[test]
+    fn fails_on_empty_script() {
+        assert!(Script::new(vec![]).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use lexer::parser::Response;
+
+use crate::{transcript::TranscriptEntry, Prompter, PrompterErr};
+
+/// Names one response of one prompt, for picking several at once with
+/// `answer_many`. Ids from another prompt are turned down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResponseId {
+    prompt: usize,
+    response: usize,
+}
+
+impl ResponseId {
+    /// Index of the response within its prompt.
+    pub fn index(&self) -> usize {
+        self.response
+    }
+}
+
+impl<'a> Prompter<'a> {
+    /// Id of one of the current prompt's available responses.
+    pub fn response_id(&self, response: &Response) -> Option<ResponseId> {
+        self.offered()
+            .find(|idx| &self.current().responses[*idx] == response)
+            .map(|response| ResponseId {
+                prompt: self.next_idx,
+                response,
+            })
+    }
+
+    /// Ids of the current prompt's available responses, in the order they're
+    /// offered.
+    pub fn response_ids(&self) -> Vec<ResponseId> {
+        self.offered()
+            .map(|response| ResponseId {
+                prompt: self.next_idx,
+                response,
+            })
+            .collect()
+    }
+
+    /// Answers a multi-select prompt with several of its responses. Each one
+    /// sets its variables and scores in the order given, then the prompt's
+    /// target or the next prompt follows.
+    pub fn answer_many(mut self, ids: &[ResponseId]) -> Result<Prompter<'a>, PrompterErr> {
+        if self.is_finished() {
+            return Err(PrompterErr::NoMoreQ);
+        }
+        let Some(selection) = self.current().multi else {
+            return Err(PrompterErr::BadResponse);
+        };
+
+        let offered = self.response_ids();
+        for (idx, id) in ids.iter().enumerate() {
+            // Ids of other prompts can't be looked up in this one
+            if !offered.contains(id)
+                || ids[..idx].contains(id)
+                || self.current().responses[id.response].input.is_some()
+            {
+                return Err(PrompterErr::BadResponse);
+            }
+        }
+        if !selection.allows(ids.len()) {
+            return Err(PrompterErr::SelectionCount(selection));
+        }
+
+        let script = Arc::clone(self.script());
+        let responses = &script.prompts()[self.next_idx].responses;
+        self.record(TranscriptEntry::Selection {
+            answers: ids
+                .iter()
+                .map(|id| (id.response, responses[id.response].text.to_string()))
+                .collect(),
+        });
+        for id in ids {
+            self.choose(id.response, &responses[id.response]);
+        }
+
+        let next = self.follow_label(None);
+        self.move_on(next);
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use lexer::parse;
+
+    use crate::{Prompter, PrompterErr};
+
+    #[test]
+    fn answers_with_several_responses() {
+        let data = read_to_string("./multi_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+        let ids = prompter.response_ids();
+        assert_eq!(ids.len(), 3);
+
+        let response = prompter.current().responses[0].clone();
+        assert_eq!(prompter.response_id(&response), Some(ids[0]));
+        assert!(matches!(
+            prompter.clone().answer(&response),
+            Err(PrompterErr::SelectionRequired)
+        ));
+
+        let party = prompter.answer_many(&[ids[2], ids[0]]).unwrap();
+        assert_eq!(party.current().label, Some("PARTY"));
+        assert_eq!(party.variables().get_bool("likes_cake"), Some(true));
+        assert_eq!(party.variables().get_bool("likes_balloons"), Some(true));
+        assert_eq!(party.variables().get_bool("likes_candles"), None);
+        assert_eq!(party.score("score"), 3);
+    }
+
+    #[test]
+    fn turns_down_bad_selections() {
+        let data = read_to_string("./multi_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+        let ids = prompter.response_ids();
+
+        assert!(matches!(
+            prompter.clone().answer_many(&[]),
+            Err(PrompterErr::SelectionCount(_))
+        ));
+        assert!(matches!(
+            prompter.clone().answer_many(&ids),
+            Err(PrompterErr::SelectionCount(_))
+        ));
+        assert!(matches!(
+            prompter.clone().answer_many(&[ids[0], ids[0]]),
+            Err(PrompterErr::BadResponse)
+        ));
+
+        let party = prompter.answer_many(&ids[..1]).unwrap();
+        assert!(matches!(
+            party.clone().answer_many(&ids[..1]),
+            Err(PrompterErr::BadResponse)
+        ));
+    }
+
+    #[test]
+    fn turns_down_ids_of_earlier_prompts() {
+        let data = "> (START) \"Pick some\" [multi]\n\
+                    < \"A\"\n< \"B\"\n< \"C\"\n\
+                    > \"Pick more\" [multi]\n\
+                    < (END) \"D\"\n";
+        let prompter = Prompter::new(parse(data).unwrap()).unwrap();
+        let ids = prompter.response_ids();
+
+        let next = prompter.answer_many(&ids[..1]).unwrap();
+        assert!(matches!(
+            next.answer_many(&ids[2..]),
+            Err(PrompterErr::BadResponse)
+        ));
+    }
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use lexer::parser::Response;
+
+use crate::{Prompter, PrompterErr};
+
+impl<'a> Prompter<'a> {
+    fn default_index(&self) -> Option<usize> {
+        self.offered()
+            .find(|idx| self.current().responses[*idx].default)
+    }
+
+    /// The current prompt's `[default]` response, if it's available.
+    pub fn default_response(&self) -> Option<&Response<'a>> {
+        self.default_index()
+            .map(|idx| &self.current().responses[idx])
+    }
+
+    /// How long to wait for an answer before calling `timeout`, set when the
+    /// current prompt has a `[timeout ...]` and a default to take.
+    pub fn timeout_duration(&self) -> Option<Duration> {
+        if self.is_finished() {
+            return None;
+        }
+        self.default_response()?;
+        self.current().timeout
+    }
+
+    /// Answers with the `[default]` response, for when the prompt timed out
+    /// or the user submitted nothing.
+    pub fn timeout(self) -> Result<Prompter<'a>, PrompterErr> {
+        if self.is_finished() {
+            return Err(PrompterErr::NoMoreQ);
+        }
+        let index = self.default_index().ok_or(PrompterErr::NoDefault)?;
+
+        if self.current().multi.is_some() {
+            let id = self
+                .response_ids()
+                .into_iter()
+                .find(|id| id.index() == index);
+            return self.answer_many(&Vec::from_iter(id));
+        }
+        let text = self.current().responses[index].text;
+        Ok(self.take(index, text, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::read_to_string, time::Duration};
+
+    use lexer::parse;
+
+    use crate::{Prompter, PrompterErr};
+
+    #[test]
+    fn takes_the_default_on_timeout() {
+        let data = read_to_string("./timeout_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+        assert_eq!(prompter.timeout_duration(), Some(Duration::from_secs(30)));
+        assert_eq!(prompter.default_response().unwrap().text, "I'm here");
+
+        let here = prompter.timeout().unwrap();
+        assert_eq!(here.current().label, Some("NAME"));
+        assert_eq!(here.timeout_duration(), None);
+
+        // Empty input takes the default too
+        let named = here.clone().answer_input("  ").unwrap();
+        assert_eq!(named.variables().get_str("name"), Some("friend"));
+        let named = here.answer_input("Ada").unwrap();
+        assert_eq!(named.variables().get_str("name"), Some("Ada"));
+
+        // Without a default there's nothing to take
+        assert!(matches!(named.timeout(), Err(PrompterErr::NoDefault)));
+    }
+}
@@ -0,0 +1,475 @@
+use std::fmt::{self, Display};
+
+use crate::json::{Json, JsonError};
+use crate::{Prompter, PrompterErr};
+
+/// A single step of a recorded conversation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranscriptEntry {
+    Prompt {
+        label: Option<String>,
+        text: String,
+    },
+    Answer {
+        index: usize,
+        text: String,
+    },
+    /// Several responses chosen at once, as indices and texts.
+    Selection {
+        answers: Vec<(usize, String)>,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TranscriptErr {
+    InvalidJson { line: usize, err: JsonError },
+    InvalidEntry { line: usize },
+}
+
+/// Everything shown to and answered by the user, in order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Transcript {
+    entries: Vec<TranscriptEntry>,
+}
+
+/// Where a replayed conversation stopped matching its transcript. `found` is
+/// `None` when the script has nothing at that point, e.g. the conversation
+/// ended early or the recorded response no longer exists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub entry: usize,
+    pub expected: TranscriptEntry,
+    pub found: Option<TranscriptEntry>,
+}
+
+impl TranscriptEntry {
+    /// The entry as one line of `to_jsonl`.
+    pub fn to_json(&self) -> Json {
+        match self {
+            TranscriptEntry::Prompt { label, text } => Json::object([
+                ("type", Json::from("prompt")),
+                ("label", Json::from(label.clone())),
+                ("text", Json::from(text.as_str())),
+            ]),
+            TranscriptEntry::Answer { index, text } => Json::object([
+                ("type", Json::from("answer")),
+                ("index", Json::from(*index)),
+                ("text", Json::from(text.as_str())),
+            ]),
+            TranscriptEntry::Selection { answers } => Json::object([
+                ("type", Json::from("selection")),
+                (
+                    "answers",
+                    Json::Array(
+                        answers
+                            .iter()
+                            .map(|(index, text)| {
+                                Json::object([
+                                    ("index", Json::from(*index)),
+                                    ("text", Json::from(text.as_str())),
+                                ])
+                            })
+                            .collect(),
+                    ),
+                ),
+            ]),
+        }
+    }
+
+    fn answer_from_json(value: &Json) -> Option<(usize, String)> {
+        let index = value.get("index")?.as_u64()? as usize;
+        let text = value.get("text")?.as_str()?.to_string();
+        Some((index, text))
+    }
+
+    /// An entry from one line of `to_jsonl`, `None` when it isn't one.
+    pub fn from_json(value: &Json) -> Option<TranscriptEntry> {
+        if value.get("type")?.as_str()? == "selection" {
+            let answers = value.get("answers")?.as_array()?;
+            let answers = answers
+                .iter()
+                .map(Self::answer_from_json)
+                .collect::<Option<_>>()?;
+            return Some(TranscriptEntry::Selection { answers });
+        }
+
+        let text = value.get("text")?.as_str()?.to_string();
+        match value.get("type")?.as_str()? {
+            "prompt" => {
+                let label = match value.get("label") {
+                    None | Some(Json::Null) => None,
+                    Some(label) => Some(label.as_str()?.to_string()),
+                };
+                Some(TranscriptEntry::Prompt { label, text })
+            }
+            "answer" => {
+                let index = value.get("index")?.as_u64()? as usize;
+                Some(TranscriptEntry::Answer { index, text })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for TranscriptEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranscriptEntry::Prompt {
+                label: Some(label),
+                text,
+            } => write!(f, "> ({label}) \"{text}\""),
+            TranscriptEntry::Prompt { label: None, text } => write!(f, "> \"{text}\""),
+            TranscriptEntry::Answer { text, .. } => write!(f, "< \"{text}\""),
+            TranscriptEntry::Selection { answers } => {
+                let texts: Vec<String> = answers
+                    .iter()
+                    .map(|(_, text)| format!("\"{text}\""))
+                    .collect();
+                write!(f, "< {}", texts.join(", "))
+            }
+        }
+    }
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.found {
+            Some(found) => write!(
+                f,
+                "entry {}: expected {}, found {}",
+                self.entry, self.expected, found
+            ),
+            None => write!(
+                f,
+                "entry {}: expected {}, found nothing",
+                self.entry, self.expected
+            ),
+        }
+    }
+}
+
+impl Transcript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn entries(&self) -> &[TranscriptEntry] {
+        &self.entries
+    }
+
+    pub(crate) fn push(&mut self, entry: TranscriptEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Human readable export, one step per line in the style of the DSL.
+    pub fn to_text(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| format!("{entry}\n"))
+            .collect()
+    }
+
+    /// JSON Lines export, one object per step.
+    pub fn to_jsonl(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| format!("{}\n", entry.to_json()))
+            .collect()
+    }
+
+    pub fn from_jsonl(data: &str) -> Result<Transcript, TranscriptErr> {
+        let mut entries = vec![];
+
+        for (idx, line) in data.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let line_no = idx + 1;
+            let value = Json::parse(line)
+                .map_err(|err| TranscriptErr::InvalidJson { line: line_no, err })?;
+            let entry = TranscriptEntry::from_json(&value)
+                .ok_or(TranscriptErr::InvalidEntry { line: line_no })?;
+            entries.push(entry);
+        }
+
+        Ok(Transcript { entries })
+    }
+}
+
+impl FromIterator<TranscriptEntry> for Transcript {
+    fn from_iter<I: IntoIterator<Item = TranscriptEntry>>(entries: I) -> Self {
+        Transcript {
+            entries: entries.into_iter().collect(),
+        }
+    }
+}
+
+impl<'a> Prompter<'a> {
+    /// Re-drives the conversation from a transcript, checking every recorded
+    /// prompt and answer against the script as it is now.
+    pub fn replay(self, transcript: &Transcript) -> Result<Prompter<'a>, Divergence> {
+        let mut prompter = self;
+
+        for (entry, expected) in transcript.entries().iter().enumerate() {
+            // The last prompt shown stays current once finished, only
+            // answers need more
+            if prompter.is_finished() && !matches!(expected, TranscriptEntry::Prompt { .. }) {
+                return Err(Divergence {
+                    entry,
+                    expected: expected.clone(),
+                    found: None,
+                });
+            }
+
+            let next = prompter.current().clone();
+            match expected {
+                TranscriptEntry::Prompt { label, text } => {
+                    if next.label != label.as_deref() || next.text != text {
+                        return Err(Divergence {
+                            entry,
+                            expected: expected.clone(),
+                            found: Some(TranscriptEntry::Prompt {
+                                label: next.label.map(str::to_string),
+                                text: next.text.to_string(),
+                            }),
+                        });
+                    }
+                    // Statements are moved on from as soon as they're shown
+                    if prompter.is_statement() {
+                        prompter = prompter.advance().map_err(|_| Divergence {
+                            entry,
+                            expected: expected.clone(),
+                            found: None,
+                        })?;
+                    }
+                }
+                // Typed in answers are replayed as typed
+                TranscriptEntry::Answer { index, text }
+                    if next
+                        .responses
+                        .get(*index)
+                        .is_some_and(|r| r.input.is_some()) =>
+                {
+                    prompter = prompter
+                        .answer_input_at(*index, text)
+                        .map_err(|_| Divergence {
+                            entry,
+                            expected: expected.clone(),
+                            found: None,
+                        })?;
+                }
+                TranscriptEntry::Answer { index, text } => {
+                    let response = next.responses.get(*index).filter(|r| r.text == text);
+                    let Some(response) = response else {
+                        return Err(Divergence {
+                            entry,
+                            expected: expected.clone(),
+                            found: next.responses.get(*index).map(|r| TranscriptEntry::Answer {
+                                index: *index,
+                                text: r.text.to_string(),
+                            }),
+                        });
+                    };
+
+                    prompter = prompter.answer(response).map_err(|_| Divergence {
+                        entry,
+                        expected: expected.clone(),
+                        found: None,
+                    })?;
+                }
+                TranscriptEntry::Selection { answers } => {
+                    let ids = answers
+                        .iter()
+                        .map(|(index, text)| {
+                            let response = next.responses.get(*index).filter(|r| r.text == text)?;
+                            prompter.response_id(response)
+                        })
+                        .collect::<Option<Vec<_>>>();
+                    let answered = ids
+                        .ok_or(PrompterErr::BadResponse)
+                        .and_then(|ids| prompter.answer_many(&ids));
+                    prompter = answered.map_err(|_| Divergence {
+                        entry,
+                        expected: expected.clone(),
+                        found: None,
+                    })?;
+                }
+            }
+        }
+
+        Ok(prompter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use lexer::parse;
+
+    use super::{Transcript, TranscriptEntry};
+    use crate::Prompter;
+
+    fn record_loop(data: &str) -> Transcript {
+        let prompts = parse(data).unwrap();
+        let prompter = Prompter::new(prompts).unwrap().with_transcript();
+        let first = prompter.next();
+        let prompter = prompter.answer(&first.responses[1]).unwrap();
+        let second = prompter.next();
+        let prompter = prompter.answer(&second.responses[0]).unwrap();
+
+        prompter.transcript().unwrap().clone()
+    }
+
+    #[test]
+    fn records_prompts_and_answers() {
+        let data = read_to_string("./simple_prompt.txt").unwrap();
+        let transcript = record_loop(&data);
+
+        assert_eq!(transcript.entries().len(), 5);
+        assert_eq!(
+            transcript.entries()[1],
+            TranscriptEntry::Answer {
+                index: 1,
+                text: "No".to_string()
+            }
+        );
+        assert_eq!(
+            transcript.to_text(),
+            "> (START) \"Are you a human?\"\n\
+             < \"No\"\n\
+             > (ANS_NO) \"That's very weird! Care to try again?\"\n\
+             < \"Please!\"\n\
+             > (START) \"Are you a human?\"\n"
+        );
+    }
+
+    #[test]
+    fn round_trips_through_jsonl() {
+        let data = read_to_string("./simple_prompt.txt").unwrap();
+        let transcript = record_loop(&data);
+
+        let exported = transcript.to_jsonl();
+        assert_eq!(exported.lines().count(), 5);
+        assert_eq!(Transcript::from_jsonl(&exported), Ok(transcript));
+    }
+
+    #[test]
+    fn replays_a_transcript() {
+        let data = read_to_string("./simple_prompt.txt").unwrap();
+        let transcript = record_loop(&data);
+
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+        let replayed = prompter.replay(&transcript).ok().unwrap();
+        assert_eq!(replayed.next().label, Some("START"));
+    }
+
+    #[test]
+    fn replays_a_finished_conversation() {
+        let data = read_to_string("./outcome_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap())
+            .unwrap()
+            .with_transcript();
+        let start = prompter.next();
+        let prompter = prompter.answer(&start.responses[1]).unwrap();
+        let sad = prompter.next();
+        let prompter = prompter.answer(&sad.responses[0]).unwrap();
+        assert!(prompter.is_finished());
+
+        let transcript = prompter.transcript().unwrap();
+        let replayed = Prompter::new(parse(&data).unwrap())
+            .unwrap()
+            .replay(transcript)
+            .ok()
+            .unwrap();
+        assert!(replayed.is_finished());
+        assert_eq!(replayed.next().text, "Here's a big hug!");
+    }
+
+    #[test]
+    fn reports_first_divergence() {
+        let data = read_to_string("./simple_prompt.txt").unwrap();
+        let transcript = record_loop(&data);
+
+        let changed = data.replace("Care to try again?", "Try again?");
+        let prompter = Prompter::new(parse(&changed).unwrap()).unwrap();
+        let divergence = prompter.replay(&transcript).err().unwrap();
+
+        assert_eq!(divergence.entry, 2);
+        assert_eq!(
+            divergence.found,
+            Some(TranscriptEntry::Prompt {
+                label: Some("ANS_NO".to_string()),
+                text: "That's very weird! Try again?".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn replays_typed_in_answers() {
+        let data = read_to_string("./input_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+        let recorded = prompter
+            .clone()
+            .with_transcript()
+            .answer_input("Ada")
+            .unwrap();
+        let transcript = recorded.transcript().unwrap();
+        assert_eq!(
+            transcript.entries()[1],
+            TranscriptEntry::Answer {
+                index: 0,
+                text: "Ada".to_string()
+            }
+        );
+
+        let replayed = prompter.replay(transcript).ok().unwrap();
+        assert_eq!(replayed.variables().get_str("name"), Some("Ada"));
+    }
+
+    #[test]
+    fn replays_typed_in_answers_into_the_recorded_input() {
+        let data = "> (START) \"Nickname or name?\"\n\
+                    < (END) input nickname\n\
+                    < (END) input name\n";
+        let prompter = Prompter::new(parse(data).unwrap()).unwrap();
+        let mut transcript = Transcript::new();
+        transcript.push(TranscriptEntry::Prompt {
+            label: Some("START".to_string()),
+            text: "Nickname or name?".to_string(),
+        });
+        transcript.push(TranscriptEntry::Answer {
+            index: 1,
+            text: "Ada".to_string(),
+        });
+
+        let replayed = prompter.replay(&transcript).ok().unwrap();
+        assert_eq!(replayed.variables().get_str("name"), Some("Ada"));
+        assert_eq!(replayed.variables().get_str("nickname"), None);
+    }
+
+    #[test]
+    fn records_and_replays_selections() {
+        let data = read_to_string("./multi_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+        let ids = prompter.response_ids();
+        let recorded = prompter
+            .clone()
+            .with_transcript()
+            .answer_many(&[ids[1], ids[0]])
+            .unwrap();
+        let transcript = recorded.transcript().unwrap();
+        assert_eq!(
+            transcript.to_text().lines().nth(1),
+            Some("< \"Candles\", \"Cake\"")
+        );
+
+        let parsed = Transcript::from_jsonl(&transcript.to_jsonl()).unwrap();
+        assert_eq!(&parsed, transcript);
+
+        let replayed = prompter.replay(&parsed).ok().unwrap();
+        assert_eq!(replayed.variables().get_bool("likes_candles"), Some(true));
+        assert_eq!(replayed.current().label, Some("PARTY"));
+    }
+}
@@ -0,0 +1,355 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+};
+
+use lexer::{
+    parser::{Advance, InputCheck},
+    template::{parse_template, Filter, Segment},
+};
+
+use crate::{graph::target_labels, pattern::Pattern, script::Script, Ending};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found in a script, located by prompt index and, when it's about
+/// a response, that response's index within the prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic<'a> {
+    pub severity: Severity,
+    pub prompt: usize,
+    pub response: Option<usize>,
+    pub message: String,
+    /// The label it's about, borrowed from the script's text.
+    pub label: Option<&'a str>,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+impl Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: prompt {}", self.severity, self.prompt)?;
+        if let Some(response) = self.response {
+            write!(f, ", response {response}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+struct Validator<'v, 's> {
+    known_variables: HashSet<&'v str>,
+    diagnostics: Vec<Diagnostic<'s>>,
+}
+
+impl<'s> Validator<'_, 's> {
+    fn report(&mut self, severity: Severity, at: (usize, Option<usize>), message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            prompt: at.0,
+            response: at.1,
+            message,
+            label: None,
+        });
+    }
+
+    fn report_label(
+        &mut self,
+        severity: Severity,
+        at: (usize, Option<usize>),
+        label: &'s str,
+        message: String,
+    ) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            prompt: at.0,
+            response: at.1,
+            message,
+            label: Some(label),
+        });
+    }
+
+    // Labels followed from a prompt or response have to lead somewhere
+    fn check_labels(
+        &mut self,
+        script: &Script<'s>,
+        labels: impl IntoIterator<Item = &'s str>,
+        at: (usize, Option<usize>),
+    ) {
+        for label in labels {
+            if script.find(label).is_none() && Ending::from_label(label).is_none() {
+                let message = format!("no prompt is labelled `{label}`");
+                self.report_label(Severity::Error, at, label, message);
+            }
+        }
+    }
+
+    fn check_text(&mut self, text: &str, at: (usize, Option<usize>)) {
+        let segments = match parse_template(text) {
+            Ok(segments) => segments,
+            Err(err) => {
+                self.report(Severity::Error, at, err.to_string());
+                return;
+            }
+        };
+
+        for segment in segments {
+            let Segment::Placeholder(placeholder) = segment else {
+                continue;
+            };
+
+            let has_default = placeholder
+                .filters
+                .iter()
+                .any(|filter| matches!(filter, Filter::Default(_)));
+            if !has_default && !self.known_variables.contains(placeholder.name) {
+                let message = format!("variable `{}` is never set", placeholder.name);
+                self.report(Severity::Warning, at, message);
+            }
+        }
+    }
+}
+
+// Where a statement certainly goes, unless guards or targets make it depend
+// on how the conversation went
+fn statement_step(script: &Script, idx: usize) -> Option<usize> {
+    let prompt = script.get(idx)?;
+    if prompt.guard.is_some() || prompt.target.is_some() {
+        return None;
+    }
+
+    let next_idx = match prompt.advance? {
+        Advance::Next => idx + 1,
+        Advance::Goto(label) => script.find(label)?,
+    };
+    script.get(next_idx).filter(|next| next.guard.is_none())?;
+    Some(next_idx)
+}
+
+// Whether following statements from `start` comes back to it, reported once
+// per loop at its first prompt
+fn starts_statement_loop(script: &Script, start: usize) -> bool {
+    let mut seen = vec![start];
+    let mut idx = start;
+    while let Some(next_idx) = statement_step(script, idx) {
+        if next_idx == start {
+            return seen.iter().all(|seen| *seen >= start);
+        }
+        if seen.contains(&next_idx) {
+            return false;
+        }
+        seen.push(next_idx);
+        idx = next_idx;
+    }
+    false
+}
+
+/// Checks a script for problems that would only show up while prompting.
+/// `host_variables` are the names the host application provides itself.
+pub fn validate<'a>(script: &Script<'a>, host_variables: &[&str]) -> Vec<Diagnostic<'a>> {
+    let mut known_variables: HashSet<&str> = host_variables.iter().copied().collect();
+    known_variables.extend(script.scores());
+    for prompt in script.prompts() {
+        for response in &prompt.responses {
+            known_variables.extend(response.assignments.iter().map(|a| a.name));
+            known_variables.extend(response.input.as_ref().map(|input| input.variable));
+        }
+    }
+
+    let mut validator = Validator {
+        known_variables,
+        diagnostics: vec![],
+    };
+
+    for (prompt_idx, prompt) in script.prompts().iter().enumerate() {
+        let at = (prompt_idx, None);
+        validator.check_text(prompt.text, at);
+        if starts_statement_loop(script, prompt_idx) {
+            let message = "statements loop forever without asking anything".to_string();
+            validator.report(Severity::Error, at, message);
+        }
+        if let Some(label) = prompt
+            .label
+            .filter(|label| script.find(label) != Some(prompt_idx))
+        {
+            let message = format!("another prompt is already labelled `{label}`");
+            validator.report_label(Severity::Warning, at, label, message);
+        }
+        let goto = match prompt.advance {
+            Some(Advance::Goto(label)) => Some(label),
+            _ => None,
+        };
+        let targets = prompt.target.iter().flat_map(target_labels);
+        validator.check_labels(script, goto.into_iter().chain(targets), at);
+
+        for (response_idx, response) in prompt.responses.iter().enumerate() {
+            let at = (prompt_idx, Some(response_idx));
+            validator.check_text(response.text, at);
+            let labelled = response.label.is_some() || response.target.is_some();
+            if prompt.multi.is_some() && labelled {
+                let message = "multi-select responses don't follow their own label".to_string();
+                validator.report(Severity::Warning, at, message);
+            } else {
+                let targets = response.target.iter().flat_map(target_labels);
+                validator.check_labels(script, response.label.into_iter().chain(targets), at);
+            }
+
+            let Some(input) = &response.input else {
+                continue;
+            };
+            if let Some(retry) = input.retry {
+                validator.check_text(retry, at);
+            }
+            if let Some(InputCheck::Regex(pattern)) = &input.check {
+                if let Err(err) = Pattern::new(pattern) {
+                    validator.report(Severity::Error, at, err.to_string());
+                }
+            }
+        }
+    }
+
+    validator.diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use lexer::parse;
+
+    use super::{validate, Diagnostic, Severity};
+    use crate::script::Script;
+
+    #[test]
+    fn warns_about_unset_variables() {
+        let data = read_to_string("./template_prompt.txt").unwrap();
+        let script = Script::new(parse(&data).unwrap()).unwrap();
+
+        let diagnostics = validate(&script, &["name", "sender"]);
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                severity: Severity::Warning,
+                prompt: 2,
+                response: None,
+                message: "variable `missing_sender` is never set".to_string(),
+                label: None,
+            }]
+        );
+
+        let diagnostics = validate(&script, &[]);
+        assert_eq!(diagnostics.len(), 4);
+        assert_eq!(diagnostics[0].prompt, 0);
+        assert_eq!(diagnostics[0].response, Some(0));
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "warning: prompt 0, response 0: variable `sender` is never set"
+        );
+    }
+
+    #[test]
+    fn reports_invalid_templates() {
+        let data = "> \"Hi {name\"\n< \"Hey {name|shout}\" {name = \"Ada\"}";
+        let script = Script::new(parse(data).unwrap()).unwrap();
+
+        let diagnostics = validate(&script, &[]);
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Error));
+        assert_eq!(diagnostics[1].message, "unknown filter `shout`");
+    }
+
+    #[test]
+    fn knows_input_variables() {
+        let data = read_to_string("./input_prompt.txt").unwrap();
+        let script = Script::new(parse(&data).unwrap()).unwrap();
+
+        assert_eq!(validate(&script, &[]), vec![]);
+    }
+
+    #[test]
+    fn reports_invalid_patterns() {
+        let data = "> \"Code?\"\n< input code [regex \"[A-Z\"] [retry \"Hi {who}\"]";
+        let script = Script::new(parse(data).unwrap()).unwrap();
+
+        let diagnostics = validate(&script, &[]);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].message, "invalid `[...]` class in pattern");
+    }
+
+    #[test]
+    fn reports_statement_loops() {
+        let data = read_to_string("./story_prompt.txt").unwrap();
+        let script = Script::new(parse(&data).unwrap()).unwrap();
+
+        let diagnostics = validate(&script, &[]);
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                severity: Severity::Error,
+                prompt: 5,
+                response: None,
+                message: "statements loop forever without asking anything".to_string(),
+                label: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn warns_about_multi_select_labels() {
+        let data = read_to_string("./multi_prompt.txt").unwrap();
+        let script = Script::new(parse(&data).unwrap()).unwrap();
+
+        let diagnostics = validate(&script, &[]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].response, Some(2));
+        assert_eq!(
+            diagnostics[0].message,
+            "multi-select responses don't follow their own label"
+        );
+    }
+
+    #[test]
+    fn reports_labels_that_lead_nowhere() {
+        let data = "> (START) \"Hi\" -> GONE\n\
+                    > (START) \"Again?\" [continue ELSEWHERE]\n\
+                    < (TYPO) \"Bye\"\n\
+                    < (END:happy) \"Done\"\n\
+                    < \"Spin\" [random END, LOST 2]\n\
+                    < \"Grade\" [bands 5.. GOLD, 2..4 SILVER, \"END:meh\"] [score 1]\n\
+                    > (GOLD) \"Gold!\"\n";
+        let script = Script::new(parse(data).unwrap()).unwrap();
+
+        let diagnostics = validate(&script, &[]);
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.severity, d.prompt, d.response, d.label.unwrap()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Severity::Error, 0, None, "GONE"),
+                (Severity::Warning, 1, None, "START"),
+                (Severity::Error, 1, None, "ELSEWHERE"),
+                (Severity::Error, 1, Some(0), "TYPO"),
+                (Severity::Error, 1, Some(2), "LOST"),
+                (Severity::Error, 1, Some(3), "SILVER"),
+            ]
+        );
+        assert_eq!(diagnostics[3].message, "no prompt is labelled `TYPO`");
+        assert_eq!(
+            diagnostics[1].message,
+            "another prompt is already labelled `START`"
+        );
+    }
+}
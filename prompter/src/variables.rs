@@ -0,0 +1,161 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+};
+
+use lexer::expr::Literal;
+
+/// A conversation variable's value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Bool(bool),
+}
+
+impl From<&Literal<'_>> for Value {
+    fn from(literal: &Literal) -> Self {
+        match literal {
+            Literal::Str(s) => Value::Str(s.to_string()),
+            Literal::Int(n) => Value::Int(*n),
+            Literal::Bool(b) => Value::Bool(*b),
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Str(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Str(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Str(s) => f.write_str(s),
+            Value::Int(n) => write!(f, "{n}"),
+            Value::Bool(b) => write!(f, "{b}"),
+        }
+    }
+}
+
+/// Named values set over the course of a conversation, kept in name order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Variables {
+    values: BTreeMap<String, Value>,
+}
+
+impl Variables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn get_int(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            Value::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<Value>) {
+        self.values.insert(name.into(), value.into());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use lexer::parse;
+
+    use super::Value;
+    use crate::Prompter;
+
+    #[test]
+    fn answers_set_typed_variables() {
+        let data = read_to_string("./variables_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+        assert!(prompter.variables().is_empty());
+
+        let response = prompter.current().responses[1].clone();
+        let prompter = prompter.answer(&response).unwrap();
+        let response = prompter.current().responses[0].clone();
+        let prompter = prompter.answer(&response).unwrap();
+
+        let variables = prompter.variables();
+        assert_eq!(variables.get_str("colour"), Some("red"));
+        assert_eq!(variables.get_int("candles"), Some(30));
+        assert_eq!(variables.get_bool("likes_cake"), Some(true));
+        assert_eq!(variables.get_int("colour"), None);
+        assert_eq!(variables.len(), 3);
+    }
+
+    #[test]
+    fn later_answers_overwrite_and_host_can_preset() {
+        let data = read_to_string("./variables_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap())
+            .unwrap()
+            .with_variable("colour", "green")
+            .with_variable("guest", true);
+        assert_eq!(
+            prompter.variables().get("colour"),
+            Some(&Value::from("green"))
+        );
+
+        let response = prompter.current().responses[0].clone();
+        let prompter = prompter.answer(&response).unwrap();
+
+        assert_eq!(prompter.variables().get_str("colour"), Some("blue"));
+        assert_eq!(prompter.variables().get_bool("guest"), Some(true));
+    }
+}
@@ -0,0 +1,29 @@
+use std::{
+    env,
+    io::{self, BufReader},
+    process::ExitCode,
+};
+
+use promptgen::lsp::Server;
+
+const USAGE: &str = "\
+usage: promptgen-lsp
+
+A language server for promptgen scripts, speaking LSP on stdin and stdout.
+Point an editor's LSP client at it for files ending in .pg.";
+
+fn main() -> ExitCode {
+    if env::args().nth(1).is_some() {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+
+    let mut reader = BufReader::new(io::stdin().lock());
+    match Server::new().serve(&mut reader, &mut io::stdout().lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("promptgen-lsp: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::{
+    env,
+    fs::read_to_string,
+    net::{Ipv4Addr, TcpListener},
+    path::Path,
+    process::ExitCode,
+    time::Duration,
+};
+
+use promptgen::{
+    server::{Server, DEFAULT_TTL},
+    session::LoadErr,
+};
+
+const USAGE: &str = "\
+usage: promptgen-server <scripts...> [--port N] [--ttl SECONDS]
+
+Serves the scripts as a JSON API on localhost, each named after its file
+without the extension. Sessions unused for --ttl seconds, 1800 by default,
+are forgotten.";
+
+fn serve(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut port = 8080;
+    let mut ttl = DEFAULT_TTL;
+    let mut paths = vec![];
+
+    while let Some(arg) = args.next() {
+        let mut number = |name: &str| -> Result<u64, String> {
+            let value = args.next().ok_or(format!("{name} needs a value"))?;
+            value
+                .parse()
+                .map_err(|_| format!("{name} needs a number, not {value}"))
+        };
+        match arg.as_str() {
+            "--port" => {
+                port = u16::try_from(number("--port")?).map_err(|_| "--port is too big")?;
+            }
+            "--ttl" => ttl = Duration::from_secs(number("--ttl")?),
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        return Err(USAGE.to_string());
+    }
+
+    let mut server = Server::new(ttl);
+    for path in &paths {
+        let data = read_to_string(path).map_err(|err| format!("can't read {path}: {err}"))?;
+        let name = Path::new(path)
+            .file_stem()
+            .map_or(path.clone(), |stem| stem.to_string_lossy().into_owned());
+        server.load(name, data).map_err(|err| match err {
+            LoadErr::Syntax { .. } => format!("{path}:{err}"),
+            LoadErr::NoPrompts => format!("{path}: {err}"),
+        })?;
+    }
+
+    // Only ever on localhost, there's no authentication
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .map_err(|err| format!("can't listen on port {port}: {err}"))?;
+    eprintln!("promptgen-server: listening on http://127.0.0.1:{port}");
+    server
+        .serve(listener)
+        .map_err(|err| format!("stopped serving: {err}"))
+}
+
+fn main() -> ExitCode {
+    match serve(env::args().skip(1)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("promptgen-server: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! Parsing and validating a script, with problems located in its text.
+
+use lexer::parse_located;
+use prompter::{
+    script::Script,
+    validate::{validate, Diagnostic, Severity},
+};
+
+/// A problem in one script, at a byte offset when it can be pointed at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+    pub offset: Option<usize>,
+}
+
+/// Byte offset of a slice borrowed from `data`.
+pub fn offset_in(data: &str, slice: &str) -> Option<usize> {
+    let offset = (slice.as_ptr() as usize).checked_sub(data.as_ptr() as usize)?;
+    (offset < data.len() && !slice.is_empty()).then_some(offset)
+}
+
+// Where a validation diagnostic points, the label it's about or else the
+// earliest of its label, text and input variable
+fn diagnostic_offset(data: &str, script: &Script, diagnostic: &Diagnostic) -> Option<usize> {
+    if let Some(offset) = diagnostic.label.and_then(|label| offset_in(data, label)) {
+        return Some(offset);
+    }
+    let prompt = script.get(diagnostic.prompt)?;
+    let slices = match diagnostic.response {
+        Some(idx) => {
+            let response = prompt.responses.get(idx)?;
+            let variable = response.input.as_ref().map(|input| input.variable);
+            vec![response.label, Some(response.text), variable]
+        }
+        None => vec![prompt.label, Some(prompt.text)],
+    };
+
+    slices
+        .into_iter()
+        .flatten()
+        .filter_map(|slice| offset_in(data, slice))
+        .min()
+}
+
+/// Parses and validates a script, `host_variables` being the names the host
+/// application provides.
+pub fn check(data: &str, host_variables: &[&str]) -> Vec<Finding> {
+    let error = |message: String, offset| Finding {
+        severity: Severity::Error,
+        message,
+        offset,
+    };
+
+    let prompts = match parse_located(data) {
+        Ok(prompts) => prompts,
+        Err(err) => return vec![error(err.message, Some(err.offset))],
+    };
+    let Ok(script) = Script::new(prompts) else {
+        return vec![error("script has no prompts".to_string(), None)];
+    };
+
+    validate(&script, host_variables)
+        .into_iter()
+        .map(|diagnostic| Finding {
+            severity: diagnostic.severity,
+            offset: diagnostic_offset(data, &script, &diagnostic),
+            message: diagnostic.message,
+        })
+        .collect()
+}
@@ -0,0 +1,262 @@
+use std::process::ExitCode;
+
+use lexer::line_and_column;
+use prompter::{json::Json, validate::Severity};
+use promptgen::check::{check, Finding};
+
+use super::{files, read, Args};
+
+const USAGE: &str =
+    "usage: promptgen check <scripts...> [--format human|json|sarif] [--deny-warnings] [--vars a,b]";
+
+/// The findings for one script, along with its text for snippets.
+pub struct Report {
+    pub path: String,
+    pub data: String,
+    pub findings: Vec<Finding>,
+}
+
+impl Report {
+    fn location(&self, finding: &Finding) -> Option<(usize, usize)> {
+        Some(line_and_column(&self.data, finding.offset?))
+    }
+
+    fn human(&self) -> String {
+        let mut out = String::new();
+
+        for finding in &self.findings {
+            out += &format!("{}: {}\n", finding.severity, finding.message);
+            let Some((line, column)) = self.location(finding) else {
+                out += &format!("  --> {}\n\n", self.path);
+                continue;
+            };
+
+            let source = self.data.lines().nth(line - 1).unwrap_or_default();
+            let gutter = " ".repeat(line.to_string().len());
+            out += &format!("{gutter}--> {}:{line}:{column}\n", self.path);
+            out += &format!("{gutter} |\n{line} | {source}\n");
+            out += &format!("{gutter} | {}^\n\n", " ".repeat(column - 1));
+        }
+        out
+    }
+
+    fn json(&self) -> Vec<Json> {
+        self.findings
+            .iter()
+            .map(|finding| {
+                let location = self.location(finding);
+                Json::object([
+                    ("file", Json::from(self.path.as_str())),
+                    ("severity", Json::from(finding.severity.to_string())),
+                    ("message", Json::from(finding.message.as_str())),
+                    ("line", Json::from(location.map(|(line, _)| line))),
+                    ("column", Json::from(location.map(|(_, column)| column))),
+                ])
+            })
+            .collect()
+    }
+
+    fn sarif(&self) -> Vec<Json> {
+        self.findings
+            .iter()
+            .map(|finding| {
+                let mut physical = vec![(
+                    "artifactLocation",
+                    Json::object([("uri", Json::from(self.path.as_str()))]),
+                )];
+                if let Some((line, column)) = self.location(finding) {
+                    let region = [
+                        ("startLine", Json::from(line)),
+                        ("startColumn", Json::from(column)),
+                    ];
+                    physical.push(("region", Json::object(region)));
+                }
+
+                Json::object([
+                    ("level", Json::from(finding.severity.to_string())),
+                    (
+                        "message",
+                        Json::object([("text", Json::from(finding.message.as_str()))]),
+                    ),
+                    (
+                        "locations",
+                        Json::Array(vec![Json::object([(
+                            "physicalLocation",
+                            Json::object(physical),
+                        )])]),
+                    ),
+                ])
+            })
+            .collect()
+    }
+}
+
+fn sarif_log(results: Vec<Json>) -> Json {
+    let driver = Json::object([
+        ("name", Json::from("promptgen")),
+        ("version", Json::from(env!("CARGO_PKG_VERSION"))),
+    ]);
+    let run = Json::object([
+        ("tool", Json::object([("driver", driver)])),
+        ("results", Json::Array(results)),
+    ]);
+
+    Json::object([
+        (
+            "$schema",
+            Json::from("https://json.schemastore.org/sarif-2.1.0.json"),
+        ),
+        ("version", Json::from("2.1.0")),
+        ("runs", Json::Array(vec![run])),
+    ])
+}
+
+/// `promptgen check <scripts...>`
+pub fn main(args: impl Iterator<Item = String>) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &["deny-warnings"], &["format", "vars"])?;
+    if args.positional().is_empty() {
+        return Err(USAGE.to_string());
+    }
+    let host_variables: Vec<&str> = args
+        .value("vars")
+        .map(|vars| vars.split(',').map(str::trim).collect())
+        .unwrap_or_default();
+
+    let reports: Vec<Report> = files(args.positional(), "pg")?
+        .into_iter()
+        .map(|path| match read(&path) {
+            Ok(data) => Report {
+                findings: check(&data, &host_variables),
+                path,
+                data,
+            },
+            Err(message) => Report {
+                findings: vec![Finding {
+                    severity: Severity::Error,
+                    message,
+                    offset: None,
+                }],
+                path,
+                data: String::new(),
+            },
+        })
+        .collect();
+
+    let findings = reports.iter().flat_map(|report| &report.findings);
+    let count = |severity| findings.clone().filter(|f| f.severity == severity).count();
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+
+    match args.value("format").unwrap_or("human") {
+        "human" => {
+            reports
+                .iter()
+                .for_each(|report| print!("{}", report.human()));
+            println!(
+                "checked {}: {}, {}",
+                counted(reports.len(), "script"),
+                counted(errors, "error"),
+                counted(warnings, "warning")
+            );
+        }
+        "json" => println!(
+            "{}",
+            Json::Array(reports.iter().flat_map(Report::json).collect())
+        ),
+        "sarif" => println!(
+            "{}",
+            sarif_log(reports.iter().flat_map(Report::sarif).collect())
+        ),
+        format => return Err(format!("unknown format {format}, use human, json or sarif")),
+    }
+
+    match errors > 0 || (args.flag("deny-warnings") && warnings > 0) {
+        true => Ok(ExitCode::FAILURE),
+        false => Ok(ExitCode::SUCCESS),
+    }
+}
+
+// Like `1 script` or `2 scripts`
+fn counted(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {noun}"),
+        count => format!("{count} {noun}s"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prompter::validate::Severity;
+
+    use promptgen::check::{check, Finding};
+
+    use super::{counted, Report};
+
+    fn report(data: &str) -> Report {
+        Report {
+            path: "card.pg".to_string(),
+            data: data.to_string(),
+            findings: check(data, &[]),
+        }
+    }
+
+    #[test]
+    fn points_at_problems() {
+        let data = "> \"Hi {name}\"\n< \"Hello\" [nope]\n";
+        let report = report(data);
+        assert_eq!(
+            report.findings,
+            [Finding {
+                severity: Severity::Error,
+                message: "unknown directive".to_string(),
+                offset: Some(24),
+            }]
+        );
+        assert_eq!(
+            report.human(),
+            "error: unknown directive\n \
+             --> card.pg:2:11\n  \
+             |\n\
+             2 | < \"Hello\" [nope]\n  \
+             |           ^\n\n"
+        );
+
+        let report = check(data.replace(" [nope]", "").as_str(), &["name"]);
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn points_at_labels() {
+        let data = "> (START) \"Hi\"\n< (TYPO) \"Bye\"\n> (START) \"Again\" -> END\n";
+        let offsets: Vec<_> = check(data, &[])
+            .into_iter()
+            .map(|finding| (finding.severity, finding.offset))
+            .collect();
+        assert_eq!(
+            offsets,
+            [(Severity::Error, Some(18)), (Severity::Warning, Some(33))]
+        );
+    }
+
+    #[test]
+    fn counts_in_the_plural() {
+        assert_eq!(counted(1, "script"), "1 script");
+        assert_eq!(counted(0, "error"), "0 errors");
+        assert_eq!(counted(2, "warning"), "2 warnings");
+    }
+
+    #[test]
+    fn reports_validation_as_json_and_sarif() {
+        let report = report("> (START) \"Hi\"\n< \"Hello {who}\"\n");
+
+        assert_eq!(
+            report.json()[0].to_string(),
+            r#"{"file":"card.pg","severity":"warning","message":"variable `who` is never set","line":2,"column":4}"#
+        );
+        let sarif = &report.sarif()[0];
+        assert_eq!(sarif.get("level").unwrap().as_str(), Some("warning"));
+        assert_eq!(
+            sarif.get("locations").unwrap().as_array().unwrap()[0].to_string(),
+            r#"{"physicalLocation":{"artifactLocation":{"uri":"card.pg"},"region":{"startLine":2,"startColumn":4}}}"#
+        );
+    }
+}
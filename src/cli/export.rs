@@ -0,0 +1,71 @@
+use std::{
+    fs::{self, write},
+    process::ExitCode,
+};
+
+use promptgen::html::{export, HtmlOptions, Image, Theme};
+
+use super::{load, read, Args};
+
+const USAGE: &str = "usage: promptgen export <script> -o <page.html> [--title TEXT] \
+                     [--theme light|dark] [--accent COLOR] [--font FAMILY] [--image LABEL=path]...";
+
+// `LABEL=path` read into an image shown with that prompt
+fn image(arg: &str) -> Result<Image, String> {
+    let (label, path) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("--image needs LABEL=path, not {arg}"))?;
+    let mime = Image::mime_for(path)
+        .ok_or_else(|| format!("{path}: use a png, jpg, gif, svg or webp image"))?;
+    let data = fs::read(path).map_err(|err| format!("can't read {path}: {err}"))?;
+
+    Ok(Image {
+        label: label.to_string(),
+        mime: mime.to_string(),
+        data,
+    })
+}
+
+/// `promptgen export <script> -o <page.html>`
+pub fn main(args: impl Iterator<Item = String>) -> Result<ExitCode, String> {
+    // `-o` is short for `--output`
+    let args = args.map(|arg| match arg.as_str() {
+        "-o" => "--output".to_string(),
+        _ => arg,
+    });
+    let args = Args::parse(
+        args,
+        &[],
+        &["output", "title", "theme", "accent", "font", "image"],
+    )?;
+    let ([path], Some(output)) = (args.positional(), args.value("output")) else {
+        return Err(USAGE.to_string());
+    };
+
+    let mut theme = match args.value("theme").unwrap_or("light") {
+        "light" => Theme::light(),
+        "dark" => Theme::dark(),
+        theme => return Err(format!("unknown theme {theme}, use light or dark")),
+    };
+    if let Some(accent) = args.value("accent") {
+        theme.accent = accent.to_string();
+    }
+    if let Some(font) = args.value("font") {
+        theme.font = font.to_string();
+    }
+    let options = HtmlOptions {
+        title: args.value("title").unwrap_or(path).to_string(),
+        theme,
+        images: args
+            .values("image")
+            .into_iter()
+            .map(image)
+            .collect::<Result<_, _>>()?,
+    };
+
+    let data = read(path)?;
+    let script = load(path, &data)?;
+    write(output, export(&script, &options))
+        .map_err(|err| format!("can't write {output}: {err}"))?;
+    Ok(ExitCode::SUCCESS)
+}
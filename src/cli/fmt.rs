@@ -0,0 +1,267 @@
+use std::{
+    fs::write,
+    io::{self, Read},
+    process::ExitCode,
+};
+
+use lexer::format::format;
+
+use super::{files, read, Args};
+
+const CONTEXT: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Keep,
+    Remove,
+    Add,
+}
+
+// Lengths of the longest common subsequences of `old` with each prefix of
+// `new`, a row at a time so it takes no more than a row of memory
+fn common_lengths<'s>(old: impl Iterator<Item = &'s str>, new: &[&str]) -> Vec<usize> {
+    let mut row = vec![0; new.len() + 1];
+    for old_line in old {
+        let mut diagonal = 0;
+        for (j, new_line) in new.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = match old_line == *new_line {
+                true => diagonal + 1,
+                false => above.max(row[j]),
+            };
+            diagonal = above;
+        }
+    }
+    row
+}
+
+// Hirschberg's algorithm: `old` is split in half, and `new` where the most
+// lines in common with both halves are kept
+fn push_edits<'s>(old: &[&'s str], new: &[&'s str], edits: &mut Vec<(Edit, &'s str)>) {
+    match old {
+        [] => edits.extend(new.iter().map(|line| (Edit::Add, *line))),
+        [line] => match new.iter().position(|new_line| new_line == line) {
+            Some(at) => {
+                edits.extend(new[..at].iter().map(|line| (Edit::Add, *line)));
+                edits.push((Edit::Keep, line));
+                edits.extend(new[at + 1..].iter().map(|line| (Edit::Add, *line)));
+            }
+            None => {
+                edits.push((Edit::Remove, line));
+                edits.extend(new.iter().map(|line| (Edit::Add, *line)));
+            }
+        },
+        _ => {
+            let (first, second) = old.split_at(old.len() / 2);
+            let forward = common_lengths(first.iter().copied(), new);
+            let reversed: Vec<&str> = new.iter().rev().copied().collect();
+            let backward = common_lengths(second.iter().rev().copied(), &reversed);
+            let split = (0..=new.len())
+                .max_by_key(|j| (forward[*j] + backward[new.len() - j], *j))
+                .unwrap_or_default();
+            push_edits(first, &new[..split], edits);
+            push_edits(second, &new[split..], edits);
+        }
+    }
+}
+
+// Line edits turning `old` into `new`, from their longest common
+// subsequence. Lines the same at either end are kept without looking for it,
+// and memory stays linear in the number of lines for big scripts
+fn edits<'s>(old: &[&'s str], new: &[&'s str]) -> Vec<(Edit, &'s str)> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+    let suffix = old_rest
+        .iter()
+        .rev()
+        .zip(new_rest.iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    let mut edits: Vec<_> = old[..prefix]
+        .iter()
+        .map(|line| (Edit::Keep, *line))
+        .collect();
+    push_edits(
+        &old_rest[..old_rest.len() - suffix],
+        &new_rest[..new_rest.len() - suffix],
+        &mut edits,
+    );
+    edits.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| (Edit::Keep, *line)),
+    );
+    edits
+}
+
+/// A unified diff from `old` to `new`, with a couple of lines of context
+/// around each change.
+pub fn diff(path: &str, old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = edits(&old_lines, &new_lines);
+
+    // Edits near a change, grouped into hunks where they're close together
+    let changed: Vec<usize> = (0..edits.len())
+        .filter(|idx| edits[*idx].0 != Edit::Keep)
+        .collect();
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for idx in changed {
+        let (start, end) = (
+            idx.saturating_sub(CONTEXT),
+            (idx + CONTEXT + 1).min(edits.len()),
+        );
+        match hunks.last_mut() {
+            Some(hunk) if hunk.1 >= start => hunk.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = format!("--- {path}\n+++ {path} (formatted)\n");
+    let hunks_empty = hunks.is_empty();
+    for (start, end) in hunks {
+        // Line numbers are counted from the edits before the hunk
+        let before = &edits[..start];
+        let old_start = before.iter().filter(|(e, _)| *e != Edit::Add).count() + 1;
+        let new_start = before.iter().filter(|(e, _)| *e != Edit::Remove).count() + 1;
+        let hunk = &edits[start..end];
+        let old_count = hunk.iter().filter(|(e, _)| *e != Edit::Add).count();
+        let new_count = hunk.iter().filter(|(e, _)| *e != Edit::Remove).count();
+
+        out += &format!("@@ -{old_start},{old_count} +{new_start},{new_count} @@\n");
+        for (edit, line) in hunk {
+            let sign = match edit {
+                Edit::Keep => ' ',
+                Edit::Remove => '-',
+                Edit::Add => '+',
+            };
+            out += &format!("{sign}{line}\n");
+        }
+    }
+
+    // Differences splitting into lines hides
+    if old.ends_with('\n') != new.ends_with('\n') {
+        out += "trailing newline differs\n";
+    } else if hunks_empty && old != new {
+        out += "line endings differ\n";
+    }
+    out
+}
+
+// Formats one script's text, a syntax error is shown where it is
+fn formatted(path: &str, data: &str) -> Result<String, String> {
+    format(data).map_err(|err| {
+        let (line, column) = err.line_and_column(data);
+        format!("{path}:{line}:{column}: {err}")
+    })
+}
+
+/// `promptgen fmt [scripts...] [--check]`
+pub fn main(args: impl Iterator<Item = String>) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &["check"], &[])?;
+    let check = args.flag("check");
+
+    // Piped through from stdin to stdout for editors
+    if matches!(args.positional(), [] | [_]) && args.positional().iter().all(|p| p == "-") {
+        let mut data = String::new();
+        io::stdin()
+            .read_to_string(&mut data)
+            .map_err(|err| format!("can't read stdin: {err}"))?;
+        let formatted = formatted("<stdin>", &data)?;
+
+        return match check {
+            true if formatted != data => {
+                print!("{}", diff("<stdin>", &data, &formatted));
+                Ok(ExitCode::FAILURE)
+            }
+            true => Ok(ExitCode::SUCCESS),
+            false => {
+                print!("{formatted}");
+                Ok(ExitCode::SUCCESS)
+            }
+        };
+    }
+
+    let mut failed = false;
+    for path in files(args.positional(), "pg")? {
+        let result = read(&path).and_then(|data| Ok((formatted(&path, &data)?, data)));
+        let (formatted, data) = match result {
+            Ok(result) => result,
+            Err(err) => {
+                eprintln!("promptgen: {err}");
+                failed = true;
+                continue;
+            }
+        };
+        if formatted == data {
+            continue;
+        }
+
+        match check {
+            true => {
+                print!("{}", diff(&path, &data, &formatted));
+                failed = true;
+            }
+            false => write(&path, formatted).map_err(|err| format!("can't write {path}: {err}"))?,
+        }
+    }
+
+    match failed {
+        true => Ok(ExitCode::FAILURE),
+        false => Ok(ExitCode::SUCCESS),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::diff;
+
+    #[test]
+    fn diffs_lines() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\n";
+
+        assert_eq!(
+            diff("card.pg", old, new),
+            "--- card.pg\n+++ card.pg (formatted)\n\
+             @@ -1,4 +1,4 @@\n a\n-b\n+B\n c\n d\n\
+             @@ -7,2 +7,3 @@\n g\n h\n+i\n"
+        );
+        assert_eq!(
+            diff("card.pg", old, old),
+            "--- card.pg\n+++ card.pg (formatted)\n"
+        );
+    }
+
+    #[test]
+    fn diffs_long_scripts() {
+        let old: String = (1..=10_000).map(|n| format!("{n}\n")).collect();
+        let new = old
+            .replace("\n2000\n", "\nTWO\n")
+            .replace("\n8000\n", "\n8000\nEIGHT\n");
+
+        assert_eq!(
+            diff("card.pg", &old, &new),
+            "--- card.pg\n+++ card.pg (formatted)\n\
+             @@ -1998,5 +1998,5 @@\n 1998\n 1999\n-2000\n+TWO\n 2001\n 2002\n\
+             @@ -7999,4 +7999,5 @@\n 7999\n 8000\n+EIGHT\n 8001\n 8002\n"
+        );
+    }
+
+    #[test]
+    fn diffs_what_lines_dont_show() {
+        assert_eq!(
+            diff("card.pg", "a\nb", "a\nb\n"),
+            "--- card.pg\n+++ card.pg (formatted)\ntrailing newline differs\n"
+        );
+        assert_eq!(
+            diff("card.pg", "a\r\nb\n", "a\nb\n"),
+            "--- card.pg\n+++ card.pg (formatted)\nline endings differ\n"
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use std::process::ExitCode;
+
+use prompter::graph::{Graph, GraphOptions};
+
+use super::{load, read, Args};
+
+const USAGE: &str = "usage: promptgen graph <script> [--format dot|mermaid] [--max-text N] \
+                     [--labels-only] [--unreachable]";
+
+/// `promptgen graph <script>`
+pub fn main(args: impl Iterator<Item = String>) -> Result<ExitCode, String> {
+    let args = Args::parse(
+        args,
+        &["labels-only", "unreachable"],
+        &["format", "max-text"],
+    )?;
+    let [path] = args.positional() else {
+        return Err(USAGE.to_string());
+    };
+
+    let max_text = match args.value("max-text") {
+        Some(max) => Some(
+            max.parse()
+                .map_err(|_| format!("--max-text needs a number, not {max}"))?,
+        ),
+        None => None,
+    };
+    let options = GraphOptions {
+        max_text,
+        labels_only: args.flag("labels-only"),
+        highlight_unreachable: args.flag("unreachable"),
+    };
+
+    let data = read(path)?;
+    let script = load(path, &data)?;
+    let graph = Graph::new(&script);
+
+    match args.value("format").unwrap_or("dot") {
+        "dot" => print!("{}", graph.to_dot(&options)),
+        "mermaid" => print!("{}", graph.to_mermaid(&options)),
+        format => return Err(format!("unknown format {format}, use dot or mermaid")),
+    }
+    Ok(ExitCode::SUCCESS)
+}
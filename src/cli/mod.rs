@@ -0,0 +1,246 @@
+use std::{
+    collections::HashMap,
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+};
+
+use lexer::parse_located;
+use prompter::script::Script;
+
+pub mod check;
+pub mod export;
+pub mod fmt;
+pub mod graph;
+pub mod run;
+pub mod serve;
+pub mod test;
+
+/// Command line arguments, split into positionals and `--name [value]`
+/// options.
+#[derive(Debug, Default)]
+pub struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, Vec<String>>,
+}
+
+impl Args {
+    /// Splits `args`, where `flags` are the options on their own and
+    /// `with_values` the ones followed by a value. A lone `-` is a positional,
+    /// standing for stdin.
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+        flags: &[&str],
+        with_values: &[&str],
+    ) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                parsed.positional.push(arg);
+                continue;
+            };
+            let (name, value) = match name.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (name, None),
+            };
+
+            let value = match (flags.contains(&name), with_values.contains(&name)) {
+                (true, _) if value.is_none() => None,
+                (_, true) => match value.or_else(|| args.next()) {
+                    Some(value) => Some(value),
+                    None => return Err(format!("--{name} needs a value")),
+                },
+                _ => return Err(format!("unknown option --{name}")),
+            };
+            let values = parsed.options.entry(name.to_string()).or_default();
+            values.extend(value);
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    /// The last value given for an option.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values(name).last().copied()
+    }
+
+    /// Every value given for an option that can be repeated.
+    pub fn values(&self, name: &str) -> Vec<&str> {
+        self.options
+            .get(name)
+            .map_or(vec![], |values| values.iter().map(String::as_str).collect())
+    }
+}
+
+// Whether a file name matches a glob segment with `*` and `?`
+fn matches_name(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, _) => name.is_empty(),
+        (Some(('*', rest)), _) => (0..=name.len()).any(|skip| matches_name(rest, &name[skip..])),
+        (Some(('?', rest)), Some((_, name))) => matches_name(rest, name),
+        (Some((c, rest)), Some((n, name))) => c == n && matches_name(rest, name),
+        (Some(_), None) => false,
+    }
+}
+
+// Whether path components match glob segments, where `**` matches any number
+// of directories
+fn matches_glob(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| matches_glob(rest, &path[skip..])),
+        Some((segment, rest)) => path.split_first().is_some_and(|(name, path)| {
+            let segment: Vec<char> = segment.chars().collect();
+            let name: Vec<char> = name.chars().collect();
+            matches_name(&segment, &name) && matches_glob(rest, path)
+        }),
+    }
+}
+
+// Every file under `dir`, sorted so output is stable
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = read_dir(dir).map_err(|err| format!("can't read {}: {err}", dir.display()))?;
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        match path.is_dir() {
+            true => walk(&path, files)?,
+            false => files.push(path),
+        }
+    }
+    Ok(())
+}
+
+/// Expands command line paths into files. Directories give the files in them
+/// with `extension`, and quoted globs like `cards/**/*.pg` are matched here
+/// for shells that don't.
+pub fn files(paths: &[String], extension: &str) -> Result<Vec<String>, String> {
+    let mut expanded = vec![];
+
+    for path in paths {
+        let is_glob = path.contains(['*', '?']);
+        if !is_glob && !Path::new(path).is_dir() {
+            expanded.push(path.clone());
+            continue;
+        }
+
+        let segments: Vec<&str> = path.split('/').collect();
+        let base_len = segments
+            .iter()
+            .take_while(|segment| !segment.contains(['*', '?']))
+            .count();
+        let base = match segments[..base_len].join("/") {
+            base if base.is_empty() => ".".to_string(),
+            base => base,
+        };
+
+        let mut found = vec![];
+        walk(Path::new(&base), &mut found)?;
+        let start = expanded.len();
+        for file in found {
+            let file = file.to_string_lossy();
+            let file = file.strip_prefix("./").unwrap_or(&file).to_string();
+            let components: Vec<&str> = file.split('/').collect();
+            let matches = match is_glob {
+                true => matches_glob(&segments, &components),
+                false => Path::new(&file)
+                    .extension()
+                    .is_some_and(|ext| ext == extension),
+            };
+            if matches {
+                expanded.push(file);
+            }
+        }
+        if expanded.len() == start {
+            return Err(format!("no scripts found in {path}"));
+        }
+    }
+
+    Ok(expanded)
+}
+
+pub fn read(path: &str) -> Result<String, String> {
+    read_to_string(path).map_err(|err| format!("can't read {path}: {err}"))
+}
+
+/// Parses and compiles a script read from `path`, syntax errors pointing at
+/// where they are like `card.pg:2:1: ...`.
+pub fn load<'a>(path: &str, data: &'a str) -> Result<Script<'a>, String> {
+    let prompts = parse_located(data).map_err(|err| {
+        let (line, column) = err.line_and_column(data);
+        format!("{path}:{line}:{column}: {err}")
+    })?;
+    Script::new(prompts).map_err(|_| format!("{path}: no prompts to start from"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{files, load, matches_glob, Args};
+
+    fn args(args: &[&str]) -> Result<Args, String> {
+        let args = args.iter().map(|arg| arg.to_string());
+        Args::parse(args, &["check"], &["start"])
+    }
+
+    #[test]
+    fn parses_options() {
+        let parsed = args(&["card.pg", "--start", "CAKE", "--check", "-"]).unwrap();
+        assert_eq!(parsed.positional(), ["card.pg", "-"]);
+        assert_eq!(parsed.value("start"), Some("CAKE"));
+        assert!(parsed.flag("check"));
+
+        let parsed = args(&["--start=CAKE", "--start", "PIE"]).unwrap();
+        assert_eq!(parsed.value("start"), Some("PIE"));
+        assert_eq!(parsed.values("start"), ["CAKE", "PIE"]);
+
+        assert_eq!(args(&["--start"]).unwrap_err(), "--start needs a value");
+        assert_eq!(args(&["--nope"]).unwrap_err(), "unknown option --nope");
+        assert_eq!(args(&["--check=1"]).unwrap_err(), "unknown option --check");
+    }
+
+    #[test]
+    fn matches_globs() {
+        let glob = |pattern: &str, path: &str| {
+            let pattern: Vec<&str> = pattern.split('/').collect();
+            let path: Vec<&str> = path.split('/').collect();
+            matches_glob(&pattern, &path)
+        };
+
+        assert!(glob("cards/*.pg", "cards/birthday.pg"));
+        assert!(!glob("cards/*.pg", "cards/old/birthday.pg"));
+        assert!(glob("cards/**/*.pg", "cards/old/birthday.pg"));
+        assert!(glob("cards/**/*.pg", "cards/birthday.pg"));
+        assert!(glob("**/card?.pg", "a/b/card1.pg"));
+        assert!(!glob("*.pg", "card.txt"));
+    }
+
+    #[test]
+    fn expands_paths() {
+        let found = files(&["prompter/s*_prompt.txt".to_string()], "pg").unwrap();
+        assert_eq!(
+            found,
+            ["prompter/simple_prompt.txt", "prompter/story_prompt.txt"]
+        );
+        assert!(files(&["prompter".to_string()], "pg").is_err());
+        assert_eq!(files(&["x.pg".to_string()], "pg").unwrap(), ["x.pg"]);
+    }
+
+    #[test]
+    fn locates_syntax_errors() {
+        let err = load("card.pg", "> (START) \"Hi\"\n< \"Bye\n")
+            .err()
+            .unwrap();
+        assert!(err.starts_with("card.pg:2:3: "), "{err}");
+        assert!(load("card.pg", "").is_err());
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::ExitCode,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use prompter::{
+    frontend::{run, Frontend, Reply},
+    render::RenderedPrompt,
+    select::ResponseId,
+    Ending, Prompter, PrompterErr,
+};
+
+use super::{load, read, Args};
+
+enum Line {
+    Text(String),
+    Ended,
+    TimedOut,
+}
+
+/// Plays a conversation on a terminal, with numbered responses.
+pub struct Terminal<W> {
+    lines: Receiver<io::Result<String>>,
+    out: W,
+}
+
+impl<W: Write> Terminal<W> {
+    /// Lines are read on their own thread, so questions can time out.
+    pub fn new(input: impl BufRead + Send + 'static, out: W) -> Self {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in input.lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { lines, out }
+    }
+
+    fn read_line(&mut self, timeout: Option<Duration>) -> io::Result<Line> {
+        let line = match timeout {
+            Some(timeout) => self.lines.recv_timeout(timeout),
+            None => self
+                .lines
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match line {
+            Ok(line) => line.map(Line::Text),
+            Err(RecvTimeoutError::Timeout) => Ok(Line::TimedOut),
+            Err(RecvTimeoutError::Disconnected) => Ok(Line::Ended),
+        }
+    }
+}
+
+// Responses picked by number, free text ones aren't numbered
+fn numbered<'p>(prompt: &'p RenderedPrompt) -> impl Iterator<Item = ResponseId> + 'p {
+    prompt
+        .responses
+        .iter()
+        .filter(|response| response.response.input.is_none())
+        .map(|response| response.id)
+}
+
+fn takes_input(prompt: &RenderedPrompt) -> bool {
+    prompt.responses.iter().any(|r| r.response.input.is_some())
+}
+
+fn pick(prompt: &RenderedPrompt, text: &str) -> Option<ResponseId> {
+    let number: usize = text.parse().ok()?;
+    numbered(prompt).nth(number.checked_sub(1)?)
+}
+
+// What a line typed in means for the prompt, or what to type instead
+fn reply(prompt: &RenderedPrompt, line: &str) -> Result<Reply, String> {
+    let text = line.trim();
+    let count = numbered(prompt).count();
+    let hint = format!("Pick a number from 1 to {count}.");
+
+    if prompt.multi.is_some() {
+        let picks = text
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|number| !number.is_empty())
+            .map(|number| pick(prompt, number))
+            .collect::<Option<Vec<_>>>();
+        return picks.map(Reply::ChooseMany).ok_or(hint);
+    }
+    if takes_input(prompt) {
+        return Ok(match pick(prompt, text) {
+            Some(id) => Reply::Choose(id),
+            None => Reply::Type(line.to_string()),
+        });
+    }
+
+    match pick(prompt, text) {
+        Some(id) => Ok(Reply::Choose(id)),
+        // Blank takes the question's default
+        None if text.is_empty() => Ok(Reply::TimedOut),
+        None => Err(hint),
+    }
+}
+
+impl<W: Write> Frontend for Terminal<W> {
+    type Error = io::Error;
+
+    fn show_prompt(&mut self, prompt: &RenderedPrompt) -> io::Result<()> {
+        writeln!(self.out, "\n{}", prompt.text)?;
+
+        let texts = prompt
+            .responses
+            .iter()
+            .filter(|r| r.response.input.is_none());
+        for (number, response) in texts.enumerate() {
+            writeln!(self.out, "  {}. {}", number + 1, response.text)?;
+        }
+        if let Some(selection) = prompt.multi {
+            let count = match (selection.min, selection.max) {
+                (0, None) => "any".to_string(),
+                (min, None) => format!("at least {min}"),
+                (min, Some(max)) if min == max => format!("{min}"),
+                (min, Some(max)) => format!("{min} to {max}"),
+            };
+            writeln!(self.out, "  (pick {count}, separated by commas)")?;
+        } else if takes_input(prompt) {
+            writeln!(self.out, "  (type your answer)")?;
+        }
+        Ok(())
+    }
+
+    fn collect_answer(
+        &mut self,
+        prompt: &RenderedPrompt,
+        timeout: Option<Duration>,
+    ) -> io::Result<Reply> {
+        loop {
+            write!(self.out, "> ")?;
+            self.out.flush()?;
+
+            let line = match self.read_line(timeout)? {
+                Line::Text(line) => line,
+                Line::Ended => return Ok(Reply::Quit),
+                Line::TimedOut => {
+                    writeln!(self.out)?;
+                    return Ok(Reply::TimedOut);
+                }
+            };
+            match reply(prompt, &line) {
+                Ok(reply) => return Ok(reply),
+                Err(hint) => writeln!(self.out, "{hint}")?,
+            }
+        }
+    }
+
+    fn show_end(&mut self, ending: &Ending) -> io::Result<()> {
+        match ending.outcome() {
+            Some(outcome) => writeln!(self.out, "\n[{outcome}]"),
+            None => Ok(()),
+        }
+    }
+
+    fn show_error(&mut self, error: &PrompterErr) -> io::Result<()> {
+        match error {
+            PrompterErr::InvalidInput(invalid) => writeln!(self.out, "{invalid}"),
+            PrompterErr::SelectionCount(_) => writeln!(self.out, "That's not how many to pick."),
+            PrompterErr::NoDefault => writeln!(self.out, "Pick one of the answers."),
+            _ => writeln!(self.out, "That's not one of the answers."),
+        }
+    }
+}
+
+// How a broken script left the conversation
+fn failure(ending: &Ending) -> Option<String> {
+    match ending {
+        Ending::Completed { .. } => None,
+        Ending::DeadEnd => Some("the conversation hit a dead end".to_string()),
+        Ending::UnresolvedLabel(label) => Some(format!("no prompt is labelled {label}")),
+        Ending::Looped => Some("statements looped without asking anything".to_string()),
+    }
+}
+
+/// `promptgen run <script> [--start LABEL]`
+pub fn main(args: impl Iterator<Item = String>) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &[], &["start"])?;
+    let [path] = args.positional() else {
+        return Err("usage: promptgen run <script> [--start LABEL]".to_string());
+    };
+
+    let data = read(path)?;
+    let mut script = load(path, &data)?;
+    if let Some(label) = args.value("start") {
+        script = script
+            .with_start(label)
+            .ok_or(format!("{path}: no prompt is labelled {label}"))?;
+    }
+
+    let mut terminal = Terminal::new(BufReader::new(io::stdin()), io::stdout());
+    let prompter = run(Prompter::from_script(Arc::new(script)), &mut terminal)
+        .map_err(|err| format!("terminal error: {err}"))?;
+
+    match prompter.ending().and_then(failure) {
+        Some(failure) => Err(format!("{path}: {failure}")),
+        None => Ok(ExitCode::SUCCESS),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::read_to_string, io::Cursor};
+
+    use lexer::parse;
+    use prompter::{frontend::run, Prompter};
+
+    use super::Terminal;
+
+    fn play(path: &str, input: &str) -> (Prompter<'static>, String) {
+        let data = read_to_string(path).unwrap().leak();
+        let prompter = Prompter::new(parse(data).unwrap()).unwrap();
+        let mut terminal = Terminal::new(Cursor::new(input.to_string()), vec![]);
+
+        let prompter = run(prompter, &mut terminal).unwrap();
+        (prompter, String::from_utf8(terminal.out).unwrap())
+    }
+
+    #[test]
+    fn plays_numbered_choices() {
+        let (prompter, out) = play("./prompter/simple_prompt.txt", "3\n2\n1\n1\n");
+
+        assert!(prompter.is_finished());
+        assert_eq!(
+            out,
+            "\nAre you a human?\n  1. Yes, I am\n  2. No\n\
+             > Pick a number from 1 to 2.\n\
+             > \nThat's very weird! Care to try again?\n  1. Please!\n\
+             > \nAre you a human?\n  1. Yes, I am\n  2. No\n\
+             > \nNice! Glad to meet you human!\n"
+        );
+    }
+
+    #[test]
+    fn takes_typed_answers() {
+        let (prompter, out) = play("./prompter/input_prompt.txt", "Ada\n200\n37\n");
+
+        assert!(!prompter.is_finished());
+        assert_eq!(prompter.variables().get_int("age"), Some(37));
+        assert!(out.contains("  (type your answer)\n"));
+        assert!(out.contains("> Come on Ada, between 1 and 120 please.\n"));
+    }
+}
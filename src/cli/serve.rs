@@ -0,0 +1,450 @@
+//! `promptgen serve --stdio`, conversations for a host process speaking JSON
+//! lines on stdin and stdout.
+//!
+//! Every request is one line, `{"id": ..., "op": "...", ...}`, answered by
+//! one line echoing the `id`, either `{"id": ..., "ok": true, "result": ...}`
+//! or `{"id": ..., "ok": false, "error": {"code": ..., "message": ...}}`.
+//! The operations are
+//!
+//! - `load` with `script` and either `source` or `path`, giving the number
+//!   of `prompts`
+//! - `start` with `script` and optionally `start`, `variables` and `seed`,
+//!   giving the new `session` and its `prompt`
+//! - `next` with `session`, giving the current `prompt`
+//! - `answer` with `session` and an answer as taken by `session::answer`,
+//!   giving the next `prompt`
+//! - `snapshot` with `session`, giving a `snapshot` that `restore` takes
+//!   back, in this process or another one with the same script loaded
+//! - `restore` with `snapshot`, giving a new `session` and its `prompt`
+//! - `close` with `session`
+
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    io::{self, BufRead, Write},
+    process::ExitCode,
+};
+
+use prompter::{
+    json::Json,
+    owned::{OwnedPrompter, OwnedScript},
+    random::{RandomSource, SeededRandom},
+    transcript::{Transcript, TranscriptEntry},
+};
+use promptgen::session::{answer, error_json, load_error_json, load_owned, prompt_json, start};
+
+use super::Args;
+
+const USAGE: &str = "usage: promptgen serve --stdio";
+
+// A conversation along with what it takes to start it over
+struct Session {
+    prompter: OwnedPrompter,
+    script: String,
+    start: Json,
+    variables: Json,
+    seed: u64,
+}
+
+/// The scripts and sessions of one `serve --stdio` process. A script's text
+/// is freed once it's been loaded over and its sessions are closed.
+#[derive(Default)]
+pub struct Protocol {
+    scripts: HashMap<String, OwnedScript>,
+    sessions: HashMap<String, Session>,
+    started: usize,
+}
+
+// A string field of the request, `what` saying what it's for
+fn field<'r>(request: &'r Json, name: &str, what: &str) -> Result<&'r str, Json> {
+    request
+        .get(name)
+        .and_then(Json::as_str)
+        .ok_or_else(|| error_json("bad_request", format!("`{name}` {what}")))
+}
+
+// Seeds are strings of digits, JSON numbers can't hold every u64
+fn seed(request: &Json) -> Result<Option<u64>, Json> {
+    match request.get("seed") {
+        None | Some(Json::Null) => Ok(None),
+        Some(seed) => seed
+            .as_str()
+            .and_then(|seed| seed.parse().ok())
+            .map(Some)
+            .ok_or_else(|| error_json("bad_request", "`seed` takes a string of digits")),
+    }
+}
+
+fn no_session(id: &str) -> Json {
+    error_json("no_session", format!("no session called {id}"))
+}
+
+impl Protocol {
+    /// Answers one request line with one response line.
+    pub fn handle(&mut self, line: &str) -> Json {
+        let (id, result) = match Json::parse(line) {
+            Ok(request) => {
+                let id = request.get("id").cloned().unwrap_or(Json::Null);
+                (id, self.dispatch(&request))
+            }
+            Err(err) => (Json::Null, Err(error_json("bad_json", err))),
+        };
+
+        match result {
+            Ok(result) => Json::object([("id", id), ("ok", Json::Bool(true)), ("result", result)]),
+            Err(error) => Json::object([("id", id), ("ok", Json::Bool(false)), ("error", error)]),
+        }
+    }
+
+    fn dispatch(&mut self, request: &Json) -> Result<Json, Json> {
+        match field(request, "op", "names the operation")? {
+            "load" => self.load(request),
+            "start" => {
+                let script = field(request, "script", "names the script to start")?;
+                let seed =
+                    seed(request)?.unwrap_or_else(|| SeededRandom::from_entropy().next_u64());
+                self.start(script, request, seed, Transcript::new())
+            }
+            "next" => {
+                let session = self.session(request)?;
+                let prompt = session.prompter.with(prompt_json);
+                Ok(Json::object([("prompt", prompt)]))
+            }
+            "answer" => {
+                let session = self.session(request)?;
+                session
+                    .prompter
+                    .update(|prompter| answer(prompter, request))?;
+                let prompt = session.prompter.with(prompt_json);
+                Ok(Json::object([("prompt", prompt)]))
+            }
+            "snapshot" => {
+                let session = self.session(request)?;
+                let transcript = session.prompter.with(|prompter| {
+                    prompter
+                        .transcript()
+                        .expect("sessions keep transcripts")
+                        .entries()
+                        .iter()
+                        .map(|entry| entry.to_json())
+                        .collect()
+                });
+                let snapshot = Json::object([
+                    ("script", Json::from(session.script.as_str())),
+                    ("start", session.start.clone()),
+                    ("variables", session.variables.clone()),
+                    ("seed", Json::from(session.seed.to_string())),
+                    ("transcript", Json::Array(transcript)),
+                ]);
+                Ok(Json::object([("snapshot", snapshot)]))
+            }
+            "restore" => self.restore(request),
+            "close" => {
+                let id = field(request, "session", "names the session")?;
+                self.sessions
+                    .remove(id)
+                    .map(|_| Json::Object(vec![]))
+                    .ok_or_else(|| no_session(id))
+            }
+            op => Err(error_json("bad_request", format!("unknown operation {op}"))),
+        }
+    }
+
+    fn load(&mut self, request: &Json) -> Result<Json, Json> {
+        let name = field(request, "script", "names the script to load")?;
+        let data = match (request.get("source"), request.get("path")) {
+            (Some(source), _) => source
+                .as_str()
+                .ok_or_else(|| error_json("bad_request", "`source` takes the script's text"))?
+                .to_string(),
+            (None, Some(path)) => {
+                let path = path
+                    .as_str()
+                    .ok_or_else(|| error_json("bad_request", "`path` takes a file name"))?;
+                read_to_string(path)
+                    .map_err(|err| error_json("io", format!("can't read {path}: {err}")))?
+            }
+            (None, None) => return Err(error_json("bad_request", "expected `source` or `path`")),
+        };
+
+        let script = load_owned(data).map_err(|err| load_error_json(&err))?;
+        let prompts = script.with_script(|script| script.prompts().len());
+        self.scripts.insert(name.to_string(), script);
+        Ok(Json::object([("prompts", Json::from(prompts))]))
+    }
+
+    // Starts `script` as a new session, seeded so that replaying `transcript`
+    // picks and shuffles the same way it did the first time
+    fn start(
+        &mut self,
+        script: &str,
+        request: &Json,
+        seed: u64,
+        transcript: Transcript,
+    ) -> Result<Json, Json> {
+        let loaded = self
+            .scripts
+            .get(script)
+            .ok_or_else(|| error_json("no_script", format!("no script called {script}")))?;
+        let prompter = loaded.start_with(|loaded| {
+            let prompter = start(loaded, request)?
+                .with_random(SeededRandom::new(seed))
+                .with_transcript();
+
+            // The last prompt recorded is where the session was, replaying it
+            // would move on from statements that were still being shown
+            let entries = transcript.entries();
+            let (current, replayed) = match entries.split_last() {
+                Some((last @ TranscriptEntry::Prompt { .. }, rest)) => (Some(last), rest),
+                _ => (None, entries),
+            };
+            let replayed: Transcript = replayed.iter().cloned().collect();
+            let prompter = prompter.replay(&replayed).map_err(|divergence| {
+                let mut error = error_json("diverged", &divergence);
+                if let Json::Object(fields) = &mut error {
+                    fields.push(("entry".to_string(), Json::from(divergence.entry)));
+                }
+                error
+            })?;
+            if let Some(current) = current {
+                let shown = prompter
+                    .transcript()
+                    .and_then(|shown| shown.entries().last());
+                if shown != Some(current) {
+                    let message =
+                        format!("the session was at {current}, the script isn't any more");
+                    return Err(error_json("diverged", message));
+                }
+            }
+            Ok(prompter)
+        })?;
+
+        self.started += 1;
+        let id = format!("s{}", self.started);
+        let result = Json::object([
+            ("session", Json::from(id.as_str())),
+            ("prompt", prompter.with(prompt_json)),
+        ]);
+        self.sessions.insert(
+            id,
+            Session {
+                prompter,
+                script: script.to_string(),
+                start: request.get("start").cloned().unwrap_or(Json::Null),
+                variables: request.get("variables").cloned().unwrap_or(Json::Null),
+                seed,
+            },
+        );
+        Ok(result)
+    }
+
+    fn restore(&mut self, request: &Json) -> Result<Json, Json> {
+        let snapshot = request
+            .get("snapshot")
+            .ok_or_else(|| error_json("bad_request", "`snapshot` takes a snapshot"))?;
+        let script = field(snapshot, "script", "names the script to restore")?;
+        let seed = seed(snapshot)?
+            .ok_or_else(|| error_json("bad_request", "the snapshot has no `seed`"))?;
+        let transcript = snapshot
+            .get("transcript")
+            .and_then(Json::as_array)
+            .ok_or_else(|| error_json("bad_request", "the snapshot has no `transcript`"))?;
+
+        let transcript = transcript
+            .iter()
+            .map(TranscriptEntry::from_json)
+            .collect::<Option<_>>()
+            .ok_or_else(|| error_json("bad_request", "the snapshot's transcript is broken"))?;
+        self.start(script, snapshot, seed, transcript)
+    }
+
+    fn session(&mut self, request: &Json) -> Result<&mut Session, Json> {
+        let id = field(request, "session", "names the session")?;
+        self.sessions.get_mut(id).ok_or_else(|| no_session(id))
+    }
+}
+
+/// `promptgen serve --stdio`
+pub fn main(args: impl Iterator<Item = String>) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &["stdio"], &[])?;
+    if !args.flag("stdio") || !args.positional().is_empty() {
+        return Err(USAGE.to_string());
+    }
+
+    let mut protocol = Protocol::default();
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line.map_err(|err| format!("can't read stdin: {err}"))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(stdout, "{}", protocol.handle(&line))
+            .and_then(|()| stdout.flush())
+            .map_err(|err| format!("can't write stdout: {err}"))?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use prompter::json::Json;
+
+    use super::Protocol;
+
+    // The result of a request that has to succeed
+    fn ok(protocol: &mut Protocol, line: &str) -> Json {
+        let response = protocol.handle(line);
+        assert_eq!(response.get("ok"), Some(&Json::Bool(true)), "{response}");
+        response.get("result").unwrap().clone()
+    }
+
+    fn error_code(protocol: &mut Protocol, line: &str) -> String {
+        let response = protocol.handle(line);
+        let error = response.get("error").expect("an error");
+        error.get("code").unwrap().as_str().unwrap().to_string()
+    }
+
+    fn text(result: &Json) -> &str {
+        result
+            .get("prompt")
+            .unwrap()
+            .get("text")
+            .unwrap()
+            .as_str()
+            .unwrap()
+    }
+
+    #[test]
+    fn plays_and_restores_sessions() {
+        let mut protocol = Protocol::default();
+        let loaded = ok(
+            &mut protocol,
+            r#"{"id":1,"op":"load","script":"story","path":"./prompter/story_prompt.txt"}"#,
+        );
+        assert_eq!(loaded.to_string(), r#"{"prompts":7}"#);
+
+        let started = ok(&mut protocol, r#"{"op":"start","script":"story"}"#);
+        assert_eq!(started.get("session").unwrap().as_str(), Some("s1"));
+        let advance = r#"{"op":"answer","session":"s1","advance":true}"#;
+        ok(&mut protocol, advance);
+        assert_eq!(
+            text(&ok(&mut protocol, advance)),
+            "Do you want to go to the party?"
+        );
+
+        // Restored elsewhere, still on the question rather than past it
+        let snapshot = ok(&mut protocol, r#"{"op":"snapshot","session":"s1"}"#);
+        let mut other = Protocol::default();
+        ok(
+            &mut other,
+            r#"{"op":"load","script":"story","path":"./prompter/story_prompt.txt"}"#,
+        );
+        let restore = format!(
+            r#"{{"op":"restore","snapshot":{}}}"#,
+            snapshot.get("snapshot").unwrap()
+        );
+        let restored = ok(&mut other, &restore);
+        assert_eq!(text(&restored), "Do you want to go to the party?");
+        let next = ok(&mut other, r#"{"op":"next","session":"s1"}"#);
+        assert_eq!(next, ok(&mut protocol, r#"{"op":"next","session":"s1"}"#));
+
+        ok(&mut protocol, r#"{"op":"close","session":"s1"}"#);
+        assert_eq!(
+            error_code(&mut protocol, r#"{"op":"next","session":"s1"}"#),
+            "no_session"
+        );
+    }
+
+    #[test]
+    fn keeps_sessions_on_scripts_loaded_over() {
+        let mut protocol = Protocol::default();
+        ok(
+            &mut protocol,
+            r#"{"op":"load","script":"card","source":"> (START) \"Old\"\n< \"Hi\"\n> \"Still old\""}"#,
+        );
+        ok(&mut protocol, r#"{"op":"start","script":"card"}"#);
+        ok(
+            &mut protocol,
+            r#"{"op":"load","script":"card","source":"> (START) \"New\""}"#,
+        );
+
+        let started = ok(&mut protocol, r#"{"op":"start","script":"card"}"#);
+        assert_eq!(text(&started), "New");
+        let answered = ok(
+            &mut protocol,
+            r#"{"op":"answer","session":"s1","response":0}"#,
+        );
+        assert_eq!(text(&answered), "Still old");
+    }
+
+    #[test]
+    fn replays_random_picks_from_the_seed() {
+        let mut protocol = Protocol::default();
+        ok(
+            &mut protocol,
+            r#"{"op":"load","script":"random","path":"./prompter/random_prompt.txt"}"#,
+        );
+
+        let mut seen = vec![];
+        for seed in 0..8 {
+            let start = format!(r#"{{"op":"start","script":"random","seed":"{seed}"}}"#);
+            let session = ok(&mut protocol, &start);
+            let id = session
+                .get("session")
+                .unwrap()
+                .as_str()
+                .unwrap()
+                .to_string();
+            let answer = format!(r#"{{"op":"answer","session":"{id}","response":0}}"#);
+            let answered = ok(&mut protocol, &answer);
+
+            let snapshot = ok(
+                &mut protocol,
+                &format!(r#"{{"op":"snapshot","session":"{id}"}}"#),
+            );
+            let restore = format!(
+                r#"{{"op":"restore","snapshot":{}}}"#,
+                snapshot.get("snapshot").unwrap()
+            );
+            assert_eq!(text(&ok(&mut protocol, &restore)), text(&answered));
+            seen.push(text(&answered).to_string());
+        }
+        seen.dedup();
+        assert!(seen.len() > 1, "seeds pick differently");
+    }
+
+    #[test]
+    fn reports_structured_errors() {
+        let mut protocol = Protocol::default();
+        let response = protocol
+            .handle(r#"{"id":"a","op":"load","script":"x","source":"> (A) \"Hi\"\n< oops"}"#);
+        assert_eq!(response.get("id").unwrap().as_str(), Some("a"));
+        let error = response.get("error").unwrap();
+        assert_eq!(error.get("code").unwrap().as_str(), Some("syntax"));
+        assert_eq!(error.get("line").unwrap().as_u64(), Some(2));
+
+        assert_eq!(error_code(&mut protocol, "nope"), "bad_json");
+        assert_eq!(
+            error_code(&mut protocol, r#"{"op":"dance"}"#),
+            "bad_request"
+        );
+        assert_eq!(
+            error_code(&mut protocol, r#"{"op":"start","script":"x"}"#),
+            "no_script"
+        );
+
+        ok(
+            &mut protocol,
+            r#"{"op":"load","script":"simple","path":"./prompter/simple_prompt.txt"}"#,
+        );
+        let start = r#"{"op":"start","script":"simple","start":"NOPE"}"#;
+        assert_eq!(error_code(&mut protocol, start), "no_label");
+        ok(&mut protocol, r#"{"op":"start","script":"simple"}"#);
+        let answer = r#"{"op":"answer","session":"s1","response":9}"#;
+        assert_eq!(error_code(&mut protocol, answer), "bad_response");
+
+        // A transcript the script can't have produced
+        let restore = r#"{"op":"restore","snapshot":{"script":"simple","seed":"1","transcript":[{"type":"prompt","label":"START","text":"Are you a robot?"}]}}"#;
+        assert_eq!(error_code(&mut protocol, restore), "diverged");
+    }
+}
@@ -0,0 +1,125 @@
+use std::{path::Path, process::ExitCode, sync::Arc};
+
+use prompter::{scenario::TestFile, script::Script};
+
+use super::{files, load, read, Args};
+
+const USAGE: &str = "usage: promptgen test <tests...>";
+
+// The script a test file is for, its `script` setting taken from the test
+// file's directory, otherwise the `.pg` file of the same name
+fn script_path(test_path: &str, file: &TestFile) -> String {
+    let path = Path::new(test_path);
+    match &file.script {
+        Some(script) => path.with_file_name(script),
+        None => path.with_extension("pg"),
+    }
+    .to_string_lossy()
+    .into_owned()
+}
+
+// A test file along with the path and text of its script
+fn read_test(path: &str) -> Result<(TestFile, String, String), String> {
+    let data = read(path)?;
+    let file =
+        TestFile::parse(&data).map_err(|err| format!("{path}:{}: {}", err.line, err.message))?;
+    let script_path = script_path(path, &file);
+    let script_data = read(&script_path)?;
+    Ok((file, script_path, script_data))
+}
+
+/// Runs the scenarios of one test file, giving a line per scenario and how
+/// many passed and failed. Test files that can't be run count as a failure.
+pub fn run(path: &str) -> (String, usize, usize) {
+    let loaded = read_test(path).and_then(|(file, script_path, data)| {
+        let script = load(&script_path, &data)?;
+        Ok(run_scenarios(path, &file, Arc::new(script)))
+    });
+    loaded.unwrap_or_else(|err| (format!("error: {err}\n"), 0, 1))
+}
+
+fn run_scenarios(path: &str, file: &TestFile, script: Arc<Script>) -> (String, usize, usize) {
+    let mut out = String::new();
+    let (mut passed, mut failed) = (0, 0);
+    for scenario in &file.scenarios {
+        match scenario.run(Arc::clone(&script)) {
+            Ok(()) => {
+                passed += 1;
+                out += &format!("ok     {path}: {}\n", scenario.name);
+            }
+            Err(failure) => {
+                failed += 1;
+                out += &format!("FAILED {path}: {}\n", scenario.name);
+                out += &format!("  --> {path}:{}: {}\n", failure.line, failure.message);
+            }
+        }
+    }
+    (out, passed, failed)
+}
+
+/// `promptgen test <tests...>`
+pub fn main(args: impl Iterator<Item = String>) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &[], &[])?;
+    if args.positional().is_empty() {
+        return Err(USAGE.to_string());
+    }
+
+    let (mut passed, mut failed) = (0, 0);
+    for path in files(args.positional(), "pgtest")? {
+        let (out, file_passed, file_failed) = run(&path);
+        print!("{out}");
+        passed += file_passed;
+        failed += file_failed;
+    }
+    println!("{passed} passed, {failed} failed");
+
+    match failed {
+        0 => Ok(ExitCode::SUCCESS),
+        _ => Ok(ExitCode::FAILURE),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::run;
+
+    #[test]
+    fn runs_test_files() {
+        let (out, passed, failed) = run("./prompter/quiz_prompt.pgtest");
+        assert_eq!(
+            out,
+            "ok     ./prompter/quiz_prompt.pgtest: Gold for getting everything right\n\
+             ok     ./prompter/quiz_prompt.pgtest: Geography scores on its own\n\
+             ok     ./prompter/quiz_prompt.pgtest: Only the answers on offer\n"
+        );
+        assert_eq!((passed, failed), (3, 0));
+
+        let (out, passed, failed) = run("./prompter/missing.pgtest");
+        assert!(out.starts_with("error: can't read ./prompter/missing.pgtest"));
+        assert_eq!((passed, failed), (0, 1));
+    }
+
+    #[test]
+    fn fails_test_files_without_scenarios() {
+        let path = env::temp_dir().join(format!("promptgen-empty-{}.pgtest", process::id()));
+        fs::write(&path, "script quiz_prompt.txt\n= Misspelled\n").unwrap();
+        let path = path.to_string_lossy().into_owned();
+        let (out, passed, failed) = run(&path);
+        assert_eq!(
+            out,
+            format!("error: {path}:2: expected a setting or `==` before = Misspelled\n")
+        );
+        assert_eq!((passed, failed), (0, 1));
+
+        fs::write(&path, "# To do\nseed 7\n").unwrap();
+        let (out, passed, failed) = run(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            out,
+            format!("error: {path}:1: no scenarios, each starts with `== name`\n")
+        );
+        assert_eq!((passed, failed), (0, 1));
+    }
+}
@@ -0,0 +1,470 @@
+//! Exports a script as a single HTML page that plays it in the browser,
+//! offline, with the same rules as `Prompter`.
+
+use lexer::{
+    expr::{BinaryOp, Expr, Literal},
+    parser::{Advance, InputCheck, Prompt, Response, Target},
+};
+use prompter::{json::Json, script::Script};
+
+const PLAYER_JS: &str = include_str!("player.js");
+const PLAYER_CSS: &str = include_str!("player.css");
+
+/// Colours and font of the exported page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub background: String,
+    pub text: String,
+    pub accent: String,
+    pub font: String,
+}
+
+impl Theme {
+    pub fn light() -> Self {
+        Self {
+            background: "#fdf6ec".to_string(),
+            text: "#2b2118".to_string(),
+            accent: "#d6336c".to_string(),
+            font: "Georgia, serif".to_string(),
+        }
+    }
+
+    pub fn dark() -> Self {
+        Self {
+            background: "#1d1b26".to_string(),
+            text: "#ece8f4".to_string(),
+            accent: "#f7b32b".to_string(),
+            font: "Georgia, serif".to_string(),
+        }
+    }
+}
+
+/// A picture shown with the prompt carrying `label`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub label: String,
+    pub mime: String,
+    pub data: Vec<u8>,
+}
+
+impl Image {
+    /// The MIME type of an image file, from its extension.
+    pub fn mime_for(path: &str) -> Option<&'static str> {
+        let extension = path.rsplit_once('.')?.1.to_lowercase();
+        match extension.as_str() {
+            "png" => Some("image/png"),
+            "jpg" | "jpeg" => Some("image/jpeg"),
+            "gif" => Some("image/gif"),
+            "svg" => Some("image/svg+xml"),
+            "webp" => Some("image/webp"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtmlOptions {
+    pub title: String,
+    pub theme: Theme,
+    pub images: Vec<Image>,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        Self {
+            title: "promptgen".to_string(),
+            theme: Theme::light(),
+            images: vec![],
+        }
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for idx in 0..4 {
+            match idx <= chunk.len() {
+                true => out.push(ALPHABET[(bits >> (18 - idx * 6) & 0x3f) as usize] as char),
+                false => out.push('='),
+            }
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Keeps a theme value inside its custom property: anything that could end
+// the declaration, the rule or the `<style>` element is written as a CSS
+// escape, which reads as plain text. Closed quotes like `"Comic Sans"` are
+// kept, a stray one is escaped too.
+fn escape_css(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut quote = None;
+
+    for (idx, c) in value.char_indices() {
+        let kept = match c {
+            '"' | '\'' if quote == Some(c) => {
+                quote = None;
+                true
+            }
+            '"' | '\'' if quote.is_none() && value[idx + 1..].contains(c) => {
+                quote = Some(c);
+                true
+            }
+            '"' | '\'' | '<' | '>' | '&' | ';' | '{' | '}' | '\\' => false,
+            c => !c.is_control(),
+        };
+        match kept {
+            true => out.push(c),
+            false => out.push_str(&format!("\\{:x} ", u32::from(c))),
+        }
+    }
+    out
+}
+
+fn literal_json(literal: &Literal) -> Json {
+    match literal {
+        Literal::Str(s) => Json::from(*s),
+        Literal::Int(n) => Json::from(*n),
+        Literal::Bool(b) => Json::from(*b),
+    }
+}
+
+fn expr_json(expr: &Expr) -> Json {
+    match expr {
+        Expr::Literal(literal) => Json::object([("lit", literal_json(literal))]),
+        Expr::Variable(name) => Json::object([("var", Json::from(*name))]),
+        Expr::Not(inner) => Json::object([("not", expr_json(inner))]),
+        Expr::Binary(op, lhs, rhs) => {
+            let op = match op {
+                BinaryOp::Eq => "==",
+                BinaryOp::Ne => "!=",
+                BinaryOp::Lt => "<",
+                BinaryOp::Le => "<=",
+                BinaryOp::Gt => ">",
+                BinaryOp::Ge => ">=",
+                BinaryOp::And => "&&",
+                BinaryOp::Or => "||",
+                BinaryOp::Add => "+",
+                BinaryOp::Sub => "-",
+            };
+            Json::object([
+                ("op", Json::from(op)),
+                ("lhs", expr_json(lhs)),
+                ("rhs", expr_json(rhs)),
+            ])
+        }
+    }
+}
+
+fn target_json(target: &Target) -> Json {
+    match target {
+        Target::Label(label) => Json::object([("label", Json::from(*label))]),
+        Target::Random(labels) => Json::object([(
+            "random",
+            Json::Array(
+                labels
+                    .iter()
+                    .map(|w| Json::Array(vec![Json::from(w.label), Json::from(w.weight as i64)]))
+                    .collect(),
+            ),
+        )]),
+        Target::Bands { score, bands } => Json::object([
+            ("score", Json::from(*score)),
+            (
+                "bands",
+                Json::Array(
+                    bands
+                        .iter()
+                        .map(|band| {
+                            Json::Array(vec![
+                                Json::from(band.min),
+                                Json::from(band.max),
+                                Json::from(band.label),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ]),
+    }
+}
+
+// A `[regex ...]` goes out as the program the script compiled it to, or
+// `null` when it doesn't compile and nothing can match
+fn check_json(script: &Script, check: &InputCheck) -> Json {
+    match check {
+        InputCheck::Int { min, max } => Json::object([
+            ("int", Json::Bool(true)),
+            ("min", Json::from(*min)),
+            ("max", Json::from(*max)),
+        ]),
+        InputCheck::YesNo => Json::object([("yesno", Json::Bool(true))]),
+        InputCheck::Date => Json::object([("date", Json::Bool(true))]),
+        InputCheck::Email => Json::object([("email", Json::Bool(true))]),
+        InputCheck::OneOf(options) => Json::object([(
+            "one_of",
+            Json::Array(options.iter().map(|o| Json::from(*o)).collect()),
+        )]),
+        InputCheck::Regex(source) => Json::object([(
+            "regex",
+            script
+                .pattern(source)
+                .map_or(Json::Null, |pattern| pattern.to_json()),
+        )]),
+    }
+}
+
+fn response_json(script: &Script, response: &Response) -> Json {
+    let assignments = response.assignments.iter().map(|assignment| {
+        Json::Array(vec![
+            Json::from(assignment.name),
+            literal_json(&assignment.value),
+        ])
+    });
+    let scores = response
+        .scores
+        .iter()
+        .map(|delta| Json::Array(vec![Json::from(delta.name), Json::from(delta.delta)]));
+    let input = response.input.as_ref().map(|input| {
+        Json::object([
+            ("variable", Json::from(input.variable)),
+            (
+                "check",
+                input
+                    .check
+                    .as_ref()
+                    .map_or(Json::Null, |check| check_json(script, check)),
+            ),
+            ("retry", Json::from(input.retry)),
+        ])
+    });
+
+    Json::object([
+        ("text", Json::from(response.text)),
+        ("label", Json::from(response.label)),
+        ("assignments", Json::Array(assignments.collect())),
+        (
+            "guard",
+            response.guard.as_ref().map_or(Json::Null, expr_json),
+        ),
+        ("input", input.unwrap_or(Json::Null)),
+        ("scores", Json::Array(scores.collect())),
+        (
+            "target",
+            response.target.as_ref().map_or(Json::Null, target_json),
+        ),
+        ("default", Json::from(response.default)),
+    ])
+}
+
+fn prompt_json(script: &Script, prompt: &Prompt) -> Json {
+    let advance = match prompt.advance {
+        None => Json::Null,
+        Some(Advance::Next) => Json::object([("next", Json::Bool(true))]),
+        Some(Advance::Goto(label)) => Json::object([("goto", Json::from(label))]),
+    };
+    let multi = prompt.multi.map_or(Json::Null, |selection| {
+        Json::object([
+            ("min", Json::from(selection.min)),
+            ("max", Json::from(selection.max)),
+        ])
+    });
+
+    Json::object([
+        ("text", Json::from(prompt.text)),
+        ("label", Json::from(prompt.label)),
+        (
+            "responses",
+            Json::Array(
+                prompt
+                    .responses
+                    .iter()
+                    .map(|response| response_json(script, response))
+                    .collect(),
+            ),
+        ),
+        ("guard", prompt.guard.as_ref().map_or(Json::Null, expr_json)),
+        (
+            "target",
+            prompt.target.as_ref().map_or(Json::Null, target_json),
+        ),
+        ("shuffle", Json::from(prompt.shuffle)),
+        ("advance", advance),
+        ("multi", multi),
+        (
+            "timeout",
+            Json::from(prompt.timeout.map(|timeout| timeout.as_millis() as i64)),
+        ),
+    ])
+}
+
+/// The script as the player reads it.
+pub fn script_json(script: &Script) -> Json {
+    Json::object([
+        ("start", Json::from(script.start())),
+        (
+            "scores",
+            Json::Array(script.scores().map(Json::from).collect()),
+        ),
+        (
+            "prompts",
+            Json::Array(
+                script
+                    .prompts()
+                    .iter()
+                    .map(|prompt| prompt_json(script, prompt))
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+/// A page that plays the script, with its player, styles and images all
+/// inlined so it works opened straight from disk.
+pub fn export(script: &Script, options: &HtmlOptions) -> String {
+    // `</script>` can't appear inside the inlined data
+    let inline = |json: Json| json.to_string().replace("</", "<\\/");
+    let images = options.images.iter().map(|image| {
+        let uri = format!("data:{};base64,{}", image.mime, base64(&image.data));
+        (image.label.clone(), Json::from(uri))
+    });
+    let theme = &options.theme;
+
+    format!(
+        "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>{title}</title>
+<style>
+:root {{ --background: {background}; --text: {text}; --accent: {accent}; --font: {font}; }}
+{css}</style>
+</head>
+<body>
+<main id=\"conversation\" aria-live=\"polite\"></main>
+<script type=\"application/json\" id=\"script\">{script}</script>
+<script type=\"application/json\" id=\"images\">{images}</script>
+<script>
+{js}</script>
+</body>
+</html>
+",
+        title = escape_html(&options.title),
+        background = escape_css(&theme.background),
+        text = escape_css(&theme.text),
+        accent = escape_css(&theme.accent),
+        font = escape_css(&theme.font),
+        css = PLAYER_CSS,
+        script = inline(script_json(script)),
+        images = inline(Json::object(images)),
+        js = PLAYER_JS,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use lexer::parse;
+    use prompter::script::Script;
+
+    use super::{base64, escape_css, export, script_json, HtmlOptions, Image, Theme};
+
+    #[test]
+    fn encodes_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar!"), "Zm9vYmFyIQ==");
+    }
+
+    #[test]
+    fn serializes_scripts() {
+        let data = "> (START) \"Hi\" [if !shy]\n< (END:happy) \"Yo\" {mood = \"good\"} [score 2]\n\
+                    < \"Pick\" [random A 3, B]\n> (A) \"Age?\"\n< input age [int ..9] [retry \"No\"]\n";
+        let script = Script::new(parse(data).unwrap()).unwrap();
+        let json = script_json(&script);
+
+        let prompts = json.get("prompts").unwrap().as_array().unwrap();
+        assert_eq!(
+            prompts[0].get("guard").unwrap().to_string(),
+            r#"{"not":{"var":"shy"}}"#
+        );
+        let responses = prompts[0].get("responses").unwrap().as_array().unwrap();
+        assert_eq!(
+            responses[0].to_string(),
+            r#"{"text":"Yo","label":"END:happy","assignments":[["mood","good"]],"guard":null,"input":null,"scores":[["score",2]],"target":null,"default":false}"#
+        );
+        assert_eq!(
+            responses[1].get("target").unwrap().to_string(),
+            r#"{"random":[["A",3],["B",1]]}"#
+        );
+        let responses = prompts[1].get("responses").unwrap().as_array().unwrap();
+        assert_eq!(
+            responses[0].get("input").unwrap().to_string(),
+            r#"{"variable":"age","check":{"int":true,"min":null,"max":9},"retry":"No"}"#
+        );
+        assert_eq!(json.get("scores").unwrap().to_string(), r#"["score"]"#);
+    }
+
+    #[test]
+    fn escapes_theme_values() {
+        assert_eq!(escape_css("#fdf6ec"), "#fdf6ec");
+        assert_eq!(
+            escape_css("\"Comic Sans\", \"Pat's\", serif"),
+            "\"Comic Sans\", \"Pat\\27 s\", serif"
+        );
+        assert_eq!(escape_css("red;}</style>"), "red\\3b \\7d \\3c /style\\3e ");
+        assert_eq!(escape_css("\"Sans"), "\\22 Sans");
+    }
+
+    #[test]
+    fn exports_a_self_contained_page() {
+        let data = read_to_string("./prompter/simple_prompt.txt").unwrap();
+        let script = Script::new(parse(&data).unwrap()).unwrap();
+        let options = HtmlOptions {
+            title: "For <Ada>".to_string(),
+            images: vec![Image {
+                label: "START".to_string(),
+                mime: "image/png".to_string(),
+                data: b"png".to_vec(),
+            }],
+            ..Default::default()
+        };
+
+        let page = export(&script, &options);
+        assert!(page.contains("<title>For &lt;Ada&gt;</title>"));
+        assert!(page.contains(r#"{"START":"data:image/png;base64,cG5n"}"#));
+        assert!(page.contains(r#""text":"Are you a human?""#));
+        assert!(!page.contains("src=\"http"));
+
+        let options = HtmlOptions {
+            theme: Theme {
+                accent: "red; } </style><script>alert(1)</script>".to_string(),
+                font: "\"Comic Sans\", serif".to_string(),
+                ..Theme::dark()
+            },
+            ..Default::default()
+        };
+        let page = export(&script, &options);
+        assert_eq!(page.matches("</style>").count(), 1);
+        assert!(page.contains("--font: \"Comic Sans\", serif;"));
+    }
+}
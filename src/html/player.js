@@ -0,0 +1,780 @@
+"use strict";
+
+// Plays a script exported by `promptgen export`. The engine follows the same
+// rules as `Prompter`, the page around it is a chat log with buttons.
+(function () {
+  var END_LABEL = "END";
+
+  // Endings a conversation can have, like `Ending`
+  function completed(outcome) {
+    return { kind: "completed", outcome: outcome };
+  }
+  var DEAD_END = { kind: "dead_end" };
+  var LOOPED = { kind: "looped" };
+  function unresolved(label) {
+    return { kind: "unresolved_label", label: label };
+  }
+
+  // An answer turned down, with the `PrompterErr::code` it has in Rust
+  function refused(code, message) {
+    var err = new Error(message || code);
+    err.code = code;
+    return err;
+  }
+
+  function endingFromLabel(label) {
+    if (label === END_LABEL) return completed(null);
+    var colon = label.indexOf(":");
+    if (colon >= 0 && label.slice(0, colon) === END_LABEL) {
+      return completed(label.slice(colon + 1));
+    }
+    return null;
+  }
+
+  // Anything that can't be worked out evaluates to undefined, like `evaluate`
+  function evaluate(expr, vars) {
+    if ("lit" in expr) return expr.lit;
+    if ("var" in expr) return vars[expr.var];
+    if ("not" in expr) return !holds(expr.not, vars);
+    if (expr.op === "&&") return holds(expr.lhs, vars) && holds(expr.rhs, vars);
+    if (expr.op === "||") return holds(expr.lhs, vars) || holds(expr.rhs, vars);
+
+    var lhs = evaluate(expr.lhs, vars);
+    var rhs = evaluate(expr.rhs, vars);
+    if (lhs === undefined || rhs === undefined) return undefined;
+    var comparable = typeof lhs === typeof rhs;
+    switch (expr.op) {
+      case "+":
+        return typeof lhs === "number" && comparable ? lhs + rhs : undefined;
+      case "-":
+        return typeof lhs === "number" && comparable ? lhs - rhs : undefined;
+      case "==":
+        return lhs === rhs;
+      case "!=":
+        return lhs !== rhs;
+      case "<":
+        return comparable ? lhs < rhs : undefined;
+      case "<=":
+        return comparable ? lhs <= rhs : undefined;
+      case ">":
+        return comparable ? lhs > rhs : undefined;
+      case ">=":
+        return comparable ? lhs >= rhs : undefined;
+    }
+    return undefined;
+  }
+
+  function holds(expr, vars) {
+    var value = evaluate(expr, vars);
+    if (typeof value === "boolean") return value;
+    if (typeof value === "number") return value !== 0;
+    if (typeof value === "string") return value !== "";
+    return false;
+  }
+
+  // Fills in `{var|filter}` placeholders, text that isn't a valid template is
+  // shown as is
+  function render(text, vars) {
+    var out = "";
+    var idx = 0;
+    while (idx < text.length) {
+      var c = text[idx];
+      if ((c === "{" || c === "}") && text[idx + 1] === c) {
+        out += c;
+        idx += 2;
+      } else if (c === "{") {
+        var end = text.indexOf("}", idx);
+        if (end < 0) return text;
+        var value = placeholder(text.slice(idx + 1, end), vars);
+        if (value === null) return text;
+        out += value;
+        idx = end + 1;
+      } else if (c === "}") {
+        return text;
+      } else {
+        out += c;
+        idx += 1;
+      }
+    }
+    return out;
+  }
+
+  function placeholder(data, vars) {
+    var parts = data.split("|");
+    var name = parts[0].trim();
+    if (!/^[A-Za-z0-9_]+$/.test(name)) return null;
+
+    var value = name in vars ? String(vars[name]) : null;
+    for (var i = 1; i < parts.length; i++) {
+      var filter = parts[i].trim();
+      if (filter === "upper") {
+        value = value === null ? null : value.toUpperCase();
+      } else if (filter === "lower") {
+        value = value === null ? null : value.toLowerCase();
+      } else if (filter.indexOf("default:") === 0) {
+        if (value === null || value === "") value = filter.slice("default:".length);
+      } else {
+        return null;
+      }
+    }
+    return value === null ? "" : value;
+  }
+
+  function isDate(text) {
+    var match = /^(\d{4})-(\d{2})-(\d{2})$/.exec(text);
+    if (!match) return false;
+    var year = +match[1];
+    var month = +match[2];
+    var day = +match[3];
+    var leap = (year % 4 === 0 && year % 100 !== 0) || year % 400 === 0;
+    var days = [31, leap ? 29 : 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31][month - 1];
+    return days !== undefined && day >= 1 && day <= days;
+  }
+
+  function isEmail(text) {
+    var at = text.indexOf("@");
+    if (at <= 0 || /\s/.test(text)) return false;
+    var domain = text.slice(at + 1);
+    var parts = domain.split(".");
+    return domain.indexOf("@") < 0 && parts.length > 1 && parts.every(function (p) {
+      return p !== "";
+    });
+  }
+
+  // Runs a `[regex ...]` program written out by `Pattern::to_json` over the
+  // whole of text, the way `Pattern::is_match` does
+  function matches(program, text) {
+    var added = program.map(function () {
+      return 0;
+    });
+    function add(list, pc, step) {
+      var stack = [pc];
+      while (stack.length > 0) {
+        pc = stack.pop();
+        if (added[pc] === step) continue;
+        added[pc] = step;
+        var inst = program[pc];
+        if ("jump" in inst) stack.push(inst.jump);
+        else if ("split" in inst) stack.push(inst.split[1], inst.split[0]);
+        else list.push(pc);
+      }
+    }
+    function takes(take, code) {
+      var inRange = take.ranges.some(function (range) {
+        return code >= range[0] && code <= range[1];
+      });
+      return inRange !== take.negated;
+    }
+
+    var current = [];
+    add(current, 0, 1);
+    var chars = Array.from(text);
+    for (var idx = 0; idx < chars.length; idx++) {
+      if (current.length === 0) return false;
+      var code = chars[idx].codePointAt(0);
+      var next = [];
+      for (var i = 0; i < current.length; i++) {
+        var take = program[current[i]].take;
+        if (take && takes(take, code)) add(next, current[i] + 1, idx + 2);
+      }
+      current = next;
+    }
+    return current.some(function (pc) {
+      return "match" in program[pc];
+    });
+  }
+
+  // The value typed in text is stored as, or why it doesn't pass, like
+  // `input::check`
+  function check(check, text) {
+    var trimmed = text.trim();
+    if (!check) return { value: text };
+
+    if (check.int) {
+      // Whole numbers have to fit an `i64`
+      var big = /^[+-]?\d+$/.test(trimmed) ? BigInt(trimmed) : null;
+      if (big === null || BigInt.asIntN(64, big) !== big) return { mismatch: "not a whole number" };
+      var n = Number(big);
+      var min = check.min;
+      var max = check.max;
+      if ((min === null || n >= min) && (max === null || n <= max)) return { value: n };
+      if (min !== null && max !== null) return { mismatch: "not between " + min + " and " + max };
+      if (min !== null) return { mismatch: "less than " + min };
+      return { mismatch: "more than " + max };
+    }
+    if (check.yesno) {
+      var answer = trimmed.toLowerCase();
+      if (answer === "yes" || answer === "y") return { value: true };
+      if (answer === "no" || answer === "n") return { value: false };
+      return { mismatch: "not yes or no" };
+    }
+    if (check.date) {
+      return isDate(trimmed) ? { value: trimmed } : { mismatch: "not a YYYY-MM-DD date" };
+    }
+    if (check.email) {
+      return isEmail(trimmed) ? { value: trimmed } : { mismatch: "not an email address" };
+    }
+    if (check.one_of) {
+      return check.one_of.indexOf(trimmed) >= 0
+        ? { value: trimmed }
+        : { mismatch: "not one of " + check.one_of.join(", ") };
+    }
+    // A pattern that doesn't compile is `null` and matches nothing
+    return check.regex !== null && matches(check.regex, text)
+      ? { value: text }
+      : { mismatch: "not in the expected format" };
+  }
+
+  // SplitMix64 like `SeededRandom`, so a run seeded the same picks the same
+  // branches and shuffles as in Rust
+  function SeededRandom(seed) {
+    this.state = BigInt.asUintN(64, BigInt(seed));
+  }
+
+  SeededRandom.fromEntropy = function () {
+    var words = new Uint32Array(2);
+    if (typeof crypto !== "undefined" && crypto.getRandomValues) {
+      crypto.getRandomValues(words);
+    } else {
+      words[0] = Math.random() * 0x100000000;
+      words[1] = Math.random() * 0x100000000;
+    }
+    return new SeededRandom((BigInt(words[0]) << 32n) | BigInt(words[1]));
+  };
+
+  SeededRandom.prototype.nextU64 = function () {
+    this.state = BigInt.asUintN(64, this.state + 0x9e3779b97f4a7c15n);
+    var z = this.state;
+    z = BigInt.asUintN(64, (z ^ (z >> 30n)) * 0xbf58476d1ce4e5b9n);
+    z = BigInt.asUintN(64, (z ^ (z >> 27n)) * 0x94d049bb133111ebn);
+    return z ^ (z >> 31n);
+  };
+
+  // Evenly below `n`, which has to be more than 0
+  SeededRandom.prototype.below = function (n) {
+    return Number((this.nextU64() * BigInt(n)) >> 64n);
+  };
+
+  // One run through the script, a cursor like `Prompter`. Answers that are
+  // turned down throw a `refused` error and leave the session as it was.
+  function Session(script, random) {
+    this.script = script;
+    this.random = random || SeededRandom.fromEntropy();
+    this.labels = Object.create(null);
+    for (var idx = script.prompts.length - 1; idx >= 0; idx--) {
+      var label = script.prompts[idx].label;
+      if (label !== null) this.labels[label] = idx;
+    }
+
+    this.vars = Object.create(null);
+    this.ending = null;
+    this.order = null;
+    this.statementsSeen = [];
+    // Prompts entered after the first, telling a new prompt from one that's
+    // still being answered
+    this.entered = 0;
+    // Whether the conversation has moved on from where it started
+    this.moved = false;
+
+    var start = this.startingPoint();
+    this.idx = start.idx;
+    this.ending = start.ending;
+    this.arrange();
+  }
+
+  // The first prompt whose guard holds, and how that ends the conversation
+  // straight away if it does
+  Session.prototype.startingPoint = function () {
+    var start = this.skipGuarded(this.script.start);
+    if (start === null) return { idx: this.script.start, ending: DEAD_END };
+    return { idx: start, ending: this.endingAt(start) };
+  };
+
+  // Presets a variable. Until the conversation moves on, where it starts is
+  // worked out again so that guards on the first prompts see it.
+  Session.prototype.withVariable = function (name, value) {
+    this.vars[name] = value;
+    if (this.moved) return;
+    var start = this.startingPoint();
+    if (start.idx !== this.idx) {
+      this.idx = start.idx;
+      this.arrange();
+    }
+    this.ending = start.ending;
+  };
+
+  Session.prototype.current = function () {
+    return this.script.prompts[this.idx];
+  };
+
+  Session.prototype.holds = function (guard) {
+    return guard === null || holds(guard, this.vars);
+  };
+
+  Session.prototype.skipGuarded = function (idx) {
+    for (; idx < this.script.prompts.length; idx++) {
+      if (this.holds(this.script.prompts[idx].guard)) return idx;
+    }
+    return null;
+  };
+
+  // Indices of the current prompt's available responses, in offered order
+  Session.prototype.offered = function () {
+    var responses = this.current().responses;
+    var order = this.order || responses.map(function (_, idx) {
+      return idx;
+    });
+    var session = this;
+    return order.filter(function (idx) {
+      return session.holds(responses[idx].guard);
+    });
+  };
+
+  Session.prototype.arrange = function () {
+    var len = this.current().responses.length;
+    this.order = null;
+    if (!this.current().shuffle) return;
+
+    var order = [];
+    for (var idx = 0; idx < len; idx++) order.push(idx);
+    for (idx = len - 1; idx > 0; idx--) {
+      var other = this.random.below(idx + 1);
+      var swap = order[idx];
+      order[idx] = order[other];
+      order[other] = swap;
+    }
+    this.order = order;
+  };
+
+  Session.prototype.isStatement = function () {
+    return this.ending === null && this.current().advance !== null;
+  };
+
+  Session.prototype.endingAt = function (idx) {
+    var prompt = this.script.prompts[idx];
+    if (prompt.advance !== null) return null;
+    if (prompt.responses.length === 0) {
+      return (prompt.label !== null && endingFromLabel(prompt.label)) || completed(null);
+    }
+    var session = this;
+    var available = prompt.responses.some(function (response) {
+      return session.holds(response.guard);
+    });
+    return available ? null : DEAD_END;
+  };
+
+  Session.prototype.score = function (name) {
+    var value = this.vars[name];
+    return typeof value === "number" ? value : 0;
+  };
+
+  Session.prototype.resolveTarget = function (target) {
+    if ("label" in target) return target.label;
+    if ("random" in target) {
+      var total = target.random.reduce(function (sum, pick) {
+        return sum + pick[1];
+      }, 0);
+      if (total === 0) return null;
+      var roll = this.random.below(total);
+      for (var i = 0; i < target.random.length; i++) {
+        if (roll < target.random[i][1]) return target.random[i][0];
+        roll -= target.random[i][1];
+      }
+      return null;
+    }
+
+    var session = this;
+    var score = target.score !== null
+      ? this.score(target.score)
+      : this.script.scores.reduce(function (total, name) {
+          return total + session.score(name);
+        }, 0);
+    for (var j = 0; j < target.bands.length; j++) {
+      var band = target.bands[j];
+      if ((band[0] === null || score >= band[0]) && (band[1] === null || score <= band[1])) {
+        return band[2];
+      }
+    }
+    return null;
+  };
+
+  // The next prompt's index, or the ending going there leads to
+  Session.prototype.followLabel = function (label) {
+    var target = this.current().target;
+    if (label === null && target !== null) {
+      label = this.resolveTarget(target);
+      if (label === null) return { ending: DEAD_END };
+    }
+    if (label !== null) {
+      if (label in this.labels) return { idx: this.labels[label] };
+      return { ending: endingFromLabel(label) || unresolved(label) };
+    }
+    return this.idx + 1 < this.script.prompts.length ? { idx: this.idx + 1 } : { ending: DEAD_END };
+  };
+
+  Session.prototype.follow = function (response) {
+    if (response.target !== null) {
+      var label = this.resolveTarget(response.target);
+      return label === null ? { ending: DEAD_END } : this.followLabel(label);
+    }
+    return this.followLabel(response.label);
+  };
+
+  Session.prototype.moveOn = function (next) {
+    this.moved = true;
+    if ("ending" in next) {
+      this.ending = next.ending;
+      return;
+    }
+    var idx = this.skipGuarded(next.idx);
+    if (idx === null) {
+      this.ending = DEAD_END;
+      return;
+    }
+    this.idx = idx;
+    this.entered += 1;
+    this.arrange();
+    this.ending = this.endingAt(idx);
+  };
+
+  Session.prototype.choose = function (response) {
+    var session = this;
+    response.assignments.forEach(function (assignment) {
+      session.vars[assignment[0]] = assignment[1];
+    });
+    response.scores.forEach(function (delta) {
+      session.vars[delta[0]] = session.score(delta[0]) + delta[1];
+    });
+  };
+
+  Session.prototype.take = function (index, stored) {
+    var response = this.current().responses[index];
+    this.statementsSeen = [];
+    this.choose(response);
+    if (stored) this.vars[stored.variable] = stored.value;
+    this.moveOn(this.follow(response));
+  };
+
+  Session.prototype.advance = function () {
+    if (this.ending !== null) throw refused("finished");
+    var advance = this.current().advance;
+    if (advance === null) throw refused("not_a_statement");
+    this.statementsSeen.push(this.idx);
+    this.moveOn(this.followLabel("goto" in advance ? advance.goto : null));
+    if (this.isStatement() && this.statementsSeen.indexOf(this.idx) >= 0) {
+      this.ending = LOOPED;
+    }
+  };
+
+  Session.prototype.answer = function (index) {
+    if (this.ending !== null) throw refused("finished");
+    var response = this.current().responses[index];
+    if (response === undefined) throw refused("bad_response");
+    if (response.input !== null) throw refused("input_required");
+    if (this.current().multi !== null) throw refused("selection_required");
+    if (this.offered().indexOf(index) < 0) throw refused("bad_response");
+    this.take(index, null);
+  };
+
+  Session.prototype.answerMany = function (indices) {
+    if (this.ending !== null) throw refused("finished");
+    var multi = this.current().multi;
+    if (multi === null) throw refused("bad_response");
+
+    var session = this;
+    var responses = this.current().responses;
+    var offered = this.offered();
+    indices.forEach(function (index, at) {
+      if (offered.indexOf(index) < 0 || indices.indexOf(index) < at || responses[index].input !== null) {
+        throw refused("bad_response");
+      }
+    });
+    if (!allows(multi, indices.length)) throw refused("selection_count");
+
+    this.statementsSeen = [];
+    indices.forEach(function (index) {
+      session.choose(responses[index]);
+    });
+    this.moveOn(this.followLabel(null));
+  };
+
+  Session.prototype.defaultIndex = function () {
+    var responses = this.current().responses;
+    var found = this.offered().filter(function (idx) {
+      return responses[idx].default;
+    });
+    return found.length > 0 ? found[0] : null;
+  };
+
+  // Takes the `[default]` response. On a multi-select prompt it's picked on
+  // its own, which has to be enough of a selection.
+  Session.prototype.timeout = function () {
+    if (this.ending !== null) throw refused("finished");
+    var index = this.defaultIndex();
+    if (index === null) throw refused("no_default");
+    if (this.current().multi !== null) {
+      this.answerMany([index]);
+    } else {
+      this.take(index, null);
+    }
+  };
+
+  // Whether `timeout` would take the default rather than turn it down
+  Session.prototype.timesOut = function () {
+    var multi = this.current().multi;
+    return this.ending === null && this.defaultIndex() !== null && (multi === null || allows(multi, 1));
+  };
+
+  Session.prototype.inputIndex = function () {
+    var responses = this.current().responses;
+    var session = this;
+    var found = responses
+      .map(function (_, idx) {
+        return idx;
+      })
+      .filter(function (idx) {
+        return responses[idx].input !== null && session.holds(responses[idx].guard);
+      });
+    return found.length > 0 ? found[0] : null;
+  };
+
+  // The value typed in text would be stored as, or the retry message when
+  // it's turned down
+  Session.prototype.checkInput = function (text) {
+    var input = this.current().responses[this.inputIndex()].input;
+    var result = check(input.check, text);
+    if ("mismatch" in result && input.retry !== null) {
+      return { mismatch: render(input.retry, this.vars) };
+    }
+    return result;
+  };
+
+  // Answers with typed in text, blank text takes the `[default]` response
+  Session.prototype.answerInput = function (text) {
+    if (text.trim() === "" && this.defaultIndex() !== null) {
+      this.timeout();
+      return;
+    }
+    if (this.ending !== null) throw refused("finished");
+    var index = this.inputIndex();
+    if (index === null) throw refused("bad_response");
+    var result = this.checkInput(text);
+    if ("mismatch" in result) throw refused("invalid_input", result.mismatch);
+    this.take(index, { variable: this.current().responses[index].input.variable, value: result.value });
+  };
+
+  function selectionText(multi) {
+    if (multi.max === null) return multi.min > 0 ? "Pick at least " + multi.min : "Pick any";
+    if (multi.min === multi.max) return "Pick " + multi.min;
+    return "Pick " + multi.min + " to " + multi.max;
+  }
+
+  function endingText(ending) {
+    switch (ending.kind) {
+      case "completed":
+        return ending.outcome !== null ? "The end (" + ending.outcome + ")" : "The end";
+      case "dead_end":
+        return "The conversation has nowhere left to go";
+      case "unresolved_label":
+        return "The conversation went looking for " + ending.label + " and got lost";
+      case "looped":
+        return "The conversation went round in circles";
+    }
+  }
+
+  function element(tag, className, text) {
+    var node = document.createElement(tag);
+    if (className) node.className = className;
+    if (text !== undefined) node.textContent = text;
+    return node;
+  }
+
+  function Player(root, script, images) {
+    this.root = root;
+    this.script = script;
+    this.images = images;
+    this.timer = null;
+  }
+
+  Player.prototype.restart = function () {
+    this.root.textContent = "";
+    this.session = new Session(this.script);
+    this.shown = -1;
+    this.show();
+  };
+
+  Player.prototype.say = function (className, text) {
+    var bubble = element("div", "bubble " + className, text);
+    this.root.appendChild(bubble);
+    bubble.scrollIntoView({ behavior: "smooth", block: "end" });
+    return bubble;
+  };
+
+  // Clears the answers on offer, moving on with `then`
+  Player.prototype.respond = function (text, then) {
+    clearTimeout(this.timer);
+    var controls = this.root.querySelector(".controls");
+    if (controls) controls.remove();
+    if (text !== null) this.say("answer", text);
+    then();
+    this.show();
+  };
+
+  Player.prototype.show = function () {
+    var session = this.session;
+    var prompt = session.current();
+    if (this.shown !== session.entered) {
+      this.shown = session.entered;
+      var bubble = this.say("prompt", render(prompt.text, session.vars));
+      if (prompt.label !== null && Object.prototype.hasOwnProperty.call(this.images, prompt.label)) {
+        var picture = element("img");
+        picture.src = this.images[prompt.label];
+        picture.alt = "";
+        bubble.insertBefore(picture, bubble.firstChild);
+      }
+    }
+
+    if (session.ending !== null) {
+      this.showEnding(session.ending);
+    } else if (session.isStatement()) {
+      this.showStatement();
+    } else {
+      this.showResponses(prompt);
+    }
+  };
+
+  Player.prototype.showStatement = function () {
+    var player = this;
+    var controls = this.controls();
+    var next = element("button", "choice", "Continue");
+    next.addEventListener("click", function () {
+      player.respond(null, function () {
+        player.session.advance();
+      });
+    });
+    controls.appendChild(next);
+    next.focus();
+  };
+
+  Player.prototype.controls = function () {
+    var controls = element("div", "controls");
+    this.root.appendChild(controls);
+    return controls;
+  };
+
+  Player.prototype.showResponses = function (prompt) {
+    var player = this;
+    var session = this.session;
+    var controls = this.controls();
+    var picked = [];
+    var send = null;
+
+    session.offered().forEach(function (index) {
+      var response = prompt.responses[index];
+      if (response.input !== null) return;
+      var text = render(response.text, session.vars);
+      var button = element("button", "choice", text);
+
+      if (prompt.multi === null) {
+        button.addEventListener("click", function () {
+          player.respond(text, function () {
+            session.answer(index);
+          });
+        });
+      } else {
+        button.setAttribute("aria-pressed", "false");
+        button.addEventListener("click", function () {
+          var at = picked.indexOf(index);
+          if (at >= 0) picked.splice(at, 1);
+          else picked.push(index);
+          button.setAttribute("aria-pressed", at >= 0 ? "false" : "true");
+          send.disabled = !allows(prompt.multi, picked.length);
+        });
+      }
+      controls.appendChild(button);
+    });
+
+    if (prompt.multi !== null) {
+      send = element("button", "send", selectionText(prompt.multi));
+      send.disabled = !allows(prompt.multi, 0);
+      send.addEventListener("click", function () {
+        var texts = picked.map(function (index) {
+          return render(prompt.responses[index].text, session.vars);
+        });
+        player.respond(texts.join(", "), function () {
+          session.answerMany(picked);
+        });
+      });
+      controls.appendChild(send);
+    }
+
+    if (session.inputIndex() !== null) this.showInput(controls);
+
+    var timeout = prompt.timeout;
+    if (timeout !== null && session.timesOut()) {
+      this.timer = setTimeout(function () {
+        var response = prompt.responses[session.defaultIndex()];
+        player.respond(render(response.text, session.vars), function () {
+          session.timeout();
+        });
+      }, timeout);
+    }
+  };
+
+  Player.prototype.showInput = function (controls) {
+    var player = this;
+    var session = this.session;
+    var form = element("form", "input");
+    var field = element("input");
+    field.type = "text";
+    field.setAttribute("aria-label", "Your answer");
+    var error = element("p", "error");
+    form.appendChild(field);
+    form.appendChild(element("button", "send", "Send"));
+    form.appendChild(error);
+
+    form.addEventListener("submit", function (event) {
+      event.preventDefault();
+      var text = field.value;
+      var index = session.defaultIndex();
+      if (text.trim() === "" && index !== null) {
+        text = render(session.current().responses[index].text, session.vars);
+      } else {
+        var result = session.checkInput(text);
+        if ("mismatch" in result) {
+          error.textContent = result.mismatch;
+          field.select();
+          return;
+        }
+      }
+      player.respond(text, function () {
+        session.answerInput(field.value);
+      });
+    });
+    controls.appendChild(form);
+    field.focus();
+  };
+
+  Player.prototype.showEnding = function (ending) {
+    var player = this;
+    var controls = this.controls();
+    controls.appendChild(element("p", "ending", endingText(ending)));
+    var again = element("button", "send", "Play again");
+    again.addEventListener("click", function () {
+      player.restart();
+    });
+    controls.appendChild(again);
+  };
+
+  function allows(multi, count) {
+    return count >= multi.min && (multi.max === null || count <= multi.max);
+  }
+
+  if (typeof document !== "undefined") {
+    var script = JSON.parse(document.getElementById("script").textContent);
+    var images = JSON.parse(document.getElementById("images").textContent);
+    new Player(document.getElementById("conversation"), script, images).restart();
+  } else if (typeof module !== "undefined") {
+    module.exports = { Session: Session, SeededRandom: SeededRandom, render: render, check: check };
+  }
+})();
@@ -1,2 +1,9 @@
 pub use lexer;
 pub use prompter;
+
+pub mod check;
+pub mod html;
+pub mod lsp;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod session;
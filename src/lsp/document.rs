@@ -0,0 +1,265 @@
+use std::ops::Range;
+
+use lexer::{
+    parse_located,
+    parser::{Advance, Prompt, Target},
+};
+
+use crate::check::offset_in;
+
+/// Where a label is written, `definition` being the `(LABEL)` a prompt is
+/// known by and every other one a way to get to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence<'a> {
+    pub label: &'a str,
+    pub range: Range<usize>,
+    pub definition: bool,
+}
+
+/// A prompt as listed in an outline, `range` covering it and its responses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol<'a> {
+    pub name: &'a str,
+    pub detail: Option<&'a str>,
+    pub range: Range<usize>,
+    pub selection: Range<usize>,
+}
+
+/// The labels and prompts of a script that parses.
+pub struct Document<'a> {
+    data: &'a str,
+    prompts: Vec<Prompt<'a>>,
+    occurrences: Vec<Occurrence<'a>>,
+}
+
+fn target_labels<'a>(target: &Target<'a>) -> Vec<&'a str> {
+    match target {
+        Target::Bands { bands, .. } => bands.iter().map(|band| band.label).collect(),
+        Target::Random(labels) => labels.iter().map(|weighted| weighted.label).collect(),
+        Target::Label(label) => vec![label],
+    }
+}
+
+/// Whether `label` is an ending, `END` or `END:outcome`, rather than a prompt.
+pub fn is_ending(label: &str) -> bool {
+    label == "END" || label.starts_with("END:")
+}
+
+/// Whether a label can be written anywhere labels go, bare in `[bands ...]`
+/// and `[random ...]` included.
+pub fn is_plain_label(label: &str) -> bool {
+    !label.is_empty()
+        && label
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+}
+
+/// The zero-based line and UTF-16 character of a byte offset, as editors
+/// count them.
+pub fn position(data: &str, offset: usize) -> (usize, usize) {
+    let before = &data[..offset.min(data.len())];
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+    let line = before.matches('\n').count();
+    (line, before[line_start..].encode_utf16().count())
+}
+
+/// The byte offset of a zero-based line and UTF-16 character, clamped to
+/// the line's end.
+pub fn offset(data: &str, line: usize, character: usize) -> usize {
+    let line_start = match line {
+        0 => 0,
+        _ => match data.match_indices('\n').nth(line - 1) {
+            Some((idx, _)) => idx + 1,
+            None => return data.len(),
+        },
+    };
+
+    let mut units = 0;
+    for (idx, c) in data[line_start..].char_indices() {
+        if c == '\n' || units >= character {
+            return line_start + idx;
+        }
+        units += c.len_utf16();
+    }
+    data.len()
+}
+
+/// The labels of the prompts in `data`, with the rest of their line, found
+/// line by line so that half typed scripts that don't parse still have them.
+pub fn prompt_labels(data: &str) -> Vec<(&str, &str)> {
+    data.lines()
+        .filter_map(|line| {
+            let rest = line.trim_start().strip_prefix('>')?.trim_start();
+            let (label, rest) = rest.strip_prefix('(')?.split_once(')')?;
+            (!label.is_empty() && !label.contains(char::is_whitespace))
+                .then_some((label, rest.trim()))
+        })
+        .collect()
+}
+
+impl<'a> Document<'a> {
+    /// `None` when the script doesn't parse.
+    pub fn new(data: &'a str) -> Option<Self> {
+        let prompts = parse_located(data).ok()?;
+        let mut occurrences = vec![];
+        let mut add = |label: &'a str, definition| {
+            if let Some(start) = offset_in(data, label) {
+                occurrences.push(Occurrence {
+                    label,
+                    range: start..start + label.len(),
+                    definition,
+                });
+            }
+        };
+
+        for prompt in &prompts {
+            prompt.label.into_iter().for_each(|label| add(label, true));
+            if let Some(Advance::Goto(label)) = prompt.advance {
+                add(label, false);
+            }
+            prompt
+                .target
+                .iter()
+                .flat_map(target_labels)
+                .for_each(|label| add(label, false));
+
+            for response in &prompt.responses {
+                response
+                    .label
+                    .into_iter()
+                    .for_each(|label| add(label, false));
+                response
+                    .target
+                    .iter()
+                    .flat_map(target_labels)
+                    .for_each(|label| add(label, false));
+            }
+        }
+        occurrences.sort_by_key(|occurrence| occurrence.range.start);
+
+        Some(Document {
+            data,
+            prompts,
+            occurrences,
+        })
+    }
+
+    /// The label at a byte offset, the end of one included so that a cursor
+    /// just after a label still finds it.
+    pub fn at(&self, offset: usize) -> Option<&Occurrence<'a>> {
+        self.occurrences
+            .iter()
+            .find(|occurrence| occurrence.range.contains(&offset) || occurrence.range.end == offset)
+    }
+
+    /// Every place `label` is written, in order.
+    pub fn occurrences<'s>(&'s self, label: &'s str) -> impl Iterator<Item = &'s Occurrence<'a>> {
+        self.occurrences
+            .iter()
+            .filter(move |occurrence| occurrence.label == label)
+    }
+
+    /// Where the prompt going by `label` is labelled, the first one when
+    /// several share it, as prompting picks that one.
+    pub fn definition<'s>(&'s self, label: &'s str) -> Option<&'s Occurrence<'a>> {
+        self.occurrences(label)
+            .find(|occurrence| occurrence.definition)
+    }
+
+    pub fn prompt(&self, label: &str) -> Option<&Prompt<'a>> {
+        self.prompts
+            .iter()
+            .find(|prompt| prompt.label == Some(label))
+    }
+
+    /// The prompts in order, each from its line to the next prompt's.
+    pub fn symbols(&self) -> Vec<Symbol<'a>> {
+        let line_start = |offset: usize| self.data[..offset].rfind('\n').map_or(0, |idx| idx + 1);
+        let mut symbols: Vec<Symbol> = self
+            .prompts
+            .iter()
+            .filter_map(|prompt| {
+                let label = prompt
+                    .label
+                    .and_then(|label| Some((label, offset_in(self.data, label)?)));
+                let text = offset_in(self.data, prompt.text).map(|offset| (prompt.text, offset));
+                let (name, start) = label.or(text)?;
+                let earliest = [label, text]
+                    .into_iter()
+                    .flatten()
+                    .map(|(_, offset)| offset)
+                    .min()?;
+
+                Some(Symbol {
+                    name,
+                    detail: label.map(|_| prompt.text),
+                    range: line_start(earliest)..self.data.len(),
+                    selection: start..start + name.len(),
+                })
+            })
+            .collect();
+
+        // Each prompt runs up to where the next one starts
+        for idx in 1..symbols.len() {
+            let end = self.data[..symbols[idx].range.start].trim_end().len();
+            symbols[idx - 1].range.end = end.max(symbols[idx - 1].selection.end);
+        }
+        if let Some(last) = symbols.last_mut() {
+            last.range.end = self.data.trim_end().len().max(last.selection.end);
+        }
+        symbols
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{offset, position, prompt_labels, Document};
+
+    const SCRIPT: &str = "> (START) \"Heads or tails?\" [random WIN 3, LOSE]\n\
+                          < \"Heads\"\n\
+                          < (LOSE) \"Lose on purpose\"\n\
+                          > (WIN) \"You won!\" -> END\n\
+                          > (LOSE) \"You lost!\"\n";
+
+    #[test]
+    fn finds_labels_and_prompts() {
+        let document = Document::new(SCRIPT).unwrap();
+
+        let lose: Vec<_> = document
+            .occurrences("LOSE")
+            .map(|o| o.range.clone())
+            .collect();
+        assert_eq!(lose, [43..47, 62..66, 115..119]);
+        assert_eq!(document.definition("LOSE").unwrap().range, 115..119);
+        assert_eq!(document.at(47).unwrap().label, "LOSE");
+        assert!(document.at(20).is_none());
+        assert_eq!(document.prompt("WIN").unwrap().text, "You won!");
+
+        let symbols = document.symbols();
+        let names: Vec<_> = symbols.iter().map(|symbol| symbol.name).collect();
+        assert_eq!(names, ["START", "WIN", "LOSE"]);
+        assert_eq!(symbols[0].range, 0..85);
+        assert_eq!(symbols[2].detail, Some("You lost!"));
+
+        assert!(Document::new("> (A) \"Hi\"\n< oops").is_none());
+    }
+
+    #[test]
+    fn counts_positions_in_utf16() {
+        let data = "> \"héllo 🎂\"\n< (A) \"x\"";
+        assert_eq!(position(data, 0), (0, 0));
+        let cake = data.find('🎂').unwrap();
+        assert_eq!(position(data, cake), (0, 9));
+        assert_eq!(position(data, cake + 4), (0, 11));
+        assert_eq!(offset(data, 0, 11), cake + 4);
+        assert_eq!(offset(data, 1, 3), data.find('A').unwrap());
+        assert_eq!(offset(data, 1, 99), data.len());
+        assert_eq!(offset(data, 7, 0), data.len());
+    }
+
+    #[test]
+    fn lists_labels_of_scripts_that_dont_parse() {
+        let labels = prompt_labels("> (START) \"Hi\"\n< (\n  >  (END_IT) \"Bye\"\n> (bad label)");
+        assert_eq!(labels, [("START", "\"Hi\""), ("END_IT", "\"Bye\"")]);
+    }
+}
@@ -0,0 +1,709 @@
+//! A language server for scripts, speaking LSP over stdio so editors get
+//! diagnostics, navigation between labels and prompts, renaming, label
+//! completion, hovers and an outline.
+//!
+//! Documents are synced whole and analysed again on every request, scripts
+//! being small. Host variables the scripts can use without setting them go
+//! in the `variables` initialization option, as with `check --vars`.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    ops::Range,
+    panic::{self, AssertUnwindSafe},
+};
+
+use prompter::{json::Json, validate::Severity};
+
+use crate::check::check;
+
+pub mod document;
+pub mod rpc;
+
+use document::{is_ending, is_plain_label, offset, position, prompt_labels, Document};
+use rpc::{parse_body, read_body, write_message};
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+// LSP enumerations
+const SYNC_FULL: i64 = 1;
+const SEVERITY_ERROR: i64 = 1;
+const SEVERITY_WARNING: i64 = 2;
+const COMPLETION_REFERENCE: i64 = 18;
+const SYMBOL_KEY: i64 = 20;
+
+/// What a request failed with, sent back as its `error`.
+struct Failure {
+    code: i64,
+    message: String,
+}
+
+fn invalid_params(message: impl Into<String>) -> Failure {
+    Failure {
+        code: INVALID_PARAMS,
+        message: message.into(),
+    }
+}
+
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, String>,
+    host_variables: Vec<String>,
+    exited: bool,
+}
+
+// The response to the request numbered `id`
+fn response(id: Json, result: Result<Json, Failure>) -> Json {
+    let outcome = match result {
+        Ok(result) => ("result", result),
+        Err(failure) => (
+            "error",
+            Json::object([
+                ("code", Json::Number(failure.code as f64)),
+                ("message", Json::from(failure.message)),
+            ]),
+        ),
+    };
+    Json::object([("jsonrpc", Json::from("2.0")), ("id", id), outcome])
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    Json::object([
+        ("jsonrpc", Json::from("2.0")),
+        ("method", Json::from(method)),
+        ("params", params),
+    ])
+}
+
+fn position_json(data: &str, offset: usize) -> Json {
+    let (line, character) = position(data, offset);
+    Json::object([
+        ("line", Json::from(line)),
+        ("character", Json::from(character)),
+    ])
+}
+
+fn range_json(data: &str, range: &Range<usize>) -> Json {
+    Json::object([
+        ("start", position_json(data, range.start)),
+        ("end", position_json(data, range.end)),
+    ])
+}
+
+fn location_json(uri: &str, data: &str, range: &Range<usize>) -> Json {
+    Json::object([("uri", Json::from(uri)), ("range", range_json(data, range))])
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        ("textDocumentSync", Json::from(SYNC_FULL)),
+        ("definitionProvider", Json::Bool(true)),
+        ("referencesProvider", Json::Bool(true)),
+        ("renameProvider", Json::Bool(true)),
+        (
+            "completionProvider",
+            Json::object([("triggerCharacters", Json::Array(vec![Json::from("(")]))]),
+        ),
+        ("hoverProvider", Json::Bool(true)),
+        ("documentSymbolProvider", Json::Bool(true)),
+    ])
+}
+
+// The document and byte offset a `TextDocumentPositionParams` points at
+fn text_position(params: &Json) -> Result<(&str, usize, usize), Failure> {
+    let uri = params
+        .get("textDocument")
+        .and_then(|document| document.get("uri"))
+        .and_then(Json::as_str)
+        .ok_or_else(|| invalid_params("expected a textDocument"))?;
+    let position = params
+        .get("position")
+        .ok_or_else(|| invalid_params("expected a position"))?;
+    let number = |name| {
+        position
+            .get(name)
+            .and_then(Json::as_u64)
+            .map(|number| number as usize)
+            .ok_or_else(|| invalid_params(format!("expected the position's {name}")))
+    };
+    Ok((uri, number("line")?, number("character")?))
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the client has sent `exit`.
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Handles one message from the client, giving back the messages to send
+    /// it: the response to a request and any diagnostics published.
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message
+            .get("method")
+            .and_then(Json::as_str)
+            .unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+
+        let Some(id) = message.get("id").cloned() else {
+            return self.notify(method, &params);
+        };
+        let result = match method {
+            "initialize" => Ok(self.initialize(&params)),
+            "shutdown" => Ok(Json::Null),
+            "textDocument/definition" => self.definition(&params),
+            "textDocument/references" => self.references(&params),
+            "textDocument/rename" => self.rename(&params),
+            "textDocument/completion" => self.completion(&params),
+            "textDocument/hover" => self.hover(&params),
+            "textDocument/documentSymbol" => self.symbols(&params),
+            _ => Err(Failure {
+                code: METHOD_NOT_FOUND,
+                message: format!("{method} isn't supported"),
+            }),
+        };
+        vec![response(id, result)]
+    }
+
+    fn notify(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let document = params.get("textDocument");
+        let uri = document
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+            .unwrap_or_default()
+            .to_string();
+
+        match method {
+            "exit" => self.exited = true,
+            "textDocument/didOpen" => {
+                let text = document.and_then(|document| document.get("text"));
+                if let Some(text) = text.and_then(Json::as_str) {
+                    self.documents.insert(uri.clone(), text.to_string());
+                    return vec![self.diagnostics(&uri)];
+                }
+            }
+            "textDocument/didChange" => {
+                // Synced whole, so the last change is the document
+                let changes = params.get("contentChanges").and_then(Json::as_array);
+                let text = changes
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str);
+                if let Some(text) = text {
+                    self.documents.insert(uri.clone(), text.to_string());
+                    return vec![self.diagnostics(&uri)];
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![self.diagnostics(&uri)];
+            }
+            _ => {}
+        }
+        vec![]
+    }
+
+    fn initialize(&mut self, params: &Json) -> Json {
+        let variables = params
+            .get("initializationOptions")
+            .and_then(|options| options.get("variables"))
+            .and_then(Json::as_array);
+        self.host_variables = variables
+            .into_iter()
+            .flatten()
+            .filter_map(Json::as_str)
+            .map(str::to_string)
+            .collect();
+
+        Json::object([
+            ("capabilities", capabilities()),
+            (
+                "serverInfo",
+                Json::object([
+                    ("name", Json::from("promptgen-lsp")),
+                    ("version", Json::from(env!("CARGO_PKG_VERSION"))),
+                ]),
+            ),
+        ])
+    }
+
+    // Handles a message, answering a request that panics with an internal
+    // error rather than taking the server down with it
+    fn handle_unwinding(&mut self, message: &Json) -> Vec<Json> {
+        panic::catch_unwind(AssertUnwindSafe(|| self.handle(message))).unwrap_or_else(|_| {
+            let failure = Failure {
+                code: INTERNAL_ERROR,
+                message: "the server failed on this message".to_string(),
+            };
+            message
+                .get("id")
+                .map(|id| response(id.clone(), Err(failure)))
+                .into_iter()
+                .collect()
+        })
+    }
+
+    // Every finding of `check`, none once the document is closed
+    fn diagnostics(&self, uri: &str) -> Json {
+        let data = self.documents.get(uri).map_or("", String::as_str);
+        let host_variables: Vec<&str> = self.host_variables.iter().map(String::as_str).collect();
+        let findings = match self.documents.contains_key(uri) {
+            true => check(data, &host_variables),
+            false => vec![],
+        };
+
+        let diagnostics = findings
+            .into_iter()
+            .map(|finding| {
+                // Up to the end of the line, findings are only a place
+                let start = finding.offset.unwrap_or_default();
+                let line_end = data[start..]
+                    .find('\n')
+                    .map_or(data.len(), |end| start + end);
+                let end = start + data[start..line_end].trim_end().len();
+                let severity = match finding.severity {
+                    Severity::Error => SEVERITY_ERROR,
+                    Severity::Warning => SEVERITY_WARNING,
+                };
+                Json::object([
+                    ("range", range_json(data, &(start..end))),
+                    ("severity", Json::from(severity)),
+                    ("source", Json::from("promptgen")),
+                    ("message", Json::from(finding.message)),
+                ])
+            })
+            .collect();
+
+        notification(
+            "textDocument/publishDiagnostics",
+            Json::object([
+                ("uri", Json::from(uri)),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        )
+    }
+
+    // The document a request is about and the byte offset it points at
+    fn document<'p>(&self, params: &'p Json) -> Result<(&'p str, &str, usize), Failure> {
+        let (uri, line, character) = text_position(params)?;
+        let data = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| invalid_params(format!("{uri} isn't open")))?;
+        Ok((uri, data, offset(data, line, character)))
+    }
+
+    fn definition(&self, params: &Json) -> Result<Json, Failure> {
+        let (uri, data, offset) = self.document(params)?;
+        let definition = Document::new(data).and_then(|document| {
+            let label = document.at(offset)?.label;
+            document.definition(label).cloned()
+        });
+        Ok(definition.map_or(Json::Null, |definition| {
+            location_json(uri, data, &definition.range)
+        }))
+    }
+
+    fn references(&self, params: &Json) -> Result<Json, Failure> {
+        let (uri, data, offset) = self.document(params)?;
+        let declarations = params
+            .get("context")
+            .and_then(|context| context.get("includeDeclaration"))
+            .and_then(Json::as_bool)
+            .unwrap_or(true);
+
+        let Some(document) = Document::new(data) else {
+            return Ok(Json::Null);
+        };
+        let Some(label) = document.at(offset).map(|occurrence| occurrence.label) else {
+            return Ok(Json::Null);
+        };
+        let locations = document
+            .occurrences(label)
+            .filter(|occurrence| declarations || !occurrence.definition)
+            .map(|occurrence| location_json(uri, data, &occurrence.range))
+            .collect();
+        Ok(Json::Array(locations))
+    }
+
+    fn rename(&self, params: &Json) -> Result<Json, Failure> {
+        let (uri, data, offset) = self.document(params)?;
+        let name = params
+            .get("newName")
+            .and_then(Json::as_str)
+            .ok_or_else(|| invalid_params("expected a newName"))?;
+        if !is_plain_label(name) || is_ending(name) {
+            return Err(invalid_params(format!(
+                "{name} can't be a label, use letters, digits and `_` other than END"
+            )));
+        }
+
+        let document = Document::new(data)
+            .ok_or_else(|| invalid_params("the script needs to parse to rename labels"))?;
+        let label = document
+            .at(offset)
+            .map(|occurrence| occurrence.label)
+            .ok_or_else(|| invalid_params("there's no label here"))?;
+        if is_ending(label) {
+            return Err(invalid_params(format!("{label} is an ending, not a label")));
+        }
+
+        let edits = document
+            .occurrences(label)
+            .map(|occurrence| {
+                Json::object([
+                    ("range", range_json(data, &occurrence.range)),
+                    ("newText", Json::from(name)),
+                ])
+            })
+            .collect();
+        Ok(Json::object([(
+            "changes",
+            Json::object([(uri, Json::Array(edits))]),
+        )]))
+    }
+
+    fn completion(&self, params: &Json) -> Result<Json, Failure> {
+        let (_, data, offset) = self.document(params)?;
+
+        // Only labels are completed, between a `(` and the cursor
+        let line = &data[data[..offset].rfind('\n').map_or(0, |idx| idx + 1)..offset];
+        let in_label = line
+            .rfind('(')
+            .is_some_and(|open| !line[open + 1..].contains([')', '"', ' ', '\t']));
+        if !in_label {
+            return Ok(Json::Array(vec![]));
+        }
+
+        let mut labels = prompt_labels(data);
+        labels.dedup_by_key(|(label, _)| *label);
+        let items = labels
+            .into_iter()
+            .map(|(label, text)| {
+                Json::object([
+                    ("label", Json::from(label)),
+                    ("kind", Json::from(COMPLETION_REFERENCE)),
+                    ("detail", Json::from(text)),
+                ])
+            })
+            .chain([Json::object([
+                ("label", Json::from("END")),
+                ("kind", Json::from(COMPLETION_REFERENCE)),
+                ("detail", Json::from("ends the conversation")),
+            ])])
+            .collect();
+        Ok(Json::Array(items))
+    }
+
+    fn hover(&self, params: &Json) -> Result<Json, Failure> {
+        let (_, data, offset) = self.document(params)?;
+        let hover = Document::new(data).and_then(|document| {
+            let occurrence = document.at(offset)?;
+            let prompt = document.prompt(occurrence.label)?;
+            Some(Json::object([
+                (
+                    "contents",
+                    Json::object([
+                        ("kind", Json::from("plaintext")),
+                        ("value", Json::from(prompt.text)),
+                    ]),
+                ),
+                ("range", range_json(data, &occurrence.range)),
+            ]))
+        });
+        Ok(hover.unwrap_or(Json::Null))
+    }
+
+    fn symbols(&self, params: &Json) -> Result<Json, Failure> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+            .ok_or_else(|| invalid_params("expected a textDocument"))?;
+        let data = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| invalid_params(format!("{uri} isn't open")))?;
+
+        let symbols = Document::new(data).map_or(vec![], |document| document.symbols());
+        let symbols = symbols
+            .into_iter()
+            .map(|symbol| {
+                Json::object([
+                    ("name", Json::from(symbol.name)),
+                    ("detail", Json::from(symbol.detail)),
+                    ("kind", Json::from(SYMBOL_KEY)),
+                    ("range", range_json(data, &symbol.range)),
+                    ("selectionRange", range_json(data, &symbol.selection)),
+                ])
+            })
+            .collect();
+        Ok(Json::Array(symbols))
+    }
+
+    /// Answers messages until the client sends `exit` or hangs up. Messages
+    /// that aren't JSON get a parse error, with no id to answer, and ones the
+    /// server fails on an internal error, and the server carries on.
+    pub fn serve(mut self, reader: &mut impl BufRead, writer: &mut impl Write) -> io::Result<()> {
+        while let Some(body) = read_body(reader)? {
+            let replies = match parse_body(&body) {
+                Ok(message) => self.handle_unwinding(&message),
+                Err(message) => {
+                    let failure = Failure {
+                        code: PARSE_ERROR,
+                        message,
+                    };
+                    vec![response(Json::Null, Err(failure))]
+                }
+            };
+            for reply in replies {
+                write_message(writer, &reply)?;
+            }
+            if self.exited {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use prompter::json::Json;
+
+    use super::{
+        rpc::{read_message, write_message},
+        Server,
+    };
+
+    const URI: &str = "file:///coin.pg";
+    const SCRIPT: &str = "> (START) \"Heads or tails?\" [random WIN 3, LOSE]\n\
+                          < \"Heads\"\n\
+                          < (LOSE) \"Lose on purpose\"\n\
+                          > (WIN) \"You won!\" -> END\n\
+                          > (LOSE) \"You lost, {name}!\"\n";
+
+    fn open(server: &mut Server, text: &str) -> Json {
+        let message = Json::object([
+            ("jsonrpc", Json::from("2.0")),
+            ("method", Json::from("textDocument/didOpen")),
+            (
+                "params",
+                Json::object([(
+                    "textDocument",
+                    Json::object([("uri", Json::from(URI)), ("text", Json::from(text))]),
+                )]),
+            ),
+        ]);
+        let mut replies = server.handle(&message);
+        assert_eq!(replies.len(), 1);
+        replies.remove(0)
+    }
+
+    // Sends a request at a position in the document, giving its result
+    fn request(
+        server: &mut Server,
+        method: &str,
+        line: usize,
+        character: usize,
+        extra: &str,
+    ) -> Json {
+        let message = format!(
+            r#"{{"jsonrpc":"2.0","id":7,"method":"{method}","params":{{"textDocument":{{"uri":"{URI}"}},"position":{{"line":{line},"character":{character}}}{extra}}}}}"#
+        );
+        let reply = server.handle(&Json::parse(&message).unwrap()).remove(0);
+        assert_eq!(reply.get("id").unwrap().as_i64(), Some(7));
+        reply
+            .get("result")
+            .cloned()
+            .unwrap_or_else(|| reply.get("error").unwrap().clone())
+    }
+
+    fn start(json: &Json) -> (i64, i64) {
+        let start = json.get("range").unwrap().get("start").unwrap();
+        (
+            start.get("line").unwrap().as_i64().unwrap(),
+            start.get("character").unwrap().as_i64().unwrap(),
+        )
+    }
+
+    #[test]
+    fn publishes_diagnostics() {
+        let mut server = Server::new();
+        let init = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"initializationOptions":{"variables":["name"]}}}"#;
+        let reply = server.handle(&Json::parse(init).unwrap()).remove(0);
+        let capabilities = reply.get("result").unwrap().get("capabilities").unwrap();
+        assert_eq!(capabilities.get("renameProvider"), Some(&Json::Bool(true)));
+
+        let published = open(&mut server, SCRIPT);
+        let params = published.get("params").unwrap();
+        assert_eq!(params.get("diagnostics").unwrap().to_string(), "[]");
+
+        let published = open(&mut server, "> (START) \"Hi\"\n< \"Hello\" [nope]\n");
+        let diagnostic = &published
+            .get("params")
+            .unwrap()
+            .get("diagnostics")
+            .unwrap()
+            .as_array()
+            .unwrap()[0];
+        assert_eq!(
+            diagnostic.to_string(),
+            r#"{"range":{"start":{"line":1,"character":10},"end":{"line":1,"character":16}},"severity":1,"source":"promptgen","message":"unknown directive"}"#
+        );
+
+        let exit = server.handle(&Json::parse(r#"{"jsonrpc":"2.0","method":"exit"}"#).unwrap());
+        assert!(exit.is_empty() && server.exited());
+    }
+
+    #[test]
+    fn publishes_label_problems_in_any_text() {
+        let mut server = Server::new();
+        let published = open(
+            &mut server,
+            "> (START) \"Héllo\"\nZoë was here\n< (TYPO) \"Bye\"\n> (START) \"Again\"\n",
+        );
+        let diagnostics = published.get("params").unwrap().get("diagnostics").unwrap();
+        let found: Vec<_> = diagnostics
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|diagnostic| {
+                let message = diagnostic.get("message").unwrap().as_str().unwrap();
+                (start(diagnostic), message)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ((2, 3), "no prompt is labelled `TYPO`"),
+                ((3, 3), "another prompt is already labelled `START`"),
+            ]
+        );
+    }
+
+    #[test]
+    fn navigates_between_labels() {
+        let mut server = Server::new();
+        open(&mut server, SCRIPT);
+
+        // From `LOSE` in `[random ...]` to the prompt labelled with it
+        let definition = request(&mut server, "textDocument/definition", 0, 45, "");
+        assert_eq!(definition.get("uri").unwrap().as_str(), Some(URI));
+        assert_eq!(start(&definition), (4, 3));
+        let nowhere = request(&mut server, "textDocument/definition", 1, 4, "");
+        assert_eq!(nowhere, Json::Null);
+
+        let references = request(&mut server, "textDocument/references", 4, 4, "");
+        let starts: Vec<_> = references.as_array().unwrap().iter().map(start).collect();
+        assert_eq!(starts, [(0, 43), (2, 3), (4, 3)]);
+        let context = r#","context":{"includeDeclaration":false}"#;
+        let references = request(&mut server, "textDocument/references", 4, 4, context);
+        assert_eq!(references.as_array().unwrap().len(), 2);
+
+        let hover = request(&mut server, "textDocument/hover", 0, 38, "");
+        let contents = hover.get("contents").unwrap();
+        assert_eq!(contents.get("value").unwrap().as_str(), Some("You won!"));
+
+        let symbols = request(&mut server, "textDocument/documentSymbol", 0, 0, "");
+        let names: Vec<_> = symbols
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|symbol| symbol.get("name").unwrap().as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["START", "WIN", "LOSE"]);
+    }
+
+    #[test]
+    fn renames_labels() {
+        let mut server = Server::new();
+        open(&mut server, SCRIPT);
+
+        let rename = request(
+            &mut server,
+            "textDocument/rename",
+            2,
+            4,
+            r#","newName":"LOST""#,
+        );
+        let edits = rename
+            .get("changes")
+            .unwrap()
+            .get(URI)
+            .unwrap()
+            .as_array()
+            .unwrap();
+        assert_eq!(edits.len(), 3);
+        assert_eq!(edits[0].get("newText").unwrap().as_str(), Some("LOST"));
+
+        let bad = request(
+            &mut server,
+            "textDocument/rename",
+            2,
+            4,
+            r#","newName":"NO WAY""#,
+        );
+        assert_eq!(bad.get("code").unwrap().as_i64(), Some(-32602));
+        let ending = request(
+            &mut server,
+            "textDocument/rename",
+            3,
+            22,
+            r#","newName":"DONE""#,
+        );
+        assert_eq!(ending.get("code").unwrap().as_i64(), Some(-32602));
+    }
+
+    #[test]
+    fn completes_labels_inside_parentheses() {
+        let mut server = Server::new();
+        open(&mut server, "> (START) \"Hi\"\n< (W\n> (WAVE) \"Bye\"\n");
+
+        let items = request(&mut server, "textDocument/completion", 1, 4, "");
+        let labels: Vec<_> = items
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item.get("label").unwrap().as_str().unwrap())
+            .collect();
+        assert_eq!(labels, ["START", "WAVE", "END"]);
+
+        let outside = request(&mut server, "textDocument/completion", 0, 12, "");
+        assert_eq!(outside.to_string(), "[]");
+    }
+
+    #[test]
+    fn answers_messages_that_arent_json() {
+        let mut input = b"Content-Length: 8\r\n\r\n{\"id\": 1".to_vec();
+        for message in [
+            r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#,
+            r#"{"jsonrpc":"2.0","method":"exit"}"#,
+        ] {
+            write_message(&mut input, &Json::parse(message).unwrap()).unwrap();
+        }
+        let mut output = vec![];
+        Server::new()
+            .serve(&mut BufReader::new(&input[..]), &mut output)
+            .unwrap();
+
+        let mut output = BufReader::new(&output[..]);
+        let broken = read_message(&mut output).unwrap().unwrap();
+        assert_eq!(broken.get("id"), Some(&Json::Null));
+        let error = broken.get("error").unwrap();
+        assert_eq!(error.get("code").unwrap().as_i64(), Some(-32700));
+        let shutdown = read_message(&mut output).unwrap().unwrap();
+        assert_eq!(shutdown.get("id").unwrap().as_i64(), Some(2));
+        assert_eq!(shutdown.get("result"), Some(&Json::Null));
+        assert_eq!(read_message(&mut output).unwrap(), None);
+    }
+}
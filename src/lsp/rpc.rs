@@ -0,0 +1,101 @@
+use std::io::{self, BufRead, Write};
+
+use prompter::json::Json;
+
+/// Largest message taken, whole documents are sent on every change.
+const MAX_MESSAGE: usize = 16 * 1024 * 1024;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads the body of one `Content-Length` framed message, `None` once the
+/// client has closed the stream.
+pub fn read_body(reader: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut line = String::new();
+    let mut length = None;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return match length {
+                None => Ok(None),
+                Some(_) => Err(invalid("headers end unexpectedly")),
+            };
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            // Blank lines between messages aren't headers
+            match length {
+                Some(_) => break,
+                None => continue,
+            }
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                let value = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid("invalid content length"))?;
+                length = Some(value);
+            }
+        }
+    }
+
+    let length = length.unwrap_or_default();
+    if length > MAX_MESSAGE {
+        return Err(io::Error::new(
+            io::ErrorKind::OutOfMemory,
+            "message too large",
+        ));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+/// The JSON in a message's body, or why there isn't any.
+pub fn parse_body(body: &[u8]) -> Result<Json, String> {
+    let body = std::str::from_utf8(body).map_err(|_| "message isn't UTF-8".to_string())?;
+    Json::parse(body).map_err(|err| format!("message isn't JSON: {err}"))
+}
+
+/// Reads one `Content-Length` framed JSON-RPC message, `None` once the
+/// client has closed the stream.
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Json>> {
+    match read_body(reader)? {
+        Some(body) => parse_body(&body).map(Some).map_err(|err| invalid(&err)),
+        None => Ok(None),
+    }
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use prompter::json::Json;
+
+    use super::{read_message, write_message};
+
+    #[test]
+    fn frames_messages() {
+        let message = Json::object([("jsonrpc", Json::from("2.0")), ("id", Json::from(1i64))]);
+        let mut out = vec![];
+        write_message(&mut out, &message).unwrap();
+        write_message(&mut out, &message).unwrap();
+        assert!(out.starts_with(b"Content-Length: 24\r\n\r\n{\"jsonrpc\""));
+
+        let mut reader = BufReader::new(&out[..]);
+        assert_eq!(read_message(&mut reader).unwrap(), Some(message.clone()));
+        assert_eq!(read_message(&mut reader).unwrap(), Some(message));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+
+        let broken = b"Content-Length: 2\r\n\r\n{x";
+        assert!(read_message(&mut BufReader::new(&broken[..])).is_err());
+    }
+}
@@ -0,0 +1,47 @@
+use std::{env, process::ExitCode};
+
+mod cli;
+
+const USAGE: &str = "\
+usage: promptgen <command> [options]
+
+commands:
+  run <script> [--start LABEL]    play a script in the terminal
+  check <scripts...>              parse and validate scripts, directories or globs
+        [--format human|json|sarif] [--deny-warnings] [--vars a,b]
+  fmt [scripts...] [--check]      rewrite scripts in canonical style, stdin to stdout
+                                  without scripts or with -
+  graph <script>                  draw the conversation as DOT or Mermaid
+        [--format dot|mermaid] [--max-text N] [--labels-only] [--unreachable]
+  export <script> -o <page.html>  save a web page that plays the script offline
+        [--title TEXT] [--theme light|dark] [--accent COLOR] [--font FAMILY]
+        [--image LABEL=path]...
+  test <tests...>                 play the scenarios in .pgtest files, files or
+                                  directories, and report the ones that fail
+  serve --stdio                   play scripts for another process, JSON lines
+                                  on stdin and stdout";
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+
+    let result = match args.next().as_deref() {
+        Some("run") => cli::run::main(args),
+        Some("check") => cli::check::main(args),
+        Some("fmt") => cli::fmt::main(args),
+        Some("graph") => cli::graph::main(args),
+        Some("export") => cli::export::main(args),
+        Some("serve") => cli::serve::main(args),
+        Some("test") => cli::test::main(args),
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Some(command) => Err(format!("unknown command {command}\n\n{USAGE}")),
+        None => Err(USAGE.to_string()),
+    };
+
+    result.unwrap_or_else(|err| {
+        eprintln!("promptgen: {err}");
+        ExitCode::FAILURE
+    })
+}
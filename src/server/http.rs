@@ -0,0 +1,191 @@
+use std::io::{self, BufRead, Read, Write};
+
+use prompter::json::Json;
+
+/// Largest request body taken, scripts are loaded up front so answers are
+/// all that's ever sent.
+const MAX_BODY: usize = 64 * 1024;
+
+/// Largest request line and headers taken, all together.
+const MAX_HEAD: usize = 8 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Option<Json>,
+}
+
+impl Response {
+    pub fn json(status: u16, body: Json) -> Self {
+        Self {
+            status,
+            body: Some(body),
+        }
+    }
+
+    pub fn empty(status: u16) -> Self {
+        Self { status, body: None }
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn too_large(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::OutOfMemory, message)
+}
+
+// Reads a line of the request line and headers, `left` being how much more
+// of them is taken
+fn head_line(reader: &mut impl BufRead, line: &mut String, left: &mut usize) -> io::Result<usize> {
+    if *left == 0 {
+        return Err(too_large("request headers are too large"));
+    }
+    line.clear();
+    let read = Read::take(&mut *reader, *left as u64).read_line(line)?;
+    *left -= read;
+    match *left == 0 && !line.ends_with('\n') {
+        true => Err(too_large("request headers are too large")),
+        false => Ok(read),
+    }
+}
+
+/// Reads one HTTP/1.1 request, `None` when the connection closed before
+/// sending anything. Requests too large to take are `OutOfMemory` errors.
+pub fn read_request(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+    let mut line = String::new();
+    let mut left = MAX_HEAD;
+    if head_line(reader, &mut line, &mut left)? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(invalid("malformed request line"));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut length = 0;
+    loop {
+        if head_line(reader, &mut line, &mut left)? == 0 {
+            return Err(invalid("headers end unexpectedly"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid("invalid content length"))?;
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return Err(too_large("request body is too large"));
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    let body = String::from_utf8(body).map_err(|_| invalid("body isn't UTF-8"))?;
+
+    Ok(Some(Request { method, path, body }))
+}
+
+pub fn write_response(writer: &mut impl Write, response: &Response) -> io::Result<()> {
+    let body = response
+        .body
+        .as_ref()
+        .map_or(String::new(), |body| body.to_string());
+
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason(response.status),
+        body.len()
+    )?;
+    if response.body.is_some() {
+        write!(writer, "Content-Type: application/json\r\n")?;
+    }
+    write!(writer, "\r\n{body}")?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, ErrorKind};
+
+    use prompter::json::Json;
+
+    use super::{read_request, write_response, Request, Response};
+
+    #[test]
+    fn reads_requests() {
+        let data = "POST /sessions HTTP/1.1\r\nHost: localhost\r\ncontent-length: 17\r\n\r\n\
+                    {\"script\":\"card\"}";
+        let request = read_request(&mut BufReader::new(data.as_bytes())).unwrap();
+        assert_eq!(
+            request,
+            Some(Request {
+                method: "POST".to_string(),
+                path: "/sessions".to_string(),
+                body: "{\"script\":\"card\"}".to_string(),
+            })
+        );
+
+        assert_eq!(read_request(&mut BufReader::new(&b""[..])).unwrap(), None);
+        assert!(read_request(&mut BufReader::new(&b"GET\r\n\r\n"[..])).is_err());
+    }
+
+    #[test]
+    fn caps_headers() {
+        let read = |data: String| read_request(&mut BufReader::new(data.as_bytes()));
+        let header = format!("X-Pad: {}\r\n", "a".repeat(100));
+        let data = |headers| format!("GET / HTTP/1.1\r\n{}\r\n", header.repeat(headers));
+
+        assert!(read(data(70)).unwrap().is_some());
+        let err = read(data(90)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::OutOfMemory);
+        assert_eq!(err.to_string(), "request headers are too large");
+        let err = read(format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(10_000))).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::OutOfMemory);
+    }
+
+    #[test]
+    fn writes_responses() {
+        let mut out = vec![];
+        let response = Response::json(201, Json::object([("id", Json::from("a"))]));
+        write_response(&mut out, &response).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "HTTP/1.1 201 Created\r\nContent-Length: 10\r\nConnection: close\r\n\
+             Content-Type: application/json\r\n\r\n{\"id\":\"a\"}"
+        );
+    }
+}
@@ -0,0 +1,514 @@
+//! A small HTTP server playing conversations for web apps, speaking JSON on
+//! localhost. Scripts are loaded when it starts, and sessions are kept in
+//! memory until they've gone unused for a while. Session ids are the only
+//! thing keeping sessions apart, so they're random and can't be guessed from
+//! each other.
+//!
+//! - `GET /scripts` lists the scripts sessions can be started with.
+//! - `POST /sessions` with `{"script": "card"}` starts a session, optionally
+//!   at `"start": "LABEL"` and with host `"variables": {"name": "Ada"}`.
+//! - `GET /sessions/{id}` gives the current prompt.
+//! - `POST /sessions/{id}/answer` answers it, see `session::answer`.
+//! - `GET /sessions/{id}/transcript` gives everything shown and answered.
+//! - `DELETE /sessions/{id}` ends a session early.
+//!
+//! Failures come back as `{"error": {"code": ..., "message": ...}}`.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    hash::{BuildHasher, Hasher, RandomState},
+    io::{self, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard, TryLockError,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use prompter::{json::Json, script::Script, Prompter};
+
+use crate::session::{answer, error_json, load, prompt_json, start, LoadErr};
+
+pub mod http;
+
+use http::{read_request, write_response, Request, Response};
+
+/// How long sessions are kept without being used, unless told otherwise.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// How long to wait on a client that's gone quiet mid request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most connections served at once, any more are turned away as busy.
+const MAX_CONNECTIONS: usize = 64;
+
+/// Most sessions kept at once, no more are started until some end or expire.
+const MAX_SESSIONS: usize = 10_000;
+
+// One of the `MAX_CONNECTIONS`, given back when the connection is done with
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    fn take(open: &Arc<AtomicUsize>) -> Option<Slot> {
+        open.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| {
+            (open < MAX_CONNECTIONS).then_some(open + 1)
+        })
+        .ok()?;
+        Some(Slot(Arc::clone(open)))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+struct Session {
+    prompter: Prompter<'static>,
+    used: Instant,
+}
+
+pub struct Server {
+    scripts: HashMap<String, Arc<Script<'static>>>,
+    /// Each session has its own lock, so answering one doesn't hold up
+    /// requests for the others.
+    sessions: Mutex<HashMap<String, Arc<Mutex<Session>>>>,
+    max_sessions: usize,
+    ttl: Duration,
+    ids_issued: AtomicU64,
+}
+
+// A request that panicked holding a lock left nothing half done
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn error(status: u16, code: &str, message: impl Display) -> Response {
+    Response::json(status, Json::object([("error", error_json(code, message))]))
+}
+
+// An error from `session`, requests that make no sense are the client's
+// mistake, anything else is turned down by the script
+fn failure(err: Json) -> Response {
+    let status = match err.get("code").and_then(Json::as_str) {
+        Some("bad_request") => 400,
+        _ => 422,
+    };
+    Response::json(status, Json::object([("error", err)]))
+}
+
+fn session_json(id: &str, prompter: &Prompter) -> Json {
+    Json::object([("id", Json::from(id)), ("prompt", prompt_json(prompter))])
+}
+
+impl Server {
+    /// A server without scripts, forgetting sessions unused for `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            scripts: HashMap::new(),
+            sessions: Mutex::new(HashMap::new()),
+            max_sessions: MAX_SESSIONS,
+            ttl,
+            ids_issued: AtomicU64::new(0),
+        }
+    }
+
+    /// Parses a script and makes it available as `name`, see
+    /// `session::load`.
+    pub fn load(&mut self, name: impl Into<String>, data: String) -> Result<(), LoadErr> {
+        self.scripts.insert(name.into(), Arc::new(load(data)?));
+        Ok(())
+    }
+
+    // 128 bits hashed with secret random keys, every half from a new
+    // `RandomState` so no output says anything about the next
+    fn new_id(&self) -> String {
+        let issued = self.ids_issued.fetch_add(1, Ordering::Relaxed);
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let half = |which: u8| {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u64(issued);
+            hasher.write_u128(now.as_nanos());
+            hasher.write_u8(which);
+            hasher.finish()
+        };
+        format!("{:016x}{:016x}", half(0), half(1))
+    }
+
+    /// Answers one request. Expired sessions are swept away first, ones busy
+    /// with another request are in use.
+    pub fn handle(&self, request: &Request) -> Response {
+        lock(&self.sessions).retain(|_, session| match session.try_lock() {
+            Ok(session) => session.used.elapsed() < self.ttl,
+            Err(TryLockError::Poisoned(err)) => err.into_inner().used.elapsed() < self.ttl,
+            Err(TryLockError::WouldBlock) => true,
+        });
+
+        let path: Vec<&str> = request
+            .path
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .collect();
+        let method = request.method.as_str();
+
+        let body = match request.body.trim() {
+            "" => Json::Object(vec![]),
+            body => match Json::parse(body) {
+                Ok(body) => body,
+                Err(err) => return error(400, "bad_request", format!("invalid JSON: {err}")),
+            },
+        };
+
+        match (method, path.as_slice()) {
+            ("GET", ["scripts"]) => {
+                let mut names: Vec<&String> = self.scripts.keys().collect();
+                names.sort();
+                let names = names.into_iter().map(|name| Json::from(name.as_str()));
+                Response::json(
+                    200,
+                    Json::object([("scripts", Json::Array(names.collect()))]),
+                )
+            }
+            ("POST", ["sessions"]) => match self.start(&body) {
+                Ok(prompter) => {
+                    let mut sessions = lock(&self.sessions);
+                    if sessions.len() >= self.max_sessions {
+                        return error(503, "busy", "too many sessions, try again shortly");
+                    }
+                    let id = self.new_id();
+                    let response = Response::json(201, session_json(&id, &prompter));
+                    let session = Session {
+                        prompter,
+                        used: Instant::now(),
+                    };
+                    sessions.insert(id, Arc::new(Mutex::new(session)));
+                    response
+                }
+                Err(response) => response,
+            },
+            (_, ["scripts"] | ["sessions"]) => {
+                error(405, "method_not_allowed", "method not allowed")
+            }
+            (_, ["sessions", id, rest @ ..]) => self.session_request(request, id, rest, &body),
+            _ => error(404, "not_found", format!("nothing at {}", request.path)),
+        }
+    }
+
+    // A request under `/sessions/{id}`. The session is only locked while
+    // it's being used, so others are answered meanwhile.
+    fn session_request(&self, request: &Request, id: &str, rest: &[&str], body: &Json) -> Response {
+        let Some(session) = lock(&self.sessions).get(id).cloned() else {
+            let message = format!("no session {id}, it may have expired");
+            return error(404, "no_session", message);
+        };
+
+        match (request.method.as_str(), rest) {
+            ("GET", []) => {
+                let mut session = lock(&session);
+                session.used = Instant::now();
+                Response::json(200, session_json(id, &session.prompter))
+            }
+            ("DELETE", []) => {
+                lock(&self.sessions).remove(id);
+                Response::empty(204)
+            }
+            ("POST", ["answer"]) => {
+                let mut session = lock(&session);
+                session.used = Instant::now();
+                match answer(session.prompter.clone(), body) {
+                    Ok(prompter) => {
+                        session.prompter = prompter;
+                        Response::json(200, session_json(id, &session.prompter))
+                    }
+                    Err(err) => failure(err),
+                }
+            }
+            ("GET", ["transcript"]) => {
+                let mut session = lock(&session);
+                session.used = Instant::now();
+                let entries = session
+                    .prompter
+                    .transcript()
+                    .expect("sessions keep a transcript")
+                    .entries()
+                    .iter()
+                    .map(|entry| entry.to_json());
+                Response::json(
+                    200,
+                    Json::object([
+                        ("id", Json::from(id)),
+                        ("transcript", Json::Array(entries.collect())),
+                    ]),
+                )
+            }
+            (_, [] | ["answer" | "transcript"]) => {
+                error(405, "method_not_allowed", "method not allowed")
+            }
+            _ => error(404, "not_found", format!("nothing at {}", request.path)),
+        }
+    }
+
+    // A new conversation from a `POST /sessions` body
+    fn start(&self, body: &Json) -> Result<Prompter<'static>, Response> {
+        let name = body
+            .get("script")
+            .and_then(Json::as_str)
+            .ok_or_else(|| error(400, "bad_request", "`script` names the script to start"))?;
+        let script = self
+            .scripts
+            .get(name)
+            .cloned()
+            .ok_or_else(|| error(404, "no_script", format!("no script called {name}")))?;
+
+        let prompter = start(script, body).map_err(failure)?;
+        Ok(prompter.with_transcript())
+    }
+
+    fn connection(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream;
+
+        let response = match read_request(&mut reader) {
+            Ok(Some(request)) => self.handle(&request),
+            Ok(None) => return Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::OutOfMemory => error(413, "too_large", err),
+            Err(err) => error(400, "bad_request", err),
+        };
+        write_response(&mut writer, &response)
+    }
+
+    /// Serves requests until the listener fails, each connection on its own
+    /// thread, up to `MAX_CONNECTIONS` at once.
+    pub fn serve(self, listener: TcpListener) -> io::Result<()> {
+        let server = Arc::new(self);
+        let open = Arc::new(AtomicUsize::new(0));
+        for stream in listener.incoming() {
+            let mut stream = stream?;
+            let Some(slot) = Slot::take(&open) else {
+                let busy = error(503, "busy", "too many connections, try again shortly");
+                let _ = write_response(&mut stream, &busy);
+                continue;
+            };
+            let server = Arc::clone(&server);
+            thread::spawn(move || {
+                // A client hanging up early only affects that client
+                let _ = server.connection(stream);
+                drop(slot);
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        fs::read_to_string,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use prompter::json::Json;
+
+    use super::{http::Request, lock, Server, Slot, MAX_CONNECTIONS};
+    use crate::session::LoadErr;
+
+    fn request(server: &Server, method: &str, path: &str, body: &str) -> (u16, Json) {
+        let response = server.handle(&Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            body: body.to_string(),
+        });
+        (response.status, response.body.unwrap_or(Json::Null))
+    }
+
+    fn server(ttl: Duration) -> Server {
+        let mut server = Server::new(ttl);
+        let data = read_to_string("./prompter/input_prompt.txt").unwrap();
+        server.load("card", data).unwrap();
+        server
+    }
+
+    #[test]
+    fn plays_sessions() {
+        let server = server(Duration::from_secs(60));
+        let (status, body) = request(&server, "GET", "/scripts", "");
+        assert_eq!(
+            (status, body.to_string().as_str()),
+            (200, r#"{"scripts":["card"]}"#)
+        );
+
+        let (status, body) = request(&server, "POST", "/sessions", r#"{"script":"card"}"#);
+        assert_eq!(status, 201);
+        let id = body.get("id").unwrap().as_str().unwrap().to_string();
+        let prompt = body.get("prompt").unwrap();
+        assert_eq!(
+            prompt.get("text").unwrap().as_str(),
+            Some("What's your name?")
+        );
+
+        let path = format!("/sessions/{id}/answer");
+        let (status, body) = request(&server, "POST", &path, r#"{"text":"Ada"}"#);
+        assert_eq!(status, 200);
+        let text = body.get("prompt").unwrap().get("text").unwrap();
+        assert_eq!(
+            text.as_str(),
+            Some("Happy birthday, Ada! How old are you now?")
+        );
+
+        let (status, body) = request(&server, "POST", &path, r#"{"text":"200"}"#);
+        assert_eq!(status, 422);
+        let error = body.get("error").unwrap();
+        assert_eq!(error.get("code").unwrap().as_str(), Some("invalid_input"));
+        assert_eq!(
+            error.get("message").unwrap().as_str(),
+            Some("Come on Ada, between 1 and 120 please.")
+        );
+
+        let (status, body) = request(&server, "GET", &format!("/sessions/{id}/transcript"), "");
+        assert_eq!(status, 200);
+        assert_eq!(body.get("transcript").unwrap().as_array().unwrap().len(), 3);
+
+        assert_eq!(
+            request(&server, "DELETE", &format!("/sessions/{id}"), "").0,
+            204
+        );
+        assert_eq!(
+            request(&server, "GET", &format!("/sessions/{id}"), "").0,
+            404
+        );
+    }
+
+    #[test]
+    fn turns_down_bad_requests() {
+        let server = server(Duration::from_secs(60));
+
+        assert_eq!(request(&server, "POST", "/sessions", "{").0, 400);
+        assert_eq!(
+            request(&server, "POST", "/sessions", r#"{"script":"x"}"#).0,
+            404
+        );
+        let body = r#"{"script":"card","start":"NOPE"}"#;
+        assert_eq!(request(&server, "POST", "/sessions", body).0, 422);
+        assert_eq!(request(&server, "PUT", "/scripts", "").0, 405);
+        assert_eq!(request(&server, "GET", "/nowhere", "").0, 404);
+    }
+
+    #[test]
+    fn starts_with_variables_and_labels() {
+        let server = server(Duration::from_secs(60));
+        let body = r#"{"script":"card","start":"GREET","variables":{"name":"Grace"}}"#;
+        let (status, body) = request(&server, "POST", "/sessions", body);
+
+        assert_eq!(status, 201);
+        let text = body.get("prompt").unwrap().get("text").unwrap();
+        assert_eq!(
+            text.as_str(),
+            Some("Happy birthday, Grace! How old are you now?")
+        );
+    }
+
+    #[test]
+    fn forgets_unused_sessions() {
+        let server = server(Duration::from_millis(20));
+        let (_, body) = request(&server, "POST", "/sessions", r#"{"script":"card"}"#);
+        let id = body.get("id").unwrap().as_str().unwrap().to_string();
+
+        thread::sleep(Duration::from_millis(40));
+        let (status, body) = request(&server, "GET", &format!("/sessions/{id}"), "");
+        assert_eq!(status, 404);
+        let code = body.get("error").unwrap().get("code").unwrap();
+        assert_eq!(code.as_str(), Some("no_session"));
+    }
+
+    #[test]
+    fn serves_other_sessions_while_one_is_busy() {
+        let server = server(Duration::from_millis(20));
+        let (_, body) = request(&server, "POST", "/sessions", r#"{"script":"card"}"#);
+        let busy = body.get("id").unwrap().as_str().unwrap().to_string();
+        let (_, body) = request(&server, "POST", "/sessions", r#"{"script":"card"}"#);
+        let other = body.get("id").unwrap().as_str().unwrap().to_string();
+
+        // As if a request for `busy` were still being answered
+        let session = lock(&server.sessions).get(&busy).cloned().unwrap();
+        let guard = lock(&session);
+        let path = format!("/sessions/{other}/answer");
+        assert_eq!(request(&server, "POST", &path, r#"{"text":"Ada"}"#).0, 200);
+
+        // Sessions in use aren't expired from under the request
+        thread::sleep(Duration::from_millis(40));
+        assert_eq!(request(&server, "GET", "/scripts", "").0, 200);
+        assert!(lock(&server.sessions).contains_key(&busy));
+        assert!(!lock(&server.sessions).contains_key(&other));
+        drop(guard);
+    }
+
+    #[test]
+    fn caps_connections() {
+        let open = Arc::new(AtomicUsize::new(0));
+        let mut slots: Vec<_> = (0..MAX_CONNECTIONS)
+            .map(|_| Slot::take(&open).unwrap())
+            .collect();
+        assert!(Slot::take(&open).is_none());
+
+        slots.pop();
+        assert!(Slot::take(&open).is_some());
+        assert_eq!(open.load(Ordering::SeqCst), MAX_CONNECTIONS - 1);
+    }
+
+    #[test]
+    fn caps_sessions() {
+        let mut server = server(Duration::from_secs(60));
+        server.max_sessions = 2;
+        let start = |server: &Server| request(server, "POST", "/sessions", r#"{"script":"card"}"#);
+
+        let (_, first) = start(&server);
+        assert_eq!(start(&server).0, 201);
+        let (status, body) = start(&server);
+        assert_eq!(status, 503);
+        let code = body.get("error").unwrap().get("code").unwrap();
+        assert_eq!(code.as_str(), Some("busy"));
+
+        let id = first.get("id").unwrap().as_str().unwrap();
+        request(&server, "DELETE", &format!("/sessions/{id}"), "");
+        assert_eq!(start(&server).0, 201);
+    }
+
+    #[test]
+    fn makes_ids_apart_from_each_other() {
+        let ids: HashSet<_> = (0..1000)
+            .map(|_| Server::new(Duration::from_secs(1)).new_id())
+            .chain((0..1000).map({
+                let server = Server::new(Duration::from_secs(1));
+                move |_| server.new_id()
+            }))
+            .collect();
+        assert_eq!(ids.len(), 2000);
+        assert!(ids
+            .iter()
+            .all(|id| id.len() == 32 && id.bytes().all(|b| b.is_ascii_hexdigit())));
+    }
+
+    #[test]
+    fn refuses_broken_scripts() {
+        let mut server = Server::new(Duration::from_secs(1));
+        let err = server.load("broken", "> \"Hi".to_string()).unwrap_err();
+        assert!(matches!(err, LoadErr::Syntax { line: 1, .. }));
+        assert_eq!(server.load("empty", String::new()), Err(LoadErr::NoPrompts));
+    }
+}
@@ -0,0 +1,312 @@
+//! A conversation as JSON, for hosts that drive promptgen from another
+//! process or language rather than linking against `Prompter`.
+
+use std::{
+    fmt::{self, Display},
+    sync::Arc,
+};
+
+use lexer::{parse_located, SyntaxError};
+use prompter::{
+    frontend::{apply, Reply},
+    json::Json,
+    owned::OwnedScript,
+    render::RenderedPrompt,
+    script::Script,
+    select::ResponseId,
+    variables::Value,
+    Ending, Prompter, PrompterErr,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadErr {
+    /// The script doesn't parse, at the one-based line and column.
+    Syntax {
+        error: SyntaxError,
+        line: usize,
+        column: usize,
+    },
+    NoPrompts,
+}
+
+impl Display for LoadErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadErr::Syntax {
+                error,
+                line,
+                column,
+            } => write!(f, "{line}:{column}: {error}"),
+            LoadErr::NoPrompts => write!(f, "no prompts to start from"),
+        }
+    }
+}
+
+// The script in `data`, or where it fails to parse
+fn parse_script(data: &str) -> Result<Script<'_>, LoadErr> {
+    let prompts = parse_located(data).map_err(|error| {
+        let (line, column) = error.line_and_column(data);
+        LoadErr::Syntax {
+            error,
+            line,
+            column,
+        }
+    })?;
+    Script::new(prompts).map_err(|_| LoadErr::NoPrompts)
+}
+
+/// Parses a script for sessions that outlive the caller's copy of it. The
+/// text is kept for the rest of the process, so load scripts once rather
+/// than per session, or use `load_owned` for scripts that come and go.
+pub fn load(data: String) -> Result<Script<'static>, LoadErr> {
+    // Checked before keeping the text, broken scripts are let go
+    parse_script(&data)?;
+
+    let data: &'static str = Box::leak(data.into_boxed_str());
+    Ok(parse_script(data).expect("the script parses"))
+}
+
+/// Parses a script that keeps its own text, freed once the script and the
+/// sessions started from it are dropped.
+pub fn load_owned(data: String) -> Result<OwnedScript, LoadErr> {
+    OwnedScript::new(data, parse_script)
+}
+
+/// An error as `{"code": ..., "message": ...}`, codes being stable for hosts
+/// to match on.
+pub fn error_json(code: &str, message: impl Display) -> Json {
+    Json::object([
+        ("code", Json::from(code)),
+        ("message", Json::from(message.to_string())),
+    ])
+}
+
+/// A prompter error, with the input check's details when typed in text was
+/// turned down.
+pub fn prompter_error_json(err: &PrompterErr) -> Json {
+    let mut error = error_json(err.code(), err);
+
+    if let (PrompterErr::InvalidInput(invalid), Json::Object(fields)) = (err, &mut error) {
+        fields.push((
+            "mismatch".to_string(),
+            Json::from(invalid.mismatch.to_string()),
+        ));
+        fields.push(("retry".to_string(), Json::from(invalid.retry.clone())));
+    }
+    error
+}
+
+/// A script that didn't load, with where it went wrong for syntax errors.
+pub fn load_error_json(err: &LoadErr) -> Json {
+    match err {
+        LoadErr::Syntax {
+            error,
+            line,
+            column,
+        } => Json::object([
+            ("code", Json::from("syntax")),
+            ("message", Json::from(error.message.as_str())),
+            ("offset", Json::from(error.offset)),
+            ("line", Json::from(*line)),
+            ("column", Json::from(*column)),
+        ]),
+        LoadErr::NoPrompts => error_json("no_prompts", err),
+    }
+}
+
+pub fn ending_json(ending: &Ending) -> Json {
+    match ending {
+        Ending::Completed { outcome } => Json::object([
+            ("kind", Json::from(ending.kind())),
+            ("outcome", Json::from(*outcome)),
+        ]),
+        Ending::UnresolvedLabel(label) => Json::object([
+            ("kind", Json::from(ending.kind())),
+            ("label", Json::from(*label)),
+        ]),
+        Ending::DeadEnd | Ending::Looped => Json::object([("kind", Json::from(ending.kind()))]),
+    }
+}
+
+/// Where the conversation is: the current prompt, rendered, with its
+/// responses numbered in the order they're offered, and the ending once
+/// there is one. Statements are moved on from with `{"advance": true}`.
+pub fn prompt_json(prompter: &Prompter) -> Json {
+    let prompt = prompter.render_next();
+    let finished = prompter.is_finished();
+    let responses = match finished {
+        true => vec![],
+        false => prompt
+            .responses
+            .iter()
+            .enumerate()
+            .map(|(index, response)| {
+                Json::object([
+                    ("index", Json::from(index)),
+                    ("text", Json::from(response.text.as_str())),
+                    ("input", Json::from(response.response.input.is_some())),
+                    ("default", Json::from(response.response.default)),
+                ])
+            })
+            .collect(),
+    };
+    let multi = prompt.multi.filter(|_| !finished).map(|selection| {
+        Json::object([
+            ("min", Json::from(selection.min)),
+            ("max", Json::from(selection.max)),
+        ])
+    });
+    let timeout = prompter
+        .timeout_duration()
+        .map(|timeout| timeout.as_millis() as i64);
+
+    Json::object([
+        ("label", Json::from(prompt.label)),
+        ("text", Json::from(prompt.text)),
+        ("responses", Json::Array(responses)),
+        ("multi", Json::from(multi)),
+        ("statement", Json::from(prompter.is_statement())),
+        ("timeout_ms", Json::from(timeout)),
+        ("ending", prompter.ending().map_or(Json::Null, ending_json)),
+    ])
+}
+
+// A JSON value as a variable, only strings, integers and booleans will do
+fn value(json: &Json) -> Option<Value> {
+    match json {
+        Json::String(s) => Some(Value::from(s.as_str())),
+        Json::Bool(b) => Some(Value::Bool(*b)),
+        _ => json.as_i64().map(Value::Int),
+    }
+}
+
+/// Starts a conversation from a request like
+/// `{"start": "LABEL", "variables": {"name": "Ada"}}`, both optional.
+pub fn start<'a>(script: Arc<Script<'a>>, request: &Json) -> Result<Prompter<'a>, Json> {
+    let script = match request.get("start") {
+        None | Some(Json::Null) => script,
+        Some(label) => {
+            let label = label
+                .as_str()
+                .ok_or_else(|| error_json("bad_request", "`start` takes a label"))?;
+            // Starting elsewhere takes a script of its own, the prompts are
+            // cheap to copy since their text stays borrowed
+            let started = Script::new(script.prompts().to_vec())
+                .expect("the script has prompts")
+                .with_start(label)
+                .ok_or_else(|| error_json("no_label", format!("no prompt is labelled {label}")))?;
+            Arc::new(started)
+        }
+    };
+
+    let mut prompter = Prompter::from_script(script);
+    match request.get("variables") {
+        None | Some(Json::Null) => {}
+        Some(Json::Object(variables)) => {
+            for (name, json) in variables {
+                let value = value(json).ok_or_else(|| {
+                    let message = format!("`{name}` isn't a string, integer or boolean");
+                    error_json("bad_request", message)
+                })?;
+                prompter = prompter.with_variable(name.as_str(), value);
+            }
+        }
+        Some(_) => return Err(error_json("bad_request", "`variables` takes an object")),
+    }
+    Ok(prompter)
+}
+
+// The offered response numbered `index` by `prompt_json`
+fn response_at(prompt: &RenderedPrompt, index: &Json) -> Result<ResponseId, Json> {
+    index
+        .as_u64()
+        .and_then(|index| prompt.responses.get(index as usize))
+        .map(|response| response.id)
+        .ok_or_else(|| error_json("bad_response", "that's not one of the answers on offer"))
+}
+
+/// Answers the current prompt with a request shaped like one of
+/// `{"response": 0}`, `{"responses": [0, 2]}` on multi-select questions,
+/// `{"text": "Ada"}` for typed in text, `{"timeout": true}` to take the
+/// default answer and `{"advance": true}` to move on from a statement.
+pub fn answer<'a>(prompter: Prompter<'a>, request: &Json) -> Result<Prompter<'a>, Json> {
+    if prompter.is_finished() {
+        return Err(prompter_error_json(&PrompterErr::NoMoreQ));
+    }
+    let prompt = prompter.render_next();
+
+    let reply = if let Some(index) = request.get("response") {
+        Reply::Choose(response_at(&prompt, index)?)
+    } else if let Some(indices) = request.get("responses") {
+        let indices = indices
+            .as_array()
+            .ok_or_else(|| error_json("bad_request", "`responses` takes a list of numbers"))?;
+        let ids = indices
+            .iter()
+            .map(|index| response_at(&prompt, index))
+            .collect::<Result<_, _>>()?;
+        Reply::ChooseMany(ids)
+    } else if let Some(text) = request.get("text") {
+        let text = text
+            .as_str()
+            .ok_or_else(|| error_json("bad_request", "`text` takes a string"))?;
+        Reply::Type(text.to_string())
+    } else if request.get("timeout").and_then(Json::as_bool) == Some(true) {
+        Reply::TimedOut
+    } else if request.get("advance").and_then(Json::as_bool) == Some(true) {
+        return prompter.advance().map_err(|err| prompter_error_json(&err));
+    } else {
+        return Err(error_json(
+            "bad_request",
+            "expected `response`, `responses`, `text`, `timeout` or `advance`",
+        ));
+    };
+
+    apply(prompter, reply).map_err(|err| prompter_error_json(&err))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use lexer::parse;
+    use prompter::{json::Json, Prompter};
+
+    use super::{answer, prompt_json};
+
+    #[test]
+    fn describes_and_answers_prompts() {
+        let data = read_to_string("./prompter/timeout_prompt.txt").unwrap();
+        let prompter = Prompter::new(parse(&data).unwrap()).unwrap();
+
+        assert_eq!(
+            prompt_json(&prompter).to_string(),
+            r#"{"label":"START","text":"Anyone there?","responses":[{"index":0,"text":"I'm here","input":false,"default":true},{"index":1,"text":"Go away","input":false,"default":false}],"multi":null,"statement":false,"timeout_ms":30000,"ending":null}"#
+        );
+
+        let request = |body: &str| Json::parse(body).unwrap();
+        let err = answer(prompter.clone(), &request(r#"{"response":5}"#))
+            .err()
+            .unwrap();
+        assert_eq!(err.get("code").unwrap().as_str(), Some("bad_response"));
+        let err = answer(prompter.clone(), &request(r#"{"advance":true}"#))
+            .err()
+            .unwrap();
+        assert_eq!(err.get("code").unwrap().as_str(), Some("not_a_statement"));
+
+        let prompter = answer(prompter, &request(r#"{"timeout":true}"#)).unwrap();
+        let prompter = answer(prompter, &request(r#"{"text":""}"#)).unwrap();
+        assert_eq!(prompter.render_next().text, "Hello friend!");
+        let prompter = answer(prompter, &request(r#"{"response":0}"#)).unwrap();
+
+        let ending = prompt_json(&prompter);
+        assert_eq!(
+            ending.get("ending").unwrap().to_string(),
+            r#"{"kind":"completed","outcome":null}"#
+        );
+        let err = answer(prompter, &request(r#"{"response":0}"#))
+            .err()
+            .unwrap();
+        assert_eq!(err.get("code").unwrap().as_str(), Some("finished"));
+    }
+}
@@ -0,0 +1,70 @@
+use std::{
+    io::{BufReader, Write},
+    process::{Command, Stdio},
+};
+
+use promptgen::{
+    lsp::rpc::{read_message, write_message},
+    prompter::json::Json,
+};
+
+#[test]
+fn speaks_lsp_over_stdio() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_promptgen-lsp"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut send = |message: &str| write_message(&mut stdin, &Json::parse(message).unwrap());
+
+    send(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#).unwrap();
+    let reply = read_message(&mut stdout).unwrap().unwrap();
+    let capabilities = reply.get("result").unwrap().get("capabilities").unwrap();
+    assert_eq!(capabilities.get("hoverProvider"), Some(&Json::Bool(true)));
+
+    send(r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#).unwrap();
+    send(
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.pg","languageId":"promptgen","version":1,"text":"> (START) \"Hi {who}\"\n< (START) \"Again\"\n"}}}"#,
+    )
+    .unwrap();
+    let published = read_message(&mut stdout).unwrap().unwrap();
+    assert_eq!(
+        published.get("method").unwrap().as_str(),
+        Some("textDocument/publishDiagnostics")
+    );
+    let diagnostics = published.get("params").unwrap().get("diagnostics").unwrap();
+    let message = diagnostics.as_array().unwrap()[0].get("message").unwrap();
+    assert_eq!(message.as_str(), Some("variable `who` is never set"));
+
+    // Text outside quotes can be anything, and labels are checked
+    send(
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///b.pg","languageId":"promptgen","version":1,"text":"> (START) \"Hi\"\nZoë was here\n< (TYPO) \"Bye\"\n"}}}"#,
+    )
+    .unwrap();
+    let published = read_message(&mut stdout).unwrap().unwrap();
+    let diagnostics = published.get("params").unwrap().get("diagnostics").unwrap();
+    let message = diagnostics.as_array().unwrap()[0].get("message").unwrap();
+    assert_eq!(message.as_str(), Some("no prompt is labelled `TYPO`"));
+
+    send(r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{"textDocument":{"uri":"file:///a.pg"},"position":{"line":1,"character":4}}}"#).unwrap();
+    let reply = read_message(&mut stdout).unwrap().unwrap();
+    assert_eq!(
+        reply
+            .get("result")
+            .unwrap()
+            .get("range")
+            .unwrap()
+            .to_string(),
+        r#"{"start":{"line":0,"character":3},"end":{"line":0,"character":8}}"#
+    );
+
+    send(r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#).unwrap();
+    let reply = read_message(&mut stdout).unwrap().unwrap();
+    assert_eq!(reply.get("result"), Some(&Json::Null));
+    send(r#"{"jsonrpc":"2.0","method":"exit"}"#).unwrap();
+    stdin.flush().unwrap();
+
+    assert!(child.wait().unwrap().success());
+}
@@ -0,0 +1,126 @@
+"use strict";
+
+// Plays the scenarios `player.rs` sends on stdin through the page's player,
+// the way `Scenario::run` plays them through `Prompter`, printing a line per
+// scenario like `promptgen test`
+var fs = require("fs");
+var path = require("path");
+var player = require(path.join(__dirname, "..", "src", "html", "player.js"));
+
+function fail(line, message) {
+  return { line: line, message: message };
+}
+
+function describe(session) {
+  var prompt = session.current();
+  var text = JSON.stringify(player.render(prompt.text, session.vars));
+  return prompt.label !== null ? "> (" + prompt.label + ") " + text : "> " + text;
+}
+
+function start(script, scenario) {
+  var played = script;
+  if (scenario.start !== null) {
+    var idx = script.prompts.findIndex(function (prompt) {
+      return prompt.label === scenario.start;
+    });
+    if (idx < 0) throw fail(scenario.line, "no prompt is labelled " + scenario.start);
+    played = Object.assign({}, script, { start: idx });
+  }
+
+  var session = new player.Session(played, new player.SeededRandom(scenario.seed));
+  scenario.variables.forEach(function (variable) {
+    session.withVariable(variable[0], variable[1]);
+  });
+  return session;
+}
+
+// Answers with a step, throwing the error the player turns it down with
+function answer(session, step) {
+  if ("type" in step) return session.answerInput(step.type);
+  if ("timeout" in step) return session.timeout();
+
+  var responses = session.current().responses;
+  var offered = session.offered();
+  var indices = step.choose.map(function (text) {
+    var found = offered.filter(function (idx) {
+      return player.render(responses[idx].text, session.vars) === text;
+    });
+    if (found.length === 0) {
+      var err = new Error("bad_response");
+      err.code = "bad_response";
+      throw err;
+    }
+    return found[0];
+  });
+  if (session.current().multi === null && indices.length === 1) {
+    return session.answer(indices[0]);
+  }
+  return session.answerMany(indices);
+}
+
+function run(script, scenario) {
+  var session = start(script, scenario);
+  var steps = scenario.steps;
+
+  for (var at = 0; at < steps.length; at++) {
+    var line = steps[at][0];
+    var step = steps[at][1];
+
+    if ("expect" in step) {
+      var prompt = session.current();
+      var labelMatches = step.expect.label === null || prompt.label === step.expect.label;
+      if (!labelMatches || player.render(prompt.text, session.vars) !== step.expect.text) {
+        throw fail(line, "the script is at " + describe(session));
+      }
+      // Statements are moved on from as soon as they're shown
+      if (session.isStatement()) session.advance();
+    } else if ("end" in step) {
+      var ending = session.ending;
+      if (ending === null) throw fail(line, "expected the end, the script is at " + describe(session));
+      var outcome = ending.kind === "completed" ? ending.outcome : null;
+      var kindMatches = step.end.kind === null || step.end.kind === ending.kind;
+      var outcomeMatches = step.end.outcome === null || step.end.outcome === outcome;
+      if (!kindMatches || !outcomeMatches) {
+        throw fail(line, "the conversation ended " + ending.kind + (outcome !== null ? " " + outcome : ""));
+      }
+    } else {
+      var refused = at + 1 < steps.length && "refused" in steps[at + 1][1] ? steps[at + 1] : null;
+      var code = null;
+      try {
+        answer(session, step);
+      } catch (err) {
+        if (err.code === undefined) throw err;
+        code = err.code;
+      }
+
+      if (refused !== null) {
+        at += 1;
+        if (code === null) {
+          throw fail(refused[0], "expected the answer turned down with " + refused[1].refused + ", it was taken");
+        }
+        if (code !== refused[1].refused) {
+          throw fail(refused[0], "expected " + refused[1].refused + ", the answer was turned down with " + code);
+        }
+      } else if (code !== null) {
+        throw fail(line, "the answer was turned down: " + code);
+      }
+    }
+  }
+}
+
+var files = JSON.parse(fs.readFileSync(0, "utf8"));
+var failed = 0;
+files.forEach(function (file) {
+  file.scenarios.forEach(function (scenario) {
+    try {
+      run(file.script, scenario);
+      console.log("ok     " + file.path + ": " + scenario.name);
+    } catch (err) {
+      if (err.line === undefined) throw err;
+      failed += 1;
+      console.log("FAILED " + file.path + ": " + scenario.name);
+      console.log("  --> " + file.path + ":" + err.line + ": " + err.message);
+    }
+  });
+});
+process.exitCode = failed === 0 ? 0 : 1;
@@ -0,0 +1,134 @@
+//! Plays every `.pgtest` scenario through the exported page's player with
+//! node, which has to follow the same rules as `Prompter`. Skipped when node
+//! isn't installed.
+
+use std::{
+    fs::{read_dir, read_to_string},
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    sync::Arc,
+};
+
+use promptgen::{
+    html::script_json,
+    lexer::parse,
+    prompter::{
+        json::Json,
+        scenario::{Answer, Scenario, Step, TestFile},
+        script::Script,
+        variables::Value,
+    },
+};
+
+fn value_json(value: &Value) -> Json {
+    match value {
+        Value::Str(s) => Json::from(s.as_str()),
+        Value::Int(n) => Json::from(*n),
+        Value::Bool(b) => Json::from(*b),
+    }
+}
+
+fn step_json(step: &Step) -> Json {
+    match step {
+        Step::Expect { label, text } => Json::object([(
+            "expect",
+            Json::object([
+                ("label", Json::from(label.clone())),
+                ("text", Json::from(text.as_str())),
+            ]),
+        )]),
+        Step::Answer(Answer::Choose(texts)) => Json::object([(
+            "choose",
+            Json::Array(texts.iter().map(|text| Json::from(text.as_str())).collect()),
+        )]),
+        Step::Answer(Answer::Type(text)) => Json::object([("type", Json::from(text.as_str()))]),
+        Step::Answer(Answer::TimedOut) => Json::object([("timeout", Json::Bool(true))]),
+        Step::Refused(code) => Json::object([("refused", Json::from(code.as_str()))]),
+        Step::End { kind, outcome } => Json::object([(
+            "end",
+            Json::object([
+                ("kind", Json::from(kind.clone())),
+                ("outcome", Json::from(outcome.clone())),
+            ]),
+        )]),
+    }
+}
+
+fn scenario_json(scenario: &Scenario) -> Json {
+    let setup = &scenario.setup;
+    let variables = setup
+        .variables
+        .iter()
+        .map(|(name, value)| Json::Array(vec![Json::from(name.as_str()), value_json(value)]));
+    let steps = scenario
+        .steps
+        .iter()
+        .map(|(line, step)| Json::Array(vec![Json::from(*line), step_json(step)]));
+
+    Json::object([
+        ("name", Json::from(scenario.name.as_str())),
+        ("line", Json::from(scenario.line)),
+        // Seeds go as text, they don't all fit a JavaScript number
+        ("seed", Json::from(setup.seed.to_string())),
+        ("start", Json::from(setup.start.clone())),
+        ("variables", Json::Array(variables.collect())),
+        ("steps", Json::Array(steps.collect())),
+    ])
+}
+
+#[test]
+fn player_plays_the_scenarios_like_prompter() {
+    if Command::new("node").arg("--version").output().is_err() {
+        eprintln!("skipping, node isn't installed");
+        return;
+    }
+
+    let mut paths: Vec<_> = read_dir("./prompter")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "pgtest"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    let (mut files, mut scenarios) = (vec![], 0);
+    for path in &paths {
+        let file = TestFile::parse(&read_to_string(path).unwrap()).unwrap();
+        let script_path = Path::new(path).with_file_name(file.script.as_ref().unwrap());
+        let data = read_to_string(script_path).unwrap();
+        let script = Arc::new(Script::new(parse(&data).unwrap()).unwrap());
+
+        // The scenarios have to hold in Rust for the player to be held to them
+        for scenario in &file.scenarios {
+            let played = scenario.run(Arc::clone(&script));
+            assert_eq!(played, Ok(()), "{}: {}", path.display(), scenario.name);
+        }
+        scenarios += file.scenarios.len();
+        files.push(Json::object([
+            ("path", Json::from(path.display().to_string())),
+            ("script", script_json(&script)),
+            (
+                "scenarios",
+                Json::Array(file.scenarios.iter().map(scenario_json).collect()),
+            ),
+        ]));
+    }
+
+    let mut node = Command::new("node")
+        .arg("./tests/player.js")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = node.stdin.take().unwrap();
+    stdin
+        .write_all(Json::Array(files).to_string().as_bytes())
+        .unwrap();
+    drop(stdin);
+
+    let output = node.wait_with_output().unwrap();
+    let out = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{out}");
+    assert_eq!(out.matches("ok     ").count(), scenarios, "{out}");
+}
@@ -0,0 +1,145 @@
+#![cfg(feature = "server")]
+
+use std::{
+    fs::read_to_string,
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+use promptgen::{prompter::json::Json, server::Server};
+
+// Starts a server with the test scripts on a free port
+fn start() -> SocketAddr {
+    let mut server = Server::new(Duration::from_secs(60));
+    for name in ["simple_prompt", "multi_prompt", "story_prompt"] {
+        let data = read_to_string(format!("./prompter/{name}.txt")).unwrap();
+        server.load(name, data).unwrap();
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || server.serve(listener));
+    addr
+}
+
+// A plain HTTP/1.1 request, giving the status and JSON body
+fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Json) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "{method} {path} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\n\r\n{body}",
+        body.len()
+    )
+    .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+    let body = match body {
+        "" => Json::Null,
+        body => Json::parse(body).unwrap(),
+    };
+    (status, body)
+}
+
+fn prompt_text(body: &Json) -> &str {
+    body.get("prompt")
+        .unwrap()
+        .get("text")
+        .unwrap()
+        .as_str()
+        .unwrap()
+}
+
+#[test]
+fn plays_a_conversation_over_http() {
+    let addr = start();
+
+    let (status, body) = request(addr, "POST", "/sessions", r#"{"script":"simple_prompt"}"#);
+    assert_eq!(status, 201);
+    assert_eq!(prompt_text(&body), "Are you a human?");
+    let id = body.get("id").unwrap().as_str().unwrap().to_string();
+
+    let answer = format!("/sessions/{id}/answer");
+    let (status, body) = request(addr, "POST", &answer, r#"{"response":1}"#);
+    assert_eq!(status, 200);
+    assert_eq!(prompt_text(&body), "That's very weird! Care to try again?");
+
+    let (_, body) = request(addr, "POST", &answer, r#"{"response":0}"#);
+    assert_eq!(prompt_text(&body), "Are you a human?");
+    let (_, body) = request(addr, "POST", &answer, r#"{"response":0}"#);
+    assert_eq!(prompt_text(&body), "Nice! Glad to meet you human!");
+    let ending = body.get("prompt").unwrap().get("ending").unwrap();
+    assert_eq!(ending.to_string(), r#"{"kind":"completed","outcome":null}"#);
+
+    let (status, body) = request(addr, "POST", &answer, r#"{"response":0}"#);
+    assert_eq!(status, 422);
+    let code = body.get("error").unwrap().get("code").unwrap();
+    assert_eq!(code.as_str(), Some("finished"));
+
+    let (status, body) = request(addr, "GET", &format!("/sessions/{id}/transcript"), "");
+    assert_eq!(status, 200);
+    let entries = body.get("transcript").unwrap().as_array().unwrap();
+    assert_eq!(entries.len(), 7);
+    assert_eq!(
+        entries[1].to_string(),
+        r#"{"type":"answer","index":1,"text":"No"}"#
+    );
+}
+
+#[test]
+fn picks_several_answers_and_advances_statements() {
+    let addr = start();
+
+    let (_, body) = request(addr, "POST", "/sessions", r#"{"script":"multi_prompt"}"#);
+    let id = body.get("id").unwrap().as_str().unwrap().to_string();
+    let multi = body.get("prompt").unwrap().get("multi").unwrap();
+    assert_eq!(multi.to_string(), r#"{"min":1,"max":2}"#);
+
+    let answer = format!("/sessions/{id}/answer");
+    let (status, _) = request(addr, "POST", &answer, r#"{"responses":[0,1,2]}"#);
+    assert_eq!(status, 422);
+    let (status, body) = request(addr, "POST", &answer, r#"{"responses":[0,2]}"#);
+    assert_eq!(status, 200);
+    assert_eq!(prompt_text(&body), "Let's party!");
+
+    let (_, body) = request(addr, "POST", "/sessions", r#"{"script":"story_prompt"}"#);
+    let id = body.get("id").unwrap().as_str().unwrap().to_string();
+    assert_eq!(
+        body.get("prompt").unwrap().get("statement").unwrap(),
+        &Json::Bool(true)
+    );
+
+    let answer = format!("/sessions/{id}/answer");
+    request(addr, "POST", &answer, r#"{"advance":true}"#);
+    let (_, body) = request(addr, "POST", &answer, r#"{"advance":true}"#);
+    assert_eq!(prompt_text(&body), "Do you want to go to the party?");
+    let (status, _) = request(addr, "POST", &answer, r#"{"advance":true}"#);
+    assert_eq!(status, 422);
+    let (status, _) = request(addr, "POST", &answer, r#"{"dance":true}"#);
+    assert_eq!(status, 400);
+}
+
+#[test]
+fn reports_missing_sessions_and_routes() {
+    let addr = start();
+
+    let (status, body) = request(addr, "GET", "/sessions/nope", "");
+    assert_eq!(status, 404);
+    let code = body.get("error").unwrap().get("code").unwrap();
+    assert_eq!(code.as_str(), Some("no_session"));
+
+    assert_eq!(request(addr, "GET", "/", "").0, 404);
+    assert_eq!(request(addr, "DELETE", "/scripts", "").0, 405);
+
+    let (status, body) = request(addr, "GET", "/scripts", "");
+    assert_eq!(status, 200);
+    assert_eq!(
+        body.to_string(),
+        r#"{"scripts":["multi_prompt","simple_prompt","story_prompt"]}"#
+    );
+}